@@ -0,0 +1,14 @@
+#version 460 core
+
+in vec2 v_Uv;
+
+out vec4 out_color;
+
+uniform sampler2D u_Source;
+uniform sampler2D u_Bloom;
+uniform float u_Intensity;
+
+void main() {
+    vec3 color = texture(u_Source, v_Uv).rgb + texture(u_Bloom, v_Uv).rgb * u_Intensity;
+    out_color = vec4(color, 1.0);
+}
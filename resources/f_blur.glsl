@@ -0,0 +1,23 @@
+#version 460 core
+
+in vec2 v_Uv;
+
+out vec4 out_color;
+
+uniform sampler2D u_Source;
+// One texel along the blur axis.
+uniform vec2 u_Direction;
+
+// 9 tap gaussian folded into 5 bilinear fetches.
+const float OFFSETS[3] = float[](0.0, 1.3846153846, 3.2307692308);
+const float WEIGHTS[3] = float[](0.2270270270, 0.3162162162, 0.0702702703);
+
+void main() {
+    vec3 color = texture(u_Source, v_Uv).rgb * WEIGHTS[0];
+    for (int i = 1; i < 3; i++) {
+        vec2 offset = u_Direction * OFFSETS[i];
+        color += texture(u_Source, v_Uv + offset).rgb * WEIGHTS[i];
+        color += texture(u_Source, v_Uv - offset).rgb * WEIGHTS[i];
+    }
+    out_color = vec4(color, 1.0);
+}
@@ -0,0 +1,16 @@
+#version 460 core
+
+in vec2 v_Uv;
+
+out vec4 out_color;
+
+uniform sampler2D u_Source;
+uniform float u_Threshold;
+
+void main() {
+    vec3 color = texture(u_Source, v_Uv).rgb;
+    float brightness = max(color.r, max(color.g, color.b));
+    // Scaling instead of subtracting keeps the hue of the bright parts.
+    float contribution = max(brightness - u_Threshold, 0.0) / max(brightness, 1e-4);
+    out_color = vec4(color * contribution, 1.0);
+}
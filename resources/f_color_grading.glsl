@@ -0,0 +1,18 @@
+#version 460 core
+
+in vec2 v_Uv;
+
+out vec4 out_color;
+
+uniform sampler2D u_Source;
+uniform sampler3D u_Lut;
+uniform float u_LutSize;
+uniform float u_Strength;
+
+void main() {
+    vec3 color = clamp(texture(u_Source, v_Uv).rgb, 0.0, 1.0);
+    // Samples the centers of the first and last texels for 0 and 1.
+    vec3 coords = color * ((u_LutSize - 1.0) / u_LutSize) + 0.5 / u_LutSize;
+    vec3 graded = texture(u_Lut, coords).rgb;
+    out_color = vec4(mix(color, graded, u_Strength), 1.0);
+}
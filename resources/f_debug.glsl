@@ -0,0 +1,20 @@
+#version 460 core
+
+in vec4 v_Color;
+flat in float v_DepthTest;
+
+out vec4 out_color;
+
+// Depth of the scene, the same size as the screen.
+uniform sampler2D u_Depth;
+
+void main() {
+    if (v_DepthTest > 0.5) {
+        float scene = texelFetch(u_Depth, ivec2(gl_FragCoord.xy), 0).r;
+        // A small offset keeps lines lying on surfaces visible.
+        if (gl_FragCoord.z > scene + 0.00002) {
+            discard;
+        }
+    }
+    out_color = v_Color;
+}
@@ -0,0 +1,48 @@
+#version 460 core
+
+in vec2 v_Uv;
+
+out vec4 out_color;
+
+uniform sampler2D u_Source;
+// Size of one texel of the source.
+uniform vec2 u_Texel;
+
+const float REDUCE_MIN = 1.0 / 128.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float SPAN_MAX = 8.0;
+const vec3 LUMA = vec3(0.299, 0.587, 0.114);
+
+// Classic FXAA by Timothy Lottes: blurs along the edge found from the luma
+// of the four diagonal neighbours.
+void main() {
+    float lumaNW = dot(texture(u_Source, v_Uv + vec2(-1.0, -1.0) * u_Texel).rgb, LUMA);
+    float lumaNE = dot(texture(u_Source, v_Uv + vec2(1.0, -1.0) * u_Texel).rgb, LUMA);
+    float lumaSW = dot(texture(u_Source, v_Uv + vec2(-1.0, 1.0) * u_Texel).rgb, LUMA);
+    float lumaSE = dot(texture(u_Source, v_Uv + vec2(1.0, 1.0) * u_Texel).rgb, LUMA);
+    vec3 center = texture(u_Source, v_Uv).rgb;
+    float lumaM = dot(center, LUMA);
+    float lumaMin = min(lumaM, min(min(lumaNW, lumaNE), min(lumaSW, lumaSE)));
+    float lumaMax = max(lumaM, max(max(lumaNW, lumaNE), max(lumaSW, lumaSE)));
+
+    vec2 direction = vec2(
+        -((lumaNW + lumaNE) - (lumaSW + lumaSE)),
+        (lumaNW + lumaSW) - (lumaNE + lumaSE)
+    );
+    float reduce = max((lumaNW + lumaNE + lumaSW + lumaSE) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+    direction = clamp(direction * scale, vec2(-SPAN_MAX), vec2(SPAN_MAX)) * u_Texel;
+
+    vec3 near = 0.5 * (
+        texture(u_Source, v_Uv + direction * (1.0 / 3.0 - 0.5)).rgb +
+        texture(u_Source, v_Uv + direction * (2.0 / 3.0 - 0.5)).rgb
+    );
+    vec3 far = near * 0.5 + 0.25 * (
+        texture(u_Source, v_Uv + direction * -0.5).rgb +
+        texture(u_Source, v_Uv + direction * 0.5).rgb
+    );
+    // Sampling too far crossed another edge, the narrower blur is safer.
+    float lumaFar = dot(far, LUMA);
+    vec3 color = (lumaFar < lumaMin || lumaFar > lumaMax) ? near : far;
+    out_color = vec4(color, 1.0);
+}
@@ -0,0 +1,13 @@
+#version 460 core
+
+in vec2 v_Uv;
+
+out vec4 out_color;
+
+uniform sampler2D u_Source;
+uniform float u_Gamma;
+
+void main() {
+    vec3 color = max(texture(u_Source, v_Uv).rgb, 0.0);
+    out_color = vec4(pow(color, vec3(1.0 / u_Gamma)), 1.0);
+}
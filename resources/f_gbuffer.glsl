@@ -0,0 +1,16 @@
+
+// G-buffer pass of the deferred path, appended to lighting.glsl and
+// material.glsl.
+
+layout (location = 0) out vec4 out_baseColor;
+layout (location = 1) out vec4 out_normal;
+layout (location = 2) out vec4 out_emissive;
+layout (location = 3) out vec4 out_shading;
+
+void main() {
+    Surface surface = materialSurface();
+    out_baseColor = vec4(surface.baseColor, surface.metallic);
+    out_normal = vec4(surface.normal, surface.roughness);
+    out_emissive = vec4(surface.emissive, surface.occlusion);
+    out_shading = vec4(float(surface.model), surface.phong);
+}
@@ -0,0 +1,27 @@
+#version 460 core
+
+out vec4 out_color;
+
+// Fragments drawn per pixel in the red channel, the size of the screen.
+uniform sampler2D u_Source;
+// Count shown white.
+uniform float u_MaxCount;
+
+void main() {
+    float count = texelFetch(u_Source, ivec2(gl_FragCoord.xy), 0).r;
+    if (count < 0.5) {
+        out_color = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+    // Blue for one fragment, through green, yellow and red, to white.
+    const vec3 ramp[5] = vec3[](
+        vec3(0.0, 0.0, 1.0),
+        vec3(0.0, 1.0, 0.0),
+        vec3(1.0, 1.0, 0.0),
+        vec3(1.0, 0.0, 0.0),
+        vec3(1.0, 1.0, 1.0)
+    );
+    float t = clamp((count - 1.0) / (u_MaxCount - 1.0), 0.0, 1.0) * 4.0;
+    int i = min(int(t), 3);
+    out_color = vec4(mix(ramp[i], ramp[i + 1], t - float(i)), 1.0);
+}
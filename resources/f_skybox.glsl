@@ -0,0 +1,13 @@
+#version 460 core
+
+in vec3 v_Direction;
+
+out vec4 out_color;
+
+uniform samplerCube u_Sky;
+uniform float u_Intensity;
+
+void main() {
+    vec3 color = textureLod(u_Sky, normalize(v_Direction), 0.0).rgb * u_Intensity;
+    out_color = vec4(color, 1.0);
+}
@@ -0,0 +1,22 @@
+#version 460 core
+
+in vec2 v_Uv;
+in vec4 v_Color;
+
+out vec4 out_color;
+
+uniform sampler2D u_Atlas;
+// Whether the atlas holds distances to the outline rather than coverage.
+uniform bool u_DistanceField;
+
+void main() {
+    float value = texture(u_Atlas, v_Uv).r;
+    float alpha = value;
+    if (u_DistanceField) {
+        // The edge is at one half; blending over about a pixel either side
+        // keeps it smooth at any scale.
+        float width = max(fwidth(value), 1e-4);
+        alpha = smoothstep(0.5 - width, 0.5 + width, value);
+    }
+    out_color = vec4(v_Color.rgb, v_Color.a * alpha);
+}
@@ -0,0 +1,18 @@
+#version 460 core
+
+in vec2 v_Uv;
+
+out vec4 out_color;
+
+uniform sampler2D u_Source;
+uniform float u_Exposure;
+
+// Curve fit of the ACES reference tone mapping by Krzysztof Narkowicz.
+vec3 aces(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+void main() {
+    vec3 color = texture(u_Source, v_Uv).rgb * u_Exposure;
+    out_color = vec4(aces(color), 1.0);
+}
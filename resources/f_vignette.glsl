@@ -0,0 +1,17 @@
+#version 460 core
+
+in vec2 v_Uv;
+
+out vec4 out_color;
+
+uniform sampler2D u_Source;
+uniform float u_Radius;
+uniform float u_Softness;
+
+void main() {
+    vec3 color = texture(u_Source, v_Uv).rgb;
+    // Distance from the center, 1 in the corners.
+    float distance = length(v_Uv - 0.5) * sqrt(2.0);
+    float vignette = 1.0 - smoothstep(u_Radius, u_Radius + u_Softness, distance);
+    out_color = vec4(color * vignette, 1.0);
+}
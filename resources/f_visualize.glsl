@@ -0,0 +1,58 @@
+#version 460 core
+
+in vec3 v_Position;
+in vec3 v_Normal;
+in vec3 v_Color;
+flat in uint v_Entity;
+
+out vec4 out_color;
+
+// What to show, in the order of the RenderMode views.
+const uint WIREFRAME = 0u;
+const uint WIREFRAME_OVERLAY = 1u;
+const uint NORMALS = 2u;
+const uint DEPTH = 3u;
+const uint INSTANCE_ID = 4u;
+const uint OVERDRAW = 5u;
+
+uniform uint u_Mode;
+uniform mat4 view;
+uniform float u_Near;
+// Distance shown white in the depth view.
+uniform float u_DepthRange;
+
+// Spreads consecutive ids over distinct colors.
+vec3 idColor(uint id) {
+    uint h = id * 0x9E3779B9u;
+    h ^= h >> 16;
+    h *= 0x85EBCA6Bu;
+    h ^= h >> 13;
+    return vec3((h >> 16) & 0xFFu, (h >> 8) & 0xFFu, h & 0xFFu) / 255.0;
+}
+
+void main() {
+    vec3 color;
+    switch (u_Mode) {
+    case WIREFRAME:
+        color = v_Color;
+        break;
+    case WIREFRAME_OVERLAY:
+        color = vec3(0.01);
+        break;
+    case NORMALS:
+        color = normalize(v_Normal) * 0.5 + 0.5;
+        break;
+    case DEPTH:
+        float depth = -(view * vec4(v_Position, 1.0)).z;
+        color = vec3(clamp((depth - u_Near) / (u_DepthRange - u_Near), 0.0, 1.0));
+        break;
+    case INSTANCE_ID:
+        color = idColor(v_Entity);
+        break;
+    default:
+        // Blended additively, so the red channel counts the fragments.
+        color = vec3(1.0, 0.0, 0.0);
+        break;
+    }
+    out_color = vec4(color, 1.0);
+}
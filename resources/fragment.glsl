@@ -1,8 +1,14 @@
-#version 460 core
+
+// Forward shading, appended to lighting.glsl and material.glsl.
 
 out vec4 out_color;
-in vec3 ourColor;
 
 void main() {
-    out_color = vec4(ourColor, 1.0);
+    Surface surface = materialSurface();
+    vec3 toView = normalize(u_ViewPos - v_Position);
+    vec3 color = ambientLight(surface, toView);
+    for (uint i = 0; i < u_LightCount; i++) {
+        color += directLight(lights[i], surface, toView);
+    }
+    out_color = vec4(color, 1.0);
 }
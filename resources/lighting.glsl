@@ -0,0 +1,196 @@
+#version 460 core
+
+// Shading shared by the forward and deferred paths. Shaders using it are
+// appended to this file.
+
+const uint DIRECTIONAL = 0;
+const uint POINT = 1;
+const uint SPOT = 2;
+const uint PBR = 0;
+const uint BLINN_PHONG = 1;
+const int CASCADES = 4;
+const float PI = 3.14159265359;
+
+struct Light {
+    vec3 position;
+    uint kind;
+    vec3 direction;
+    float range;
+    vec3 color;
+    float cosInner;
+    float cosOuter;
+    int shadow;
+};
+
+layout (std430, binding = 0) readonly buffer Lights {
+    Light lights[];
+};
+
+// What the lighting needs to know about a point of a surface.
+struct Surface {
+    vec3 position;
+    vec3 normal;
+    // Normal of the geometry itself, before normal mapping.
+    vec3 geometryNormal;
+    vec3 baseColor;
+    float metallic;
+    float roughness;
+    float occlusion;
+    vec3 emissive;
+    // Shading model of the material, PBR or BLINN_PHONG.
+    uint model;
+    // Ambient, specular and shininess of Blinn-Phong materials.
+    vec3 phong;
+};
+
+uniform uint u_LightCount;
+uniform vec3 u_ViewPos;
+uniform vec3 u_ViewDir;
+
+uniform sampler2DArrayShadow u_ShadowMaps;
+uniform mat4 u_ShadowMatrices[8];
+uniform float u_CascadeSplits[CASCADES];
+uniform float u_DepthBias;
+uniform float u_NormalBias;
+uniform uint u_PcfRadius;
+
+// The environment itself, for sharp reflections.
+uniform samplerCube u_Environment;
+uniform samplerCube u_Irradiance;
+uniform samplerCube u_Prefiltered;
+uniform sampler2D u_Brdf;
+uniform float u_PrefilteredLevels;
+uniform float u_IblIntensity;
+
+// Fraction of the light reaching `position`.
+float shadow(Light light, vec3 position, vec3 normal) {
+    if (light.shadow < 0) {
+        return 1.0;
+    }
+    int layer = light.shadow;
+    if (light.kind == DIRECTIONAL) {
+        float depth = dot(position - u_ViewPos, u_ViewDir);
+        int cascade = 0;
+        while (cascade < CASCADES && depth > u_CascadeSplits[cascade]) {
+            cascade++;
+        }
+        if (cascade == CASCADES) {
+            return 1.0;
+        }
+        layer += cascade;
+    }
+
+    vec4 clip = u_ShadowMatrices[layer] * vec4(position + normal * u_NormalBias, 1.0);
+    vec3 coords = clip.xyz / clip.w * 0.5 + 0.5;
+    if (any(lessThan(coords, vec3(0.0))) || any(greaterThan(coords, vec3(1.0)))) {
+        return 1.0;
+    }
+
+    // Percentage closer filtering over a square of texels.
+    vec2 texel = 1.0 / vec2(textureSize(u_ShadowMaps, 0).xy);
+    int radius = int(u_PcfRadius);
+    float lit = 0.0;
+    for (int x = -radius; x <= radius; x++) {
+        for (int y = -radius; y <= radius; y++) {
+            vec2 uv = coords.xy + vec2(x, y) * texel;
+            lit += texture(u_ShadowMaps, vec4(uv, layer, coords.z - u_DepthBias));
+        }
+    }
+    float side = float(2 * radius + 1);
+    return lit / (side * side);
+}
+
+float distributionGgx(float nDotH, float roughness) {
+    float a2 = roughness * roughness * roughness * roughness;
+    float d = nDotH * nDotH * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+float geometrySmith(float nDotV, float nDotL, float roughness) {
+    float r = roughness + 1.0;
+    float k = r * r / 8.0;
+    return nDotV / (nDotV * (1.0 - k) + k) * nDotL / (nDotL * (1.0 - k) + k);
+}
+
+vec3 fresnelSchlick(float cosTheta, vec3 f0) {
+    return f0 + (1.0 - f0) * pow(1.0 - cosTheta, 5.0);
+}
+
+// Rough surfaces reflect less at grazing angles, which matters for the
+// environment lighting, averaged over the whole lobe.
+vec3 fresnelSchlickRoughness(float cosTheta, vec3 f0, float roughness) {
+    return f0 + (max(vec3(1.0 - roughness), f0) - f0) * pow(1.0 - cosTheta, 5.0);
+}
+
+// Dielectrics reflect about 4% at normal incidence, metals their color.
+vec3 reflectance(Surface surface) {
+    return mix(vec3(0.04), surface.baseColor, surface.metallic);
+}
+
+// Light reflected towards the viewer from one light, with Cook-Torrance and
+// the GGX distribution, or Blinn-Phong.
+vec3 directLight(Light light, Surface surface, vec3 toView) {
+    vec3 toLight = -light.direction;
+    float attenuation = 1.0;
+    if (light.kind != DIRECTIONAL) {
+        vec3 offset = light.position - surface.position;
+        float distance = max(length(offset), 1e-4);
+        toLight = offset / distance;
+        // Inverse square falloff windowed to reach zero at the range.
+        float window = clamp(1.0 - pow(distance / light.range, 4.0), 0.0, 1.0);
+        attenuation = window * window / (distance * distance + 1.0);
+        if (light.kind == SPOT) {
+            float cosAngle = dot(-toLight, light.direction);
+            attenuation *= smoothstep(light.cosOuter, light.cosInner, cosAngle);
+        }
+    }
+
+    float nDotL = dot(surface.normal, toLight);
+    if (nDotL <= 0.0 || attenuation <= 0.0) {
+        return vec3(0.0);
+    }
+    attenuation *= shadow(light, surface.position, surface.geometryNormal);
+
+    if (surface.model == BLINN_PHONG) {
+        vec3 halfway = normalize(toLight + toView);
+        float nDotH = max(dot(surface.normal, halfway), 0.0);
+        float specular = pow(nDotH, surface.phong.z) * surface.phong.y;
+        return (surface.baseColor * nDotL + specular) * light.color * attenuation;
+    }
+
+    float nDotV = max(dot(surface.normal, toView), 1e-4);
+    vec3 halfway = normalize(toLight + toView);
+    float nDotH = max(dot(surface.normal, halfway), 0.0);
+    vec3 fresnel = fresnelSchlick(max(dot(halfway, toView), 0.0), reflectance(surface));
+    vec3 specular = distributionGgx(nDotH, surface.roughness)
+        * geometrySmith(nDotV, nDotL, surface.roughness) * fresnel
+        / (4.0 * nDotV * nDotL + 1e-4);
+    vec3 diffuse = (1.0 - fresnel) * (1.0 - surface.metallic) * surface.baseColor / PI;
+    // Light colors are scaled by PI, so a white diffuse surface facing a
+    // light shows the light's color.
+    return (diffuse + specular) * light.color * attenuation * nDotL * PI;
+}
+
+// Split sum environment lighting and emission. Blinn-Phong surfaces get a
+// constant ambient term instead.
+vec3 ambientLight(Surface surface, vec3 toView) {
+    if (surface.model == BLINN_PHONG) {
+        return surface.baseColor * surface.phong.x + surface.emissive;
+    }
+    float nDotV = max(dot(surface.normal, toView), 1e-4);
+    vec3 fresnel = fresnelSchlickRoughness(nDotV, reflectance(surface), surface.roughness);
+    vec3 irradiance = texture(u_Irradiance, surface.normal).rgb;
+    vec3 diffuse = (1.0 - fresnel) * (1.0 - surface.metallic) * surface.baseColor * irradiance;
+    vec3 reflected = reflect(-toView, surface.normal);
+    float level = surface.roughness * (u_PrefilteredLevels - 1.0);
+    vec3 prefiltered = textureLod(u_Prefiltered, reflected, level).rgb;
+    if (level < 1.0) {
+        // The first prefiltered level is a low resolution copy of the
+        // environment, mirror-like surfaces reflect the original.
+        vec3 sharp = textureLod(u_Environment, reflected, 0.0).rgb;
+        prefiltered = mix(sharp, textureLod(u_Prefiltered, reflected, 1.0).rgb, level);
+    }
+    vec2 brdf = texture(u_Brdf, vec2(nDotV, surface.roughness)).rg;
+    vec3 specular = prefiltered * (fresnel * brdf.x + brdf.y);
+    return (diffuse + specular) * surface.occlusion * u_IblIntensity + surface.emissive;
+}
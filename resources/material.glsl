@@ -0,0 +1,73 @@
+
+// Material lookups shared by the forward and G-buffer fragment shaders.
+
+struct Material {
+    vec4 baseColor;
+    vec3 emissive;
+    float metallic;
+    float roughness;
+    float occlusionStrength;
+    float normalScale;
+    uint model;
+    float ambient;
+    float specular;
+    float shininess;
+};
+
+layout (std430, binding = 1) readonly buffer Materials {
+    Material materials[];
+};
+
+in vec3 v_Position;
+in vec3 v_Normal;
+in vec3 v_Color;
+in vec2 v_Uv;
+flat in uint v_Material;
+
+uniform sampler2D u_BaseColor;
+uniform sampler2D u_MetallicRoughness;
+uniform sampler2D u_NormalMap;
+uniform sampler2D u_Occlusion;
+uniform sampler2D u_Emissive;
+
+// Applies a tangent space normal from the normal map, building the tangent
+// frame from screen space derivatives so meshes need no tangents.
+vec3 mapNormal(vec3 normal, float scale) {
+    vec3 mapped = texture(u_NormalMap, v_Uv).xyz * 2.0 - 1.0;
+    mapped.xy *= scale;
+    vec3 dp1 = dFdx(v_Position);
+    vec3 dp2 = dFdy(v_Position);
+    vec2 duv1 = dFdx(v_Uv);
+    vec2 duv2 = dFdy(v_Uv);
+    vec3 dp2perp = cross(dp2, normal);
+    vec3 dp1perp = cross(normal, dp1);
+    vec3 tangent = dp2perp * duv1.x + dp1perp * duv2.x;
+    vec3 bitangent = dp2perp * duv1.y + dp1perp * duv2.y;
+    float size = max(dot(tangent, tangent), dot(bitangent, bitangent));
+    // Without UVs there is no frame to map the normal in.
+    if (size < 1e-16) {
+        return normal;
+    }
+    float scaleFrame = inversesqrt(size);
+    mat3 frame = mat3(tangent * scaleFrame, bitangent * scaleFrame, normal);
+    return normalize(frame * mapped);
+}
+
+// The surface of the fragment, with the material and its maps applied.
+Surface materialSurface() {
+    Material material = materials[v_Material];
+    vec4 metallicRoughness = texture(u_MetallicRoughness, v_Uv);
+    vec3 geometryNormal = normalize(v_Normal);
+    return Surface(
+        v_Position,
+        mapNormal(geometryNormal, material.normalScale),
+        geometryNormal,
+        material.baseColor.rgb * v_Color * texture(u_BaseColor, v_Uv).rgb,
+        material.metallic * metallicRoughness.b,
+        clamp(material.roughness * metallicRoughness.g, 0.03, 1.0),
+        mix(1.0, texture(u_Occlusion, v_Uv).r, material.occlusionStrength),
+        material.emissive * v_Color * texture(u_Emissive, v_Uv).rgb,
+        material.model,
+        vec3(material.ambient, material.specular, material.shininess)
+    );
+}
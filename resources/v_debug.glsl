@@ -0,0 +1,16 @@
+#version 460 core
+
+// Position, and 1 when the line is hidden behind the scene.
+layout(location = 0) in vec4 position;
+layout(location = 1) in vec4 color;
+
+out vec4 v_Color;
+flat out float v_DepthTest;
+
+uniform mat4 u_ViewProjection;
+
+void main() {
+    gl_Position = u_ViewProjection * vec4(position.xyz, 1.0);
+    v_Color = color;
+    v_DepthTest = position.w;
+}
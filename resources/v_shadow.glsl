@@ -0,0 +1,16 @@
+#version 460 core
+
+layout (location = 0) in vec3 a_Pos;
+layout (location = 4) in mat4 a_Model;
+layout (location = 10) in uint a_CastsShadow;
+
+uniform mat4 u_LightMatrix;
+
+void main() {
+    if (a_CastsShadow == 0) {
+        // Outside the clip volume, so the triangle is dropped.
+        gl_Position = vec4(2.0, 2.0, 2.0, 1.0);
+        return;
+    }
+    gl_Position = u_LightMatrix * a_Model * vec4(a_Pos, 1.0);
+}
@@ -0,0 +1,13 @@
+#version 460 core
+
+out vec3 v_Direction;
+
+uniform mat4 u_InverseViewProjection;
+
+void main() {
+    // One triangle covering the screen on the far plane.
+    vec2 position = vec2((gl_VertexID << 1) & 2, gl_VertexID & 2) * 2.0 - 1.0;
+    gl_Position = vec4(position, 1.0, 1.0);
+    vec4 world = u_InverseViewProjection * gl_Position;
+    v_Direction = world.xyz / world.w;
+}
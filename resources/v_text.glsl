@@ -0,0 +1,16 @@
+#version 460 core
+
+// Position in pixels from the top-left of the screen, and atlas coordinates.
+layout(location = 0) in vec4 positionUv;
+layout(location = 1) in vec4 color;
+
+out vec2 v_Uv;
+out vec4 v_Color;
+
+uniform mat4 u_Projection;
+
+void main() {
+    gl_Position = u_Projection * vec4(positionUv.xy, 0.0, 1.0);
+    v_Uv = positionUv.zw;
+    v_Color = color;
+}
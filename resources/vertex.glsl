@@ -2,14 +2,31 @@
 
 layout (location = 0) in vec3 a_Pos;
 layout (location = 1) in vec3 a_Color;
-layout (location = 2) in mat4 a_Model;
+layout (location = 2) in vec3 a_Normal;
+layout (location = 3) in vec2 a_Uv;
+layout (location = 4) in mat4 a_Model;
+layout (location = 8) in vec4 a_Tint;
+layout (location = 9) in uint a_Material;
+layout (location = 11) in uint a_Entity;
 
-out vec3 ourColor;
+out vec3 v_Position;
+out vec3 v_Normal;
+out vec3 v_Color;
+out vec2 v_Uv;
+flat out uint v_Material;
+flat out uint v_Entity;
 
 uniform mat4 view;
 uniform mat4 projection;
 
 void main() {
-    gl_Position = projection * view * a_Model * vec4(a_Pos, 1.0);
-    ourColor = a_Color;
+    vec4 world = a_Model * vec4(a_Pos, 1.0);
+    gl_Position = projection * view * world;
+    v_Position = world.xyz;
+    // The inverse transpose keeps normals perpendicular under non-uniform scale.
+    v_Normal = transpose(inverse(mat3(a_Model))) * a_Normal;
+    v_Color = a_Color * a_Tint.rgb;
+    v_Uv = a_Uv;
+    v_Material = a_Material;
+    v_Entity = a_Entity;
 }
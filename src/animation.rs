@@ -0,0 +1,474 @@
+use std::sync::Arc;
+
+use glam::{Quat, Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+
+use crate::transform::Transform;
+
+pub use self::easing::Easing;
+
+mod easing;
+
+/// A value that can be blended between keyframes.
+pub trait Animatable: Copy {
+    fn lerp(a: Self, b: Self, t: f32) -> Self;
+    /// Catmull-Rom spline through `p1` and `p2`.
+    fn cubic(p0: Self, p1: Self, p2: Self, p3: Self, t: f32) -> Self;
+}
+
+fn catmull_rom(p0: Vec4, p1: Vec4, p2: Vec4, p3: Vec4, t: f32) -> Vec4 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * ((2. * p1)
+        + (-p0 + p2) * t
+        + (2. * p0 - 5. * p1 + 4. * p2 - p3) * t2
+        + (-p0 + 3. * p1 - 3. * p2 + p3) * t3)
+}
+
+impl Animatable for Vec3 {
+    fn lerp(a: Self, b: Self, t: f32) -> Self {
+        a.lerp(b, t)
+    }
+
+    fn cubic(p0: Self, p1: Self, p2: Self, p3: Self, t: f32) -> Self {
+        catmull_rom(
+            p0.extend(0.),
+            p1.extend(0.),
+            p2.extend(0.),
+            p3.extend(0.),
+            t,
+        )
+        .truncate()
+    }
+}
+
+impl Animatable for Vec4 {
+    fn lerp(a: Self, b: Self, t: f32) -> Self {
+        a.lerp(b, t)
+    }
+
+    fn cubic(p0: Self, p1: Self, p2: Self, p3: Self, t: f32) -> Self {
+        catmull_rom(p0, p1, p2, p3, t)
+    }
+}
+
+impl Animatable for Quat {
+    fn lerp(a: Self, b: Self, t: f32) -> Self {
+        a.slerp(b, t)
+    }
+
+    fn cubic(p0: Self, p1: Self, p2: Self, p3: Self, t: f32) -> Self {
+        // Keep every control point in the hemisphere of `p1` so the spline
+        // takes the short way around.
+        let align = |q: Quat| {
+            let v = Vec4::from(q);
+            if v.dot(Vec4::from(p1)) < 0. {
+                -v
+            } else {
+                v
+            }
+        };
+        let v = catmull_rom(align(p0), Vec4::from(p1), align(p2), align(p3), t);
+        Quat::from_vec4(v).normalize()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Interpolation {
+    /// Holds each keyframe's value until the next one.
+    Step,
+    #[default]
+    Linear,
+    Cubic,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe<T> {
+    pub time: f32,
+    pub value: T,
+    /// Easing of the segment that starts at this keyframe.
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+impl<T> Keyframe<T> {
+    pub fn new(time: f32, value: T) -> Self {
+        Self {
+            time,
+            value,
+            easing: Easing::Linear,
+        }
+    }
+
+    pub fn eased(time: f32, value: T, easing: Easing) -> Self {
+        Self {
+            time,
+            value,
+            easing,
+        }
+    }
+}
+
+/// Keyframes sorted by time. Loaded tracks are sorted like the ones built
+/// with [`Track::new`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "TrackData<T>")]
+pub struct Track<T> {
+    pub interpolation: Interpolation,
+    keyframes: Vec<Keyframe<T>>,
+}
+
+/// A [`Track`] as written in files, with keyframes in any order.
+#[derive(Deserialize)]
+struct TrackData<T> {
+    interpolation: Interpolation,
+    keyframes: Vec<Keyframe<T>>,
+}
+
+impl<T> From<TrackData<T>> for Track<T> {
+    fn from(data: TrackData<T>) -> Self {
+        Track::new(data.interpolation, data.keyframes)
+    }
+}
+
+impl<T> Track<T> {
+    pub fn new(interpolation: Interpolation, mut keyframes: Vec<Keyframe<T>>) -> Self {
+        keyframes.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Self {
+            interpolation,
+            keyframes,
+        }
+    }
+}
+
+impl<T: Animatable> Track<T> {
+    #[inline]
+    pub fn get_duration(&self) -> f32 {
+        self.keyframes.last().map_or(0., |k| k.time)
+    }
+
+    /// Value of the track at `time`, holding the first and last keyframes
+    /// outside of their range.
+    pub fn sample(&self, time: f32) -> Option<T> {
+        let keys = &self.keyframes;
+        let next = keys.partition_point(|k| k.time <= time);
+        if next == 0 {
+            return keys.first().map(|k| k.value);
+        }
+        if next == keys.len() {
+            return keys.last().map(|k| k.value);
+        }
+
+        let (a, b) = (&keys[next - 1], &keys[next]);
+        let span = b.time - a.time;
+        let t = if span > 0. {
+            a.easing.apply((time - a.time) / span)
+        } else {
+            1.
+        };
+        Some(match self.interpolation {
+            Interpolation::Step => a.value,
+            Interpolation::Linear => T::lerp(a.value, b.value, t),
+            Interpolation::Cubic => {
+                let before = keys[next.saturating_sub(2)].value;
+                let after = keys.get(next + 1).unwrap_or(b).value;
+                T::cubic(before, a.value, b.value, after, t)
+            }
+        })
+    }
+}
+
+/// Set of tracks animating one entity. Missing tracks leave that part of the
+/// entity untouched.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AnimationClip {
+    pub position: Option<Track<Vec3>>,
+    pub rotation: Option<Track<Quat>>,
+    pub scale: Option<Track<Vec3>>,
+    pub color: Option<Track<Vec4>>,
+}
+
+impl AnimationClip {
+    /// Two keyframe clip moving a transform from `from` to `to`.
+    pub fn tween(from: &Transform, to: &Transform, duration: f32, easing: Easing) -> Self {
+        fn track<T: Animatable>(a: T, b: T, duration: f32, easing: Easing) -> Option<Track<T>> {
+            Some(Track::new(
+                Interpolation::Linear,
+                vec![Keyframe::eased(0., a, easing), Keyframe::new(duration, b)],
+            ))
+        }
+        Self {
+            position: track(from.get_position(), to.get_position(), duration, easing),
+            rotation: track(from.get_rotation(), to.get_rotation(), duration, easing),
+            scale: track(from.get_scale(), to.get_scale(), duration, easing),
+            color: None,
+        }
+    }
+
+    pub fn get_duration(&self) -> f32 {
+        [
+            self.position.as_ref().map(Track::get_duration),
+            self.rotation.as_ref().map(Track::get_duration),
+            self.scale.as_ref().map(Track::get_duration),
+            self.color.as_ref().map(Track::get_duration),
+        ]
+        .into_iter()
+        .flatten()
+        .fold(0., f32::max)
+    }
+
+    pub fn apply(&self, time: f32, transform: &mut Transform, color: &mut Vec4) {
+        if let Some(position) = self.position.as_ref().and_then(|t| t.sample(time)) {
+            transform.set_position(position);
+        }
+        if let Some(rotation) = self.rotation.as_ref().and_then(|t| t.sample(time)) {
+            transform.set_rotation(rotation);
+        }
+        if let Some(scale) = self.scale.as_ref().and_then(|t| t.sample(time)) {
+            transform.set_scale(scale);
+        }
+        if let Some(c) = self.color.as_ref().and_then(|t| t.sample(time)) {
+            *color = c;
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LoopMode {
+    /// Stops on the last frame.
+    #[default]
+    Once,
+    Loop,
+    /// Plays forwards, then backwards, and repeats.
+    PingPong,
+}
+
+/// Plays an [`AnimationClip`] on the entity it is attached to. Clips are
+/// shared so many entities can run the same motion.
+#[derive(Clone, Debug, PartialEq)]
+pub struct AnimationPlayer {
+    clip: Arc<AnimationClip>,
+    pub loop_mode: LoopMode,
+    pub speed: f32,
+    pub playing: bool,
+    /// Time since the start, ping-pong playback counts both directions.
+    time: f32,
+}
+
+impl AnimationPlayer {
+    pub fn new(clip: Arc<AnimationClip>, loop_mode: LoopMode) -> Self {
+        Self {
+            clip,
+            loop_mode,
+            speed: 1.,
+            playing: true,
+            time: 0.,
+        }
+    }
+
+    #[inline]
+    pub fn get_clip(&self) -> &Arc<AnimationClip> {
+        &self.clip
+    }
+
+    /// Time since the start, see [`AnimationPlayer::get_local_time`] for
+    /// the position inside the clip.
+    #[inline]
+    pub fn get_time(&self) -> f32 {
+        self.time
+    }
+
+    /// Starts the animation with an offset, e.g. to desynchronize copies.
+    pub fn with_time(mut self, time: f32) -> Self {
+        self.time = time;
+        self.advance(0.);
+        self
+    }
+
+    pub fn advance(&mut self, dt: f32) {
+        if !self.playing {
+            return;
+        }
+        let duration = self.clip.get_duration();
+        if duration <= 0. {
+            return;
+        }
+        self.time += dt * self.speed;
+        match self.loop_mode {
+            LoopMode::Once => {
+                if self.time >= duration || self.time < 0. {
+                    self.time = self.time.clamp(0., duration);
+                    self.playing = false;
+                }
+            }
+            LoopMode::Loop => self.time = self.time.rem_euclid(duration),
+            LoopMode::PingPong => self.time = self.time.rem_euclid(2. * duration),
+        }
+    }
+
+    /// Position of the playhead inside the clip.
+    pub fn get_local_time(&self) -> f32 {
+        let duration = self.clip.get_duration();
+        match self.loop_mode {
+            LoopMode::PingPong if self.time > duration => 2. * duration - self.time,
+            _ => self.time,
+        }
+    }
+
+    pub fn apply(&self, transform: &mut Transform, color: &mut Vec4) {
+        self.clip.apply(self.get_local_time(), transform, color);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn track(interpolation: Interpolation) -> Track<Vec3> {
+        Track::new(
+            interpolation,
+            vec![
+                Keyframe::new(0., Vec3::ZERO),
+                Keyframe::new(1., Vec3::X),
+                Keyframe::new(2., Vec3::new(3., 0., 0.)),
+            ],
+        )
+    }
+
+    fn clip(duration: f32) -> Arc<AnimationClip> {
+        Arc::new(AnimationClip {
+            position: Some(Track::new(
+                Interpolation::Linear,
+                vec![
+                    Keyframe::new(0., Vec3::ZERO),
+                    Keyframe::new(duration, Vec3::X),
+                ],
+            )),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn samples_step_linear_and_cubic() {
+        let step = track(Interpolation::Step);
+        assert_eq!(step.sample(0.9), Some(Vec3::ZERO));
+        assert_eq!(step.sample(1.5), Some(Vec3::X));
+
+        let linear = track(Interpolation::Linear);
+        assert_eq!(linear.sample(0.5), Some(Vec3::new(0.5, 0., 0.)));
+        assert_eq!(linear.sample(1.5), Some(Vec3::new(2., 0., 0.)));
+        // The ends are held outside the track.
+        assert_eq!(linear.sample(-1.), Some(Vec3::ZERO));
+        assert_eq!(linear.sample(5.), Some(Vec3::new(3., 0., 0.)));
+        assert_eq!(linear.get_duration(), 2.);
+
+        let cubic = track(Interpolation::Cubic);
+        assert_eq!(cubic.sample(1.), Some(Vec3::X));
+        let x = cubic.sample(0.5).unwrap().x;
+        // The spline bends towards the steeper second segment.
+        assert!(x > 0. && x < 0.5, "{x}");
+    }
+
+    #[test]
+    fn sorts_keyframes() {
+        let unsorted = Track::new(
+            Interpolation::Linear,
+            vec![Keyframe::new(1., Vec3::X), Keyframe::new(0., Vec3::ZERO)],
+        );
+        assert_eq!(unsorted.sample(0.25), Some(Vec3::new(0.25, 0., 0.)));
+
+        let text = "(interpolation: Linear, keyframes: [\
+                    (time: 2.0, value: (2.0, 0.0, 0.0)), \
+                    (time: 0.0, value: (0.0, 0.0, 0.0)), \
+                    (time: 1.0, value: (1.0, 0.0, 0.0))])";
+        let loaded: Track<Vec3> = ron::from_str(text).unwrap();
+        let expected = Track::new(
+            Interpolation::Linear,
+            (0..3)
+                .map(|i| Keyframe::new(i as f32, Vec3::new(i as f32, 0., 0.)))
+                .collect(),
+        );
+        assert_eq!(loaded, expected);
+        assert_eq!(loaded.sample(1.5), Some(Vec3::new(1.5, 0., 0.)));
+    }
+
+    #[test]
+    fn eases_segments() {
+        let eased = Track::new(
+            Interpolation::Linear,
+            vec![
+                Keyframe::eased(0., Vec3::ZERO, Easing::QuadIn),
+                Keyframe::new(1., Vec3::X),
+            ],
+        );
+        assert_eq!(eased.sample(0.5), Some(Vec3::new(0.25, 0., 0.)));
+        for easing in [
+            Easing::Linear,
+            Easing::QuadInOut,
+            Easing::CubicOut,
+            Easing::SineInOut,
+            Easing::ExpoIn,
+            Easing::ExpoOut,
+            Easing::BackOut,
+            Easing::ElasticOut,
+            Easing::BounceOut,
+        ] {
+            assert!(easing.apply(0.).abs() < 1e-3, "{easing:?}");
+            assert!((easing.apply(1.) - 1.).abs() < 1e-3, "{easing:?}");
+        }
+        assert!(Easing::BackOut.apply(0.8) > 1.);
+    }
+
+    #[test]
+    fn rotates_the_short_way() {
+        let a = Quat::from_rotation_y(0.1);
+        // The same rotation as 0.3 around y, on the other hemisphere.
+        let b = -Quat::from_rotation_y(0.3);
+        for interpolation in [Interpolation::Linear, Interpolation::Cubic] {
+            let track = Track::new(
+                interpolation,
+                vec![Keyframe::new(0., a), Keyframe::new(1., b)],
+            );
+            let middle = track.sample(0.5).unwrap();
+            assert!(
+                middle.angle_between(Quat::from_rotation_y(0.2)) < 1e-3,
+                "{interpolation:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn loops_local_time() {
+        let mut looped = AnimationPlayer::new(clip(2.), LoopMode::Loop);
+        looped.advance(5.);
+        assert_eq!(looped.get_local_time(), 1.);
+        assert!(looped.playing);
+
+        let mut ping_pong = AnimationPlayer::new(clip(2.), LoopMode::PingPong);
+        ping_pong.advance(3.5);
+        assert_eq!(ping_pong.get_local_time(), 0.5);
+        ping_pong.advance(1.);
+        assert_eq!(ping_pong.get_local_time(), 0.5);
+
+        let mut once = AnimationPlayer::new(clip(2.), LoopMode::Once);
+        once.advance(3.);
+        assert_eq!(once.get_local_time(), 2.);
+        assert!(!once.playing);
+        once.advance(1.);
+        assert_eq!(once.get_local_time(), 2.);
+    }
+
+    #[test]
+    fn applies_tweens() {
+        let from = Transform::new(Vec3::ZERO, Quat::IDENTITY, Vec3::ZERO);
+        let to = Transform::with_pos(Vec3::new(0., 2., 0.));
+        let clip = AnimationClip::tween(&from, &to, 2., Easing::Linear);
+        assert_eq!(clip.get_duration(), 2.);
+        let mut transform = from;
+        let mut color = Vec4::ONE;
+        clip.apply(1., &mut transform, &mut color);
+        assert_eq!(transform.get_position(), Vec3::Y);
+        assert_eq!(transform.get_scale(), Vec3::splat(0.5));
+        assert_eq!(color, Vec4::ONE);
+    }
+}
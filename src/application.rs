@@ -1,12 +1,14 @@
-use std::ffi::CString;
+use std::ffi::CStr;
 
 use glutin::{
     dpi::LogicalSize,
-    event::{Event, KeyboardInput, VirtualKeyCode, WindowEvent},
+    event::{ElementState, Event, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent},
     event_loop::ControlFlow,
 };
 
-use crate::key::KeyboardState;
+use crate::{clock::Clock, key::KeyboardState};
+
+const TITLE: &str = "hello opengl with rust";
 
 pub struct AppBuilder;
 
@@ -27,7 +29,7 @@ impl AppBuilder {
         let event_loop = glutin::event_loop::EventLoop::new();
 
         let window_builder = glutin::window::WindowBuilder::new()
-            .with_title("hello opengl with rust")
+            .with_title(TITLE)
             .with_inner_size(LogicalSize::new(w.width, w.height));
         // It is essential to make the context current before calling `gl::load_with`.
         let gl_context = unsafe {
@@ -46,14 +48,10 @@ impl AppBuilder {
 
         gl::load_with(|ptr| gl_context.get_proc_address(ptr));
 
-        let version = unsafe {
-            let tmp = gl::GetString(gl::VERSION) as *mut i8;
-            CString::from_raw(tmp)
-        };
-        println!("{:?}", version.to_str().unwrap());
-
         let mut app = T::new(&w);
         let mut key_state = KeyboardState::new();
+        let mut clock = Clock::new();
+        let mut shown_title = TITLE.to_owned();
 
         unsafe {
             gl::ClearColor(0., 0., 0., 1.);
@@ -66,16 +64,22 @@ impl AppBuilder {
                 *control_flow = ControlFlow::Exit;
             }
 
-            app.update(&key_state);
-
-            gl_context.window().request_redraw();
-
             match event {
-                Event::LoopDestroyed => return,
-                Event::DeviceEvent { event, .. } => match event {
-                    glutin::event::DeviceEvent::MouseMotion { delta } => app.on_mouse_move(&delta),
-                    _ => (),
-                },
+                Event::LoopDestroyed => (),
+                Event::MainEventsCleared => {
+                    clock.tick();
+                    app.update(&key_state, &mut clock);
+                    let title = title(&clock);
+                    if title != shown_title {
+                        gl_context.window().set_title(&title);
+                        shown_title = title;
+                    }
+                    gl_context.window().request_redraw();
+                }
+                Event::DeviceEvent {
+                    event: glutin::event::DeviceEvent::MouseMotion { delta },
+                    ..
+                } => app.on_mouse_move(&delta),
                 Event::WindowEvent { event, .. } => match event {
                     WindowEvent::KeyboardInput {
                         input:
@@ -85,7 +89,17 @@ impl AppBuilder {
                                 ..
                             },
                         ..
-                    } => key_state.process_event(&state, &virtual_code),
+                    } => {
+                        // Only the first press, not the repeats, is forwarded.
+                        let pressed = state == ElementState::Pressed;
+                        if pressed != key_state.is_pressed(&virtual_code) {
+                            app.on_key(virtual_code, pressed);
+                        }
+                        key_state.process_event(&state, &virtual_code);
+                    }
+                    WindowEvent::MouseInput { state, button, .. } => {
+                        app.on_mouse_button(button, state == ElementState::Pressed);
+                    }
                     WindowEvent::CloseRequested => {
                         *control_flow = ControlFlow::Exit;
                     }
@@ -94,6 +108,7 @@ impl AppBuilder {
                         w.height = size.height as f32;
                         gl_context.resize(size);
                         gl::Viewport(0, 0, size.width as i32, size.height as i32);
+                        app.on_resize(&w);
                     },
                     _ => (),
                 },
@@ -111,11 +126,36 @@ impl AppBuilder {
     }
 }
 
+/// Version string of the current OpenGL context.
+pub fn gl_version() -> String {
+    let version = unsafe { CStr::from_ptr(gl::GetString(gl::VERSION).cast()) };
+    version.to_string_lossy().into_owned()
+}
+
+/// Window title showing the state of the clock.
+fn title(clock: &Clock) -> String {
+    let mut title = TITLE.to_string();
+    if clock.is_paused() {
+        title.push_str(" [paused]");
+    }
+    if clock.get_time_scale() != 1. {
+        title.push_str(&format!(" [x{}]", clock.get_time_scale()));
+    }
+    title
+}
+
 pub trait Application {
     fn new(window: &Window) -> Self;
-    fn update(&mut self, key_state: &KeyboardState);
+    /// Called once per frame after `clock` ticked. Changes to the clock apply
+    /// from the next frame on.
+    fn update(&mut self, key_state: &KeyboardState, clock: &mut Clock);
     fn draw(&mut self);
+    #[allow(dead_code)]
     fn event(&mut self);
     fn on_mouse_move(&mut self, delta: &(f64, f64));
+    /// Called when `button` goes down or up.
+    fn on_mouse_button(&mut self, button: MouseButton, pressed: bool);
+    /// Called when `key` goes down or up, without key repeats.
+    fn on_key(&mut self, key: VirtualKeyCode, pressed: bool);
     fn on_resize(&mut self, window: &Window);
 }
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+
+use crate::bounds::Aabb;
+
+/// Handle to a mesh registered by name in an [`AssetNames`] table.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct MeshId(pub u32);
+
+/// Handle to a material registered by name in an [`AssetNames`] table.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct MaterialId(pub u32);
+
+/// Maps mesh and material names to the compact ids stored on entities.
+///
+/// Entities only carry ids; the names are what scene files and the renderer
+/// use to find the actual resources. The bounds of the meshes are kept by
+/// name too, for scene queries that need the extent of an entity.
+pub struct AssetNames {
+    meshes: Vec<String>,
+    materials: Vec<String>,
+    mesh_bounds: HashMap<String, Aabb>,
+}
+
+impl AssetNames {
+    pub const DEFAULT_MESH: &'static str = "cube";
+    pub const DEFAULT_MATERIAL: &'static str = "default";
+
+    pub fn new() -> Self {
+        Self {
+            meshes: vec![Self::DEFAULT_MESH.to_owned()],
+            materials: vec![Self::DEFAULT_MATERIAL.to_owned()],
+            mesh_bounds: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn from_names(meshes: Vec<String>, materials: Vec<String>) -> Self {
+        let mut names = Self {
+            meshes,
+            materials,
+            mesh_bounds: HashMap::new(),
+        };
+        if names.meshes.is_empty() {
+            names.meshes.push(Self::DEFAULT_MESH.to_owned());
+        }
+        if names.materials.is_empty() {
+            names.materials.push(Self::DEFAULT_MATERIAL.to_owned());
+        }
+        names
+    }
+
+    /// Returns the id of the mesh called `name`, registering it if needed.
+    pub fn mesh(&mut self, name: &str) -> MeshId {
+        MeshId(get_or_insert(&mut self.meshes, name))
+    }
+
+    /// Returns the id of the material called `name`, registering it if needed.
+    pub fn material(&mut self, name: &str) -> MaterialId {
+        MaterialId(get_or_insert(&mut self.materials, name))
+    }
+
+    #[inline]
+    pub fn get_mesh_name(&self, id: MeshId) -> Option<&str> {
+        self.meshes.get(id.0 as usize).map(String::as_str)
+    }
+
+    /// Local bounds of the mesh `id`. Meshes without known bounds are
+    /// treated as unit cubes.
+    #[inline]
+    pub fn get_mesh_bounds(&self, id: MeshId) -> Aabb {
+        self.get_mesh_name(id)
+            .and_then(|name| self.mesh_bounds.get(name))
+            .copied()
+            .unwrap_or(Aabb::UNIT)
+    }
+
+    #[inline]
+    pub(crate) fn set_mesh_bounds(&mut self, name: &str, bounds: Aabb) {
+        self.mesh_bounds.insert(name.to_owned(), bounds);
+    }
+
+    #[inline]
+    pub fn get_meshes(&self) -> &[String] {
+        &self.meshes
+    }
+
+    #[inline]
+    pub fn get_materials(&self) -> &[String] {
+        &self.materials
+    }
+}
+
+fn get_or_insert(names: &mut Vec<String>, name: &str) -> u32 {
+    match names.iter().position(|n| n == name) {
+        Some(index) => index as u32,
+        None => {
+            names.push(name.to_owned());
+            (names.len() - 1) as u32
+        }
+    }
+}
@@ -0,0 +1,135 @@
+use glam::{Mat4, Vec3, Vec4, Vec4Swizzles};
+
+/// Axis aligned bounding box.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    /// Bounds of the unit cube centered on the origin.
+    pub const UNIT: Aabb = Aabb {
+        min: Vec3::splat(-0.5),
+        max: Vec3::splat(0.5),
+    };
+
+    pub fn new(min: Vec3, max: Vec3) -> Self {
+        Self { min, max }
+    }
+
+    pub fn from_center_half_extents(center: Vec3, half_extents: Vec3) -> Self {
+        Self {
+            min: center - half_extents,
+            max: center + half_extents,
+        }
+    }
+
+    #[inline]
+    pub fn get_center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    #[inline]
+    pub fn get_half_extents(&self) -> Vec3 {
+        (self.max - self.min) * 0.5
+    }
+
+    #[inline]
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    #[inline]
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.max).all() && other.min.cmple(self.max).all()
+    }
+
+    #[inline]
+    pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+        let closest = sphere.center.clamp(self.min, self.max);
+        closest.distance_squared(sphere.center) <= sphere.radius * sphere.radius
+    }
+
+    /// Distance along `ray` to the box entry point, zero if the ray starts
+    /// inside it.
+    pub fn intersects_ray(&self, ray: &Ray) -> Option<f32> {
+        let inverse = ray.direction.recip();
+        let t1 = (self.min - ray.origin) * inverse;
+        let t2 = (self.max - ray.origin) * inverse;
+        let near = t1.min(t2).max_element().max(0.);
+        let far = t1.max(t2).min_element();
+        (near <= far).then_some(near)
+    }
+
+    /// Bounds of this box after transforming it by `matrix`.
+    pub fn transformed(&self, matrix: &Mat4) -> Aabb {
+        let center = matrix.transform_point3(self.get_center());
+        let half = self.get_half_extents();
+        let extent = matrix.x_axis.xyz().abs() * half.x
+            + matrix.y_axis.xyz().abs() * half.y
+            + matrix.z_axis.xyz().abs() * half.z;
+        Aabb::from_center_half_extents(center, extent)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Sphere {
+    pub center: Vec3,
+    pub radius: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vec3,
+    pub direction: Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: Vec3, direction: Vec3) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize(),
+        }
+    }
+
+    #[inline]
+    pub fn get_point(&self, distance: f32) -> Vec3 {
+        self.origin + self.direction * distance
+    }
+}
+
+/// The six planes of a view volume, facing inwards.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frustum {
+    planes: [Vec4; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes of a `projection * view` matrix.
+    pub fn from_matrix(matrix: &Mat4) -> Self {
+        let (r0, r1, r2, r3) = (matrix.row(0), matrix.row(1), matrix.row(2), matrix.row(3));
+        let mut planes = [r3 + r0, r3 - r0, r3 + r1, r3 - r1, r3 + r2, r3 - r2];
+        for plane in planes.iter_mut() {
+            *plane /= plane.xyz().length();
+        }
+        Self { planes }
+    }
+
+    #[inline]
+    pub fn get_planes(&self) -> &[Vec4; 6] {
+        &self.planes
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // Corner of the box furthest along the plane normal.
+            let normal = plane.xyz();
+            let corner = Vec3::select(normal.cmpge(Vec3::ZERO), aabb.max, aabb.min);
+            normal.dot(corner) + plane.w >= 0.
+        })
+    }
+}
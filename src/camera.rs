@@ -15,7 +15,8 @@ pub struct Camera {
 
 impl Camera {
     const SENSIVITY: f64 = 0.5;
-    const SPEED: f32 = 0.05;
+    /// Units per second.
+    const SPEED: f32 = 10.;
     pub fn new() -> Self {
         Self {
             position: Vec3 {
@@ -39,6 +40,29 @@ impl Camera {
         }
     }
 
+    /// Restores a camera from its position and orientation angles in degrees.
+    pub fn with_orientation(position: Vec3, up: Vec3, pitch: f64, yaw: f64) -> Self {
+        let mut camera = Self {
+            position,
+            up,
+            pitch: pitch.clamp(-89., 89.),
+            yaw,
+            ..Self::new()
+        };
+        camera.update_direction();
+        camera
+    }
+
+    #[inline]
+    pub fn get_pitch(&self) -> f64 {
+        self.pitch
+    }
+
+    #[inline]
+    pub fn get_yaw(&self) -> f64 {
+        self.yaw
+    }
+
     pub fn get_matrix(&self) -> Mat4 {
         Mat4::look_at_rh(self.position, self.position + self.direction, self.up)
     }
@@ -48,29 +72,30 @@ impl Camera {
         self.yaw += x * Camera::SENSIVITY;
         self.pitch -= y * Camera::SENSIVITY;
 
-        if self.pitch > 89. {
-            self.pitch = 89.;
-        }
-        if self.pitch < -89. {
-            self.pitch = -89.;
-        }
+        self.pitch = self.pitch.clamp(-89., 89.);
+        self.update_direction();
+    }
+
+    fn update_direction(&mut self) {
         self.direction.x = (self.yaw.to_radians().cos() * self.pitch.to_radians().cos()) as f32;
         self.direction.y = self.pitch.to_radians().sin() as f32;
         self.direction.z = (self.yaw.to_radians().sin() * self.pitch.to_radians().cos()) as f32;
     }
 
-    pub fn handle_input(&mut self, key_state: &KeyboardState) {
+    /// Moves the camera for a frame lasting `dt` seconds.
+    pub fn handle_input(&mut self, key_state: &KeyboardState, dt: f32) {
+        let speed = Camera::SPEED * dt;
         if key_state.is_pressed(&VirtualKeyCode::W) {
-            self.position += self.direction * Camera::SPEED;
+            self.position += self.direction * speed;
         }
         if key_state.is_pressed(&VirtualKeyCode::A) {
-            self.position -= self.direction.cross(self.up).normalize() * Camera::SPEED;
+            self.position -= self.direction.cross(self.up).normalize() * speed;
         }
         if key_state.is_pressed(&VirtualKeyCode::S) {
-            self.position -= self.direction * Camera::SPEED;
+            self.position -= self.direction * speed;
         }
         if key_state.is_pressed(&VirtualKeyCode::D) {
-            self.position += self.direction.cross(self.up).normalize() * Camera::SPEED;
+            self.position += self.direction.cross(self.up).normalize() * speed;
         }
     }
 }
@@ -0,0 +1,233 @@
+use std::time::Instant;
+
+/// Time source of the application loop, ticked once per frame.
+///
+/// Game time can be paused and scaled, real time keeps running so the
+/// camera can still move around a paused scene. Fixed ticks are counted
+/// on the scaled time for systems that need a constant step, like physics.
+///
+/// A frame never advances game time by more than [`Clock::MAX_FIXED_STEPS`]
+/// fixed ticks, so every system sees the same time and the fixed step
+/// systems never fall behind. With a high time scale on a slow frame, game
+/// time then runs slower than the scale asks for, see [`Clock::is_limited`].
+pub struct Clock {
+    last: Instant,
+    total: f64,
+    delta: f32,
+    real_delta: f32,
+    accumulator: f32,
+    fixed_steps: u32,
+    ticks: u64,
+    frames: u64,
+    time_scale: f32,
+    paused: bool,
+    step_requested: bool,
+    /// Whether the last frame was cut to the fixed step limit.
+    limited: bool,
+}
+
+impl Clock {
+    pub const FIXED_DELTA: f32 = 1. / 60.;
+    /// Fixed ticks a single frame may run, so a long frame cannot stall the
+    /// loop.
+    pub const MAX_FIXED_STEPS: u32 = 8;
+    /// Longer frames are clamped, e.g. after the window was dragged.
+    const MAX_DELTA: f32 = 0.25;
+    const MIN_SCALE: f32 = 1. / 16.;
+    const MAX_SCALE: f32 = 16.;
+
+    pub fn new() -> Self {
+        Self {
+            last: Instant::now(),
+            total: 0.,
+            delta: 0.,
+            real_delta: 0.,
+            accumulator: 0.,
+            fixed_steps: 0,
+            ticks: 0,
+            frames: 0,
+            time_scale: 1.,
+            paused: false,
+            step_requested: false,
+            limited: false,
+        }
+    }
+
+    /// Starts a new frame, measuring the time since the previous one.
+    pub fn tick(&mut self) {
+        let now = Instant::now();
+        self.advance((now - self.last).as_secs_f32());
+        self.last = now;
+    }
+
+    /// Starts a new frame that took `real_delta` seconds.
+    fn advance(&mut self, real_delta: f32) {
+        self.real_delta = real_delta.min(Self::MAX_DELTA);
+        self.frames += 1;
+
+        self.delta = if !self.paused {
+            self.real_delta * self.time_scale
+        } else if self.step_requested {
+            Self::FIXED_DELTA
+        } else {
+            0.
+        };
+        self.step_requested = false;
+        let max_delta = Self::MAX_FIXED_STEPS as f32 * Self::FIXED_DELTA;
+        self.limited = self.delta > max_delta;
+        self.delta = self.delta.min(max_delta);
+        self.total += self.delta as f64;
+
+        self.accumulator += self.delta;
+        self.fixed_steps = 0;
+        while self.accumulator >= Self::FIXED_DELTA {
+            self.accumulator -= Self::FIXED_DELTA;
+            self.fixed_steps += 1;
+        }
+        self.ticks += self.fixed_steps as u64;
+    }
+
+    /// Scaled time elapsed while not paused, in seconds.
+    #[inline]
+    pub fn get_total_time(&self) -> f64 {
+        self.total
+    }
+
+    /// Scaled duration of the current frame, zero while paused.
+    #[inline]
+    pub fn get_delta(&self) -> f32 {
+        self.delta
+    }
+
+    /// Duration of the current frame ignoring pause and time scale.
+    #[inline]
+    pub fn get_real_delta(&self) -> f32 {
+        self.real_delta
+    }
+
+    /// Number of [`Clock::FIXED_DELTA`] ticks that elapsed this frame.
+    #[inline]
+    pub fn get_fixed_steps(&self) -> u32 {
+        self.fixed_steps
+    }
+
+    /// Number of fixed ticks since the clock was created.
+    #[inline]
+    pub fn get_tick_count(&self) -> u64 {
+        self.ticks
+    }
+
+    #[inline]
+    pub fn get_frame_count(&self) -> u64 {
+        self.frames
+    }
+
+    #[inline]
+    pub fn get_time_scale(&self) -> f32 {
+        self.time_scale
+    }
+
+    pub fn set_time_scale(&mut self, scale: f32) {
+        self.time_scale = scale.clamp(Self::MIN_SCALE, Self::MAX_SCALE);
+    }
+
+    /// Whether the last frame ran less game time than the time scale asked
+    /// for, to stay within [`Clock::MAX_FIXED_STEPS`].
+    #[inline]
+    pub fn is_limited(&self) -> bool {
+        self.limited
+    }
+
+    #[inline]
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn toggle_pause(&mut self) {
+        self.paused = !self.paused;
+    }
+
+    /// Advances a paused clock by one fixed tick on the next frame.
+    pub fn step(&mut self) {
+        if self.paused {
+            self.step_requested = true;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: f32 = 4. * Clock::FIXED_DELTA;
+
+    #[test]
+    fn counts_fixed_steps() {
+        let mut clock = Clock::new();
+        clock.advance(FRAME);
+        assert_eq!(clock.get_delta(), FRAME);
+        assert_eq!(clock.get_fixed_steps(), 4);
+        clock.advance(FRAME);
+        assert_eq!(clock.get_tick_count(), 8);
+        assert_eq!(clock.get_frame_count(), 2);
+        assert!((clock.get_total_time() - 2. * FRAME as f64).abs() < 1e-6);
+    }
+
+    #[test]
+    fn pauses_and_steps() {
+        let mut clock = Clock::new();
+        clock.toggle_pause();
+        clock.advance(FRAME);
+        assert_eq!(clock.get_delta(), 0.);
+        assert_eq!(clock.get_real_delta(), FRAME);
+        assert_eq!(clock.get_fixed_steps(), 0);
+
+        clock.step();
+        clock.advance(FRAME);
+        assert_eq!(clock.get_delta(), Clock::FIXED_DELTA);
+        assert_eq!(clock.get_fixed_steps(), 1);
+        clock.advance(FRAME);
+        assert_eq!(clock.get_delta(), 0.);
+
+        // Stepping only applies to a paused clock.
+        clock.toggle_pause();
+        clock.step();
+        clock.advance(FRAME);
+        assert_eq!(clock.get_delta(), FRAME);
+        assert_eq!(clock.get_tick_count(), 5);
+    }
+
+    #[test]
+    fn scales_time() {
+        let mut clock = Clock::new();
+        clock.set_time_scale(0.5);
+        clock.advance(FRAME);
+        assert_eq!(clock.get_delta(), FRAME * 0.5);
+        assert_eq!(clock.get_real_delta(), FRAME);
+        assert_eq!(clock.get_fixed_steps(), 2);
+
+        clock.set_time_scale(1000.);
+        assert_eq!(clock.get_time_scale(), Clock::MAX_SCALE);
+        clock.set_time_scale(0.);
+        assert_eq!(clock.get_time_scale(), Clock::MIN_SCALE);
+    }
+
+    #[test]
+    fn limits_fixed_steps() {
+        let mut clock = Clock::new();
+        clock.set_time_scale(4.);
+        clock.advance(1.);
+        assert_eq!(clock.get_real_delta(), Clock::MAX_DELTA);
+        assert_eq!(clock.get_fixed_steps(), Clock::MAX_FIXED_STEPS);
+        assert_eq!(
+            clock.get_delta(),
+            Clock::MAX_FIXED_STEPS as f32 * Clock::FIXED_DELTA
+        );
+        assert!(clock.is_limited());
+
+        clock.set_time_scale(1.);
+        clock.advance(FRAME);
+        assert!(!clock.is_limited());
+        assert_eq!(clock.get_fixed_steps(), 4);
+    }
+}
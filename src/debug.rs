@@ -0,0 +1,273 @@
+use std::f32::consts::TAU;
+
+use glam::{BVec3, Mat4, Vec3, Vec4};
+
+use crate::bounds::{Aabb, Sphere};
+
+/// End of a debug line.
+#[derive(Clone, Copy, Debug)]
+pub struct DebugVertex {
+    pub position: Vec3,
+    pub color: Vec4,
+}
+
+/// Lines added by one call, drawn until their time runs out.
+#[derive(Clone, Copy, Debug)]
+struct Shape {
+    /// Range of the shape's vertices, two per line.
+    start: usize,
+    end: usize,
+    /// Seconds left to draw it. Shapes are dropped once it falls below zero,
+    /// so zero draws a shape for the frame it was added in.
+    remaining: f32,
+    depth_test: bool,
+}
+
+/// Options of the shape just added, set in a chain after the call adding it.
+pub struct ShapeOptions<'a> {
+    shape: Option<&'a mut Shape>,
+}
+
+impl ShapeOptions<'_> {
+    /// Keeps drawing the shape for `seconds` of real time instead of one
+    /// frame.
+    pub fn duration(mut self, seconds: f32) -> Self {
+        if let Some(shape) = self.shape.as_mut() {
+            shape.remaining = seconds;
+        }
+        self
+    }
+
+    /// Draws the shape over the scene instead of hiding it behind surfaces.
+    pub fn on_top(mut self) -> Self {
+        if let Some(shape) = self.shape.as_mut() {
+            shape.depth_test = false;
+        }
+        self
+    }
+}
+
+/// Immediate-mode debug shapes made of lines, which any system holding the
+/// scene can add during a frame and the renderer draws over it.
+///
+/// Shapes last one frame unless given a duration, and are hidden by the
+/// surfaces in front of them unless drawn on top.
+pub struct DebugDraw {
+    vertices: Vec<DebugVertex>,
+    shapes: Vec<Shape>,
+    enabled: bool,
+}
+
+impl DebugDraw {
+    /// Segments of the circles of spheres.
+    const CIRCLE_SEGMENTS: usize = 24;
+
+    pub fn new() -> Self {
+        Self {
+            vertices: vec![],
+            shapes: vec![],
+            enabled: true,
+        }
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Disabled, shapes are neither kept nor drawn.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.clear();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.shapes.clear();
+    }
+
+    /// Ages the shapes by `dt` seconds and drops the expired ones. Called
+    /// before the systems of a frame add theirs.
+    pub fn update(&mut self, dt: f32) {
+        let mut write = 0;
+        let mut kept = 0;
+        for i in 0..self.shapes.len() {
+            let mut shape = self.shapes[i];
+            shape.remaining -= dt;
+            if shape.remaining < 0. {
+                continue;
+            }
+            let count = shape.end - shape.start;
+            self.vertices.copy_within(shape.start..shape.end, write);
+            shape.start = write;
+            shape.end = write + count;
+            write += count;
+            self.shapes[kept] = shape;
+            kept += 1;
+        }
+        self.vertices.truncate(write);
+        self.shapes.truncate(kept);
+    }
+
+    /// Vertices of the lines to draw, two per line, with whether they are
+    /// depth tested.
+    pub fn get_lines(&self) -> impl Iterator<Item = (&[DebugVertex], bool)> + '_ {
+        self.shapes
+            .iter()
+            .map(|s| (&self.vertices[s.start..s.end], s.depth_test))
+    }
+
+    /// Adds a shape made of the line vertices `build` pushes.
+    fn add(&mut self, build: impl FnOnce(&mut Vec<DebugVertex>)) -> ShapeOptions<'_> {
+        if !self.enabled {
+            return ShapeOptions { shape: None };
+        }
+        let start = self.vertices.len();
+        build(&mut self.vertices);
+        self.shapes.push(Shape {
+            start,
+            end: self.vertices.len(),
+            remaining: 0.,
+            depth_test: true,
+        });
+        ShapeOptions {
+            shape: self.shapes.last_mut(),
+        }
+    }
+
+    pub fn line(&mut self, start: Vec3, end: Vec3, color: Vec4) -> ShapeOptions<'_> {
+        self.add(|v| push_line(v, start, end, color))
+    }
+
+    /// Line from `start` to `end` with a head at `end`, a fifth of its length.
+    pub fn arrow(&mut self, start: Vec3, end: Vec3, color: Vec4) -> ShapeOptions<'_> {
+        self.add(|v| {
+            push_line(v, start, end, color);
+            let axis = end - start;
+            let length = axis.length();
+            if length <= f32::EPSILON {
+                return;
+            }
+            let direction = axis / length;
+            let side = direction.any_orthonormal_vector();
+            let other = direction.cross(side);
+            let base = end - direction * length * 0.2;
+            for offset in [side, -side, other, -other] {
+                push_line(v, end, base + offset * length * 0.07, color);
+            }
+        })
+    }
+
+    /// Edges of `aabb`.
+    pub fn aabb(&mut self, aabb: &Aabb, color: Vec4) -> ShapeOptions<'_> {
+        let corners = [0, 1, 2, 3, 4, 5, 6, 7].map(|i: u32| {
+            Vec3::select(
+                BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0),
+                aabb.max,
+                aabb.min,
+            )
+        });
+        self.add(|v| push_box(v, &corners, color))
+    }
+
+    /// Three circles of `sphere`, one around each axis.
+    pub fn sphere(&mut self, sphere: &Sphere, color: Vec4) -> ShapeOptions<'_> {
+        self.add(|v| {
+            for (a, b) in [(Vec3::X, Vec3::Y), (Vec3::Y, Vec3::Z), (Vec3::Z, Vec3::X)] {
+                let point = |i: usize| {
+                    let angle = i as f32 / Self::CIRCLE_SEGMENTS as f32 * TAU;
+                    sphere.center + (a * angle.cos() + b * angle.sin()) * sphere.radius
+                };
+                for i in 0..Self::CIRCLE_SEGMENTS {
+                    push_line(v, point(i), point(i + 1), color);
+                }
+            }
+        })
+    }
+
+    /// Edges of the volume seen through the `view_projection` matrix, taken
+    /// to map it to the -1 to 1 cube like [`Frustum::from_matrix`].
+    ///
+    /// [`Frustum::from_matrix`]: crate::bounds::Frustum::from_matrix
+    pub fn frustum(&mut self, view_projection: &Mat4, color: Vec4) -> ShapeOptions<'_> {
+        let inverse = view_projection.inverse();
+        let corners = [0, 1, 2, 3, 4, 5, 6, 7].map(|i: u32| {
+            let ndc = Vec3::new(
+                if i & 1 != 0 { 1. } else { -1. },
+                if i & 2 != 0 { 1. } else { -1. },
+                if i & 4 != 0 { 1. } else { -1. },
+            );
+            inverse.project_point3(ndc)
+        });
+        self.add(|v| push_box(v, &corners, color))
+    }
+
+    /// X, Y and Z axes of `transform` in red, green and blue, `size` long.
+    pub fn axes(&mut self, transform: &Mat4, size: f32) -> ShapeOptions<'_> {
+        let origin = transform.transform_point3(Vec3::ZERO);
+        self.add(|v| {
+            for (axis, color) in [
+                (Vec3::X, Vec4::new(1., 0., 0., 1.)),
+                (Vec3::Y, Vec4::new(0., 1., 0., 1.)),
+                (Vec3::Z, Vec4::new(0., 0., 1., 1.)),
+            ] {
+                let end = transform.transform_point3(axis * size);
+                push_line(v, origin, end, color);
+            }
+        })
+    }
+
+    /// Grid on the XZ plane around `center`, of `cells` square cells
+    /// `spacing` wide along each side.
+    pub fn grid(
+        &mut self,
+        center: Vec3,
+        spacing: f32,
+        cells: u32,
+        color: Vec4,
+    ) -> ShapeOptions<'_> {
+        let half = spacing * cells as f32 / 2.;
+        self.add(|v| {
+            for i in 0..=cells {
+                let offset = i as f32 * spacing - half;
+                push_line(
+                    v,
+                    center + Vec3::new(offset, 0., -half),
+                    center + Vec3::new(offset, 0., half),
+                    color,
+                );
+                push_line(
+                    v,
+                    center + Vec3::new(-half, 0., offset),
+                    center + Vec3::new(half, 0., offset),
+                    color,
+                );
+            }
+        })
+    }
+}
+
+fn push_line(vertices: &mut Vec<DebugVertex>, start: Vec3, end: Vec3, color: Vec4) {
+    vertices.push(DebugVertex {
+        position: start,
+        color,
+    });
+    vertices.push(DebugVertex {
+        position: end,
+        color,
+    });
+}
+
+/// Edges of a box whose corner `i` lies on the positive side of X, Y and Z
+/// when bit 0, 1 and 2 of `i` are set.
+fn push_box(vertices: &mut Vec<DebugVertex>, corners: &[Vec3; 8], color: Vec4) {
+    for i in 0..8 {
+        for bit in [1, 2, 4] {
+            if i & bit == 0 {
+                push_line(vertices, corners[i], corners[i | bit], color);
+            }
+        }
+    }
+}
@@ -1,18 +1,97 @@
-use glam::Vec3;
+use glam::Vec4;
 
-use crate::transform::Transform;
+use crate::{
+    animation::AnimationPlayer,
+    asset::{MaterialId, MeshId},
+    light::Light,
+    physics::{Collider, RigidBody},
+    transform::Transform,
+};
 
+/// Stable identifier of an entity, unique within its [`Scene`](crate::scene::Scene).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, PartialOrd, Ord)]
+pub struct EntityId(pub u32);
+
+#[derive(Clone)]
 pub struct Entity {
+    id: EntityId,
+    parent: Option<EntityId>,
+    name: Option<String>,
+    mesh: MeshId,
+    material: MaterialId,
     transform: Transform,
+    /// Multiplied with the mesh colors.
+    color: Vec4,
+    rigid_body: Option<RigidBody>,
+    collider: Option<Collider>,
+    animation: Option<AnimationPlayer>,
+    light: Option<Light>,
 }
 
 impl Entity {
-    pub fn new(pos: Vec3) -> Self {
+    pub fn with_transform(id: EntityId, transform: Transform) -> Self {
         Self {
-            transform: Transform::with_pos(pos),
+            id,
+            parent: None,
+            name: None,
+            mesh: MeshId::default(),
+            material: MaterialId::default(),
+            transform,
+            color: Vec4::ONE,
+            rigid_body: None,
+            collider: None,
+            animation: None,
+            light: None,
         }
     }
 
+    #[inline]
+    pub fn get_id(&self) -> EntityId {
+        self.id
+    }
+
+    #[inline]
+    pub fn get_parent(&self) -> Option<EntityId> {
+        self.parent
+    }
+
+    /// Only [`Scene::set_parent`](crate::scene::Scene::set_parent) may change
+    /// the parent, since it has to reject cycles.
+    #[inline]
+    pub(crate) fn set_parent(&mut self, parent: Option<EntityId>) {
+        self.parent = parent;
+    }
+
+    #[inline]
+    pub fn get_name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    #[inline]
+    pub fn set_name(&mut self, name: Option<String>) {
+        self.name = name;
+    }
+
+    #[inline]
+    pub fn get_mesh(&self) -> MeshId {
+        self.mesh
+    }
+
+    #[inline]
+    pub fn set_mesh(&mut self, mesh: MeshId) {
+        self.mesh = mesh;
+    }
+
+    #[inline]
+    pub fn get_material(&self) -> MaterialId {
+        self.material
+    }
+
+    #[inline]
+    pub fn set_material(&mut self, material: MaterialId) {
+        self.material = material;
+    }
+
     #[inline]
     pub fn get_mut_transform(&mut self) -> &mut Transform {
         &mut self.transform
@@ -22,4 +101,67 @@ impl Entity {
     pub fn get_transform(&self) -> &Transform {
         &self.transform
     }
+
+    #[inline]
+    pub fn get_color(&self) -> Vec4 {
+        self.color
+    }
+
+    #[inline]
+    pub fn set_color(&mut self, color: Vec4) {
+        self.color = color;
+    }
+
+    #[inline]
+    pub fn get_rigid_body(&self) -> Option<&RigidBody> {
+        self.rigid_body.as_ref()
+    }
+
+    #[inline]
+    pub fn get_mut_rigid_body(&mut self) -> Option<&mut RigidBody> {
+        self.rigid_body.as_mut()
+    }
+
+    #[inline]
+    pub fn set_rigid_body(&mut self, rigid_body: Option<RigidBody>) {
+        self.rigid_body = rigid_body;
+    }
+
+    #[inline]
+    pub fn get_collider(&self) -> Option<&Collider> {
+        self.collider.as_ref()
+    }
+
+    #[inline]
+    pub fn set_collider(&mut self, collider: Option<Collider>) {
+        self.collider = collider;
+    }
+
+    #[inline]
+    pub fn get_animation(&self) -> Option<&AnimationPlayer> {
+        self.animation.as_ref()
+    }
+
+    #[inline]
+    pub fn set_animation(&mut self, animation: Option<AnimationPlayer>) {
+        self.animation = animation;
+    }
+
+    #[inline]
+    pub fn get_light(&self) -> Option<&Light> {
+        self.light.as_ref()
+    }
+
+    #[inline]
+    pub fn set_light(&mut self, light: Option<Light>) {
+        self.light = light;
+    }
+
+    /// Advances the animation player, if any, and applies it.
+    pub fn update_animation(&mut self, dt: f32) {
+        if let Some(player) = self.animation.as_mut() {
+            player.advance(dt);
+            player.apply(&mut self.transform, &mut self.color);
+        }
+    }
 }
@@ -0,0 +1,159 @@
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    marker::PhantomData,
+};
+
+use crate::entity::EntityId;
+
+/// An entity was added to the scene.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Spawned(pub EntityId);
+
+/// An entity was removed from the scene.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Despawned(pub EntityId);
+
+/// A key bound to an action was pressed or released.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct InputAction {
+    pub name: &'static str,
+    pub pressed: bool,
+}
+
+/// Double buffered queue of events of one type.
+///
+/// Events stay readable for the frame they are sent in and the following
+/// one, so a stage running before the sender still sees them once. Readers
+/// keep a cursor to skip what they have already seen.
+pub struct EventQueue<T> {
+    older: Vec<T>,
+    newer: Vec<T>,
+    /// Sequence number of the first event in `older`.
+    start: usize,
+}
+
+impl<T> EventQueue<T> {
+    pub fn new() -> Self {
+        Self {
+            older: vec![],
+            newer: vec![],
+            start: 0,
+        }
+    }
+
+    pub fn send(&mut self, event: T) {
+        self.newer.push(event);
+    }
+
+    /// Events `reader` has not seen yet.
+    pub fn read<'a>(&'a self, reader: &mut EventReader<T>) -> impl Iterator<Item = &'a T> {
+        let skip = reader.next.saturating_sub(self.start);
+        reader.next = self.start + self.older.len() + self.newer.len();
+        self.iter().skip(skip)
+    }
+
+    /// Every buffered event, oldest first.
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.older.iter().chain(self.newer.iter())
+    }
+
+    #[inline]
+    #[allow(dead_code)]
+    pub fn len(&self) -> usize {
+        self.older.len() + self.newer.len()
+    }
+
+    /// Drops the events of the previous frame.
+    pub fn update(&mut self) {
+        self.start += self.older.len();
+        self.older = std::mem::take(&mut self.newer);
+    }
+}
+
+/// Cursor into an [`EventQueue`].
+pub struct EventReader<T> {
+    next: usize,
+    _event: PhantomData<fn() -> T>,
+}
+
+impl<T> EventReader<T> {
+    pub fn new() -> Self {
+        Self {
+            next: 0,
+            _event: PhantomData,
+        }
+    }
+}
+
+trait Queue {
+    fn update(&mut self);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: 'static> Queue for EventQueue<T> {
+    fn update(&mut self) {
+        EventQueue::update(self);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// One [`EventQueue`] per event type, created on first use. Any `'static`
+/// type can be sent, so user code can define its own events.
+pub struct Events {
+    queues: HashMap<TypeId, Box<dyn Queue>>,
+}
+
+impl Events {
+    pub fn new() -> Self {
+        Self {
+            queues: HashMap::new(),
+        }
+    }
+
+    pub fn send<T: 'static>(&mut self, event: T) {
+        self.get_mut_queue::<T>().send(event);
+    }
+
+    /// Events of type `T` that `reader` has not seen yet.
+    pub fn read<'a, T: 'static>(
+        &'a self,
+        reader: &mut EventReader<T>,
+    ) -> impl Iterator<Item = &'a T> {
+        self.get_queue::<T>()
+            .map(|queue| queue.read(reader))
+            .into_iter()
+            .flatten()
+    }
+
+    #[inline]
+    pub fn get_queue<T: 'static>(&self) -> Option<&EventQueue<T>> {
+        self.queues
+            .get(&TypeId::of::<T>())
+            .and_then(|queue| queue.as_any().downcast_ref())
+    }
+
+    pub fn get_mut_queue<T: 'static>(&mut self) -> &mut EventQueue<T> {
+        self.queues
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(EventQueue::<T>::new()))
+            .as_any_mut()
+            .downcast_mut()
+            .unwrap()
+    }
+
+    /// Ends the frame for every queue.
+    pub fn update(&mut self) {
+        for queue in self.queues.values_mut() {
+            queue.update();
+        }
+    }
+}
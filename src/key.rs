@@ -21,7 +21,7 @@ impl KeyboardState {
             .map(|&s| s == ElementState::Pressed)
             .unwrap_or(false)
     }
-    /// Returns true if `key` is released.
+    // Returns true if `key` is released.
     // pub fn is_released(&self, key: &VirtualKeyCode) -> bool {
     //     !self.is_pressed(key)
     // }
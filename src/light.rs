@@ -0,0 +1,52 @@
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+/// How a [`Light`] spreads. Lights shine along the local -Z axis of their
+/// entity, and all but directional ones are placed at its origin.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum LightKind {
+    /// Parallel rays from infinitely far away, like the sun.
+    Directional,
+    /// Shines in every direction, fading out towards `range`.
+    Point { range: f32 },
+    /// Point light limited to a cone. Angles are in degrees from its axis,
+    /// the light fades out between the inner and the outer one.
+    Spot {
+        range: f32,
+        inner_angle: f32,
+        outer_angle: f32,
+    },
+}
+
+/// Light emitted by an entity.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Vec3,
+    pub intensity: f32,
+    /// Only directional and spot lights can cast shadows.
+    pub cast_shadows: bool,
+}
+
+impl Default for Light {
+    fn default() -> Self {
+        Self {
+            kind: LightKind::Point { range: 10. },
+            color: Vec3::ONE,
+            intensity: 1.,
+            cast_shadows: false,
+        }
+    }
+}
+
+impl Light {
+    pub fn new(kind: LightKind, color: Vec3, intensity: f32) -> Self {
+        Self {
+            kind,
+            color,
+            intensity,
+            cast_shadows: false,
+        }
+    }
+}
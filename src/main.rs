@@ -1,15 +1,48 @@
+use std::{
+    path::Path,
+    sync::Arc,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use animation::{AnimationClip, AnimationPlayer, Easing, Interpolation, Keyframe, LoopMode, Track};
 use application::Application;
 use application::*;
+use bounds::{Aabb, Ray, Sphere};
+use clock::Clock;
+use event::{EventReader, InputAction};
+use glam::{Mat4, Quat, Vec2, Vec3, Vec4};
+use glutin::event::{MouseButton, VirtualKeyCode};
 use key::KeyboardState;
-use renderer::Renderer;
-use scene::Scene;
+use light::{Light, LightKind};
+use perf::PerfOverlay;
+use physics::{Collider, RigidBody};
+use prefab::{Overrides, PrefabLibrary};
+use renderer::{
+    mesh::Mesh,
+    texture::{Filter, Texture2D},
+    Culling, Font, GlyphFormat, MaterialTextures, RenderPath, Renderer, TextStyle,
+};
+use scene::{Command, Generator, History, Scene};
+use spatial::IndexKind;
+use transform::Transform;
 
+mod animation;
 mod application;
+mod asset;
+mod bounds;
 mod camera;
+mod clock;
+mod debug;
 mod entity;
+mod event;
 mod key;
+mod light;
+mod perf;
+mod physics;
+mod prefab;
 mod renderer;
 mod scene;
+mod spatial;
 mod transform;
 
 #[cfg(debug_assertions)]
@@ -35,23 +68,229 @@ macro_rules! glcall {
     };
 }
 
+const SCENE_SEED: u64 = 0x5EED;
+/// Keys sent to the scene as [`InputAction`] events.
+const BINDINGS: &[(VirtualKeyCode, &str)] = &[
+    (VirtualKeyCode::Space, "spawn_crate"),
+    (VirtualKeyCode::Delete, "delete"),
+    (VirtualKeyCode::Z, "undo"),
+    (VirtualKeyCode::Y, "redo"),
+    (VirtualKeyCode::R, "rotate"),
+    (VirtualKeyCode::C, "recolor"),
+    (VirtualKeyCode::V, "stack"),
+    (VirtualKeyCode::B, "toggle_dynamic_index"),
+    (VirtualKeyCode::X, "blast"),
+    (VirtualKeyCode::L, "toggle_label"),
+    (VirtualKeyCode::T, "toggle_font"),
+    (VirtualKeyCode::F1, "save_scene"),
+    (VirtualKeyCode::F2, "buffer_report"),
+    (VirtualKeyCode::F3, "toggle_gpu_culling"),
+    (VirtualKeyCode::F4, "toggle_shadows"),
+    (VirtualKeyCode::Comma, "shadow_bias_down"),
+    (VirtualKeyCode::Period, "shadow_bias_up"),
+    (VirtualKeyCode::Minus, "environment_down"),
+    (VirtualKeyCode::Equals, "environment_up"),
+    (VirtualKeyCode::F5, "toggle_post"),
+    (VirtualKeyCode::F6, "toggle_deferred"),
+    (VirtualKeyCode::F7, "spawn_lights"),
+    (VirtualKeyCode::F8, "toggle_skybox"),
+    (VirtualKeyCode::F9, "toggle_debug_draw"),
+    (VirtualKeyCode::F10, "next_render_mode"),
+    (VirtualKeyCode::F11, "toggle_perf"),
+    (VirtualKeyCode::F12, "record_perf"),
+    (VirtualKeyCode::G, "toggle_particles"),
+    (VirtualKeyCode::H, "pause_particles"),
+    (VirtualKeyCode::Key1, "toggle_bloom"),
+    (VirtualKeyCode::Key2, "toggle_tone_mapping"),
+    (VirtualKeyCode::Key3, "toggle_color_grading"),
+    (VirtualKeyCode::Key4, "toggle_vignette"),
+    (VirtualKeyCode::Key5, "toggle_gamma"),
+    (VirtualKeyCode::Key6, "toggle_fxaa"),
+    (VirtualKeyCode::Key7, "toggle_texture_filter"),
+    (VirtualKeyCode::P, "pause"),
+    (VirtualKeyCode::N, "step"),
+    (VirtualKeyCode::LBracket, "slower"),
+    (VirtualKeyCode::RBracket, "faster"),
+    (VirtualKeyCode::Backslash, "reset_time_scale"),
+];
+/// Memory kept for undo steps.
+const HISTORY_LIMIT: usize = 16 << 20;
+const PREFABS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/prefabs.ron");
+const GEM: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/gem.obj");
+const CRATE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/crate.png");
+const SKY: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/sky.hdr");
+/// Files a `--sky` directory holds, from the +X to the -Z face.
+const SKY_FACES: [&str; 6] = ["px.png", "nx.png", "py.png", "ny.png", "pz.png", "nz.png"];
+const FONT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/DejaVuSans.ttf");
+/// Glyph heights in pixels of the fonts T switches between, the coverage one
+/// matching the HUD text.
+const COVERAGE_FONT_SIZE: f32 = 18.;
+const DISTANCE_FIELD_FONT_SIZE: f32 = 48.;
+const LUT: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/resources/lut_warm.png");
+/// Shown under the status lines, wrapped to the HUD width.
+const HELP: &str = "Space spawn, Delete remove, Z undo, Y redo, R rotate, C recolor, \
+                    V stack a crate, B dynamic BVH, X blast, L label, \
+                    T sharp or scalable text, F1 save, F2 buffers, \
+                    F3 GPU culling, F4 shadows, Comma and Period shadow bias, \
+                    Minus and Equals environment light, F5 post, \
+                    1 to 6 post effects, 7 texture filtering, F6 deferred, F7 lights, \
+                    F8 sky, F9 debug draw, F10 render mode, F11 performance, F12 record CSV, \
+                    G particles, H pause particles, hold the left mouse button to attract them, \
+                    P pause, N step while paused, [ and ] time scale, Backslash normal speed";
+/// Where F1 saves the scene, unless it was loaded from a file.
+const SAVED_SCENE: &str = "scene.ron";
+const HUD_WIDTH: f32 = 480.;
+/// Seconds the HUD shows the outcome of a key press for.
+const MESSAGE_SECONDS: f32 = 3.;
+/// Colors the targeted entity cycles through.
+const PALETTE: [Vec4; 4] = [
+    Vec4::new(1., 0.3, 0.3, 1.),
+    Vec4::new(0.3, 1., 0.3, 1.),
+    Vec4::new(0.3, 0.3, 1., 1.),
+    Vec4::ONE,
+];
+/// Distance of the particles' point of mass in front of the camera.
+const POINT_OF_MASS_DISTANCE: f32 = 20.;
+/// Reach of the X key blast, and the impulse it gives at its center.
+const BLAST_RADIUS: f32 = 8.;
+const BLAST_IMPULSE: f32 = 12.;
+
 struct MyApp {
     renderer: Renderer,
     scene: Scene,
+    prefabs: PrefabLibrary,
+    actions: EventReader<InputAction>,
+    history: History,
+    /// Where the scene is saved to.
+    scene_path: String,
+    gl_version: String,
+    perf: PerfOverlay,
+    /// CPU time of the last update.
+    update_ms: f32,
+    /// Whether the left mouse button is held, pulling the particles.
+    attracting: bool,
+    /// Outcome of the last key press and when it happened.
+    message: Option<(String, Instant)>,
 }
 
 impl Application for MyApp {
     fn new(window: &Window) -> Self {
-        let renderer = Renderer::cube(window);
-        let mut scene = Scene::new();
-        scene.add_entities(50, false);
-        Self { renderer, scene }
+        let mut renderer = Renderer::new(window);
+        let gem = Mesh::load_obj(GEM).unwrap_or_else(|e| panic!("Cannot load {GEM}: {e}"));
+        renderer.add_mesh("gem", gem);
+        let texture = Texture2D::load_srgb(CRATE, true)
+            .unwrap_or_else(|e| panic!("Cannot load {CRATE}: {e}"));
+        renderer.add_mesh("crate", Mesh::cube());
+        renderer.set_textures(
+            "crate",
+            MaterialTextures {
+                base_color: Some(texture),
+                ..Default::default()
+            },
+        );
+        // Optional arguments: a scene file, and `--sky` with a directory of
+        // six cube map faces to use instead of the default environment.
+        let mut scene_path = None;
+        let mut sky_faces = None;
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--sky" => sky_faces = args.next(),
+                _ => scene_path = Some(arg),
+            }
+        }
+        let ibl = renderer.get_mut_ibl();
+        match &sky_faces {
+            Some(dir) => ibl
+                .load_environment_faces(SKY_FACES.map(|face| Path::new(dir).join(face)))
+                .unwrap_or_else(|e| panic!("Cannot load the sky faces in {dir}: {e}")),
+            None => ibl
+                .load_environment(SKY)
+                .unwrap_or_else(|e| panic!("Cannot load {SKY}: {e}")),
+        }
+        renderer
+            .get_mut_post()
+            .load_lut(LUT)
+            .unwrap_or_else(|e| panic!("Cannot load {LUT}: {e}"));
+        let mut prefabs = PrefabLibrary::load(PREFABS)
+            .unwrap_or_else(|e| panic!("Cannot load prefabs {PREFABS}: {e}"));
+        let mut scene = match &scene_path {
+            Some(path) => {
+                Scene::load(path).unwrap_or_else(|e| panic!("Cannot load scene {path}: {e}"))
+            }
+            None => {
+                let mut scene = Scene::new();
+                scene.get_mut_debug().set_enabled(false);
+                scene.add_entities(&Generator::grid(50, 5.), SCENE_SEED, false);
+                add_falling_cubes(&mut scene);
+                add_animated_spiral(&mut scene);
+                add_prefabs(&mut scene, &mut prefabs);
+                add_sun(&mut scene);
+                add_shell(&mut scene);
+                scene
+            }
+        };
+        for (name, bounds) in renderer.get_mesh_bounds() {
+            scene.set_mesh_bounds(name, *bounds);
+        }
+        let labels: Vec<_> = scene
+            .get_static_entities()
+            .iter()
+            .chain(scene.get_dynamic_entities())
+            .filter_map(|e| Some((e.get_id(), e.get_name()?.to_owned())))
+            .collect();
+        for (id, name) in labels {
+            renderer.get_mut_text().set_label(id, name, label_style());
+        }
+        Self {
+            renderer,
+            scene,
+            prefabs,
+            actions: EventReader::new(),
+            history: History::new(HISTORY_LIMIT),
+            scene_path: scene_path.unwrap_or_else(|| SAVED_SCENE.to_owned()),
+            gl_version: gl_version(),
+            perf: PerfOverlay::new(),
+            update_ms: 0.,
+            attracting: false,
+            message: None,
+        }
     }
 
     #[inline]
-    fn update(&mut self, key_state: &KeyboardState) {
-        self.scene.get_mut_camera().handle_input(key_state);
-        self.scene.update();
+    fn update(&mut self, key_state: &KeyboardState, clock: &mut Clock) {
+        let started = Instant::now();
+        // The previous frame, now that it was drawn.
+        let stats = self.renderer.get_stats();
+        self.perf.record(
+            clock.get_total_time(),
+            clock.get_real_delta() * 1000.,
+            self.update_ms + stats.cpu_ms,
+            stats,
+        );
+
+        self.scene
+            .get_mut_camera()
+            .handle_input(key_state, clock.get_real_delta());
+        if let Err(e) = self.prefabs.reload_if_changed(&mut self.scene) {
+            eprintln!("Cannot reload prefabs: {e}");
+        }
+        self.handle_actions(clock);
+        self.scene.update(clock);
+        let camera = self.scene.get_camera();
+        let point_of_mass = camera.position + camera.direction.normalize() * POINT_OF_MASS_DISTANCE;
+        let particles = self.renderer.get_mut_particles();
+        particles.set_point_of_mass(point_of_mass, self.attracting);
+        particles.update(clock.get_delta());
+        if self.scene.get_debug().is_enabled() {
+            draw_debug(&mut self.scene);
+        }
+        let hud_bottom = self.draw_hud(clock);
+        self.perf.draw(
+            self.renderer.get_mut_text(),
+            Vec2::new(10., hud_bottom + 10.),
+        );
+        self.update_ms = started.elapsed().as_secs_f32() * 1000.;
     }
 
     #[inline]
@@ -66,12 +305,775 @@ impl Application for MyApp {
     fn on_mouse_move(&mut self, delta: &(f64, f64)) {
         self.scene.get_mut_camera().update(delta)
     }
+
+    #[inline]
+    fn on_mouse_button(&mut self, button: MouseButton, pressed: bool) {
+        if button == MouseButton::Left {
+            self.attracting = pressed;
+        }
+    }
+
+    fn on_key(&mut self, key: VirtualKeyCode, pressed: bool) {
+        if let Some(&(_, name)) = BINDINGS.iter().find(|(k, _)| *k == key) {
+            self.scene
+                .get_mut_events()
+                .send(InputAction { name, pressed });
+        }
+    }
+
     #[inline]
     fn on_resize(&mut self, window: &Window) {
         self.renderer.on_resize(window);
     }
 }
 
+impl MyApp {
+    /// Status of the renderer, scene and clock, and the key bindings.
+    /// Returns where the text ends, in pixels from the top.
+    fn draw_hud(&mut self, clock: &Clock) -> f32 {
+        let mut status = format!(
+            "OpenGL {}\nFrame {}, tick {}\n{:?} path, {:?} mode, shadows {}\n\
+             {} entities, {} in view, {} lights",
+            self.gl_version,
+            clock.get_frame_count(),
+            clock.get_tick_count(),
+            self.renderer.get_path(),
+            self.renderer.get_mode(),
+            if self.renderer.get_shadow_settings().enabled {
+                "on"
+            } else {
+                "off"
+            },
+            self.scene.get_static_entities().len() + self.scene.get_dynamic_entities().len(),
+            self.scene
+                .query_frustum(&self.renderer.get_frustum(self.scene.get_camera()))
+                .len(),
+            self.scene.get_lights().count(),
+        );
+        let post = self.renderer.get_post();
+        if post.is_enabled() {
+            let stages: Vec<_> = post
+                .get_stages()
+                .iter()
+                .filter(|s| s.enabled)
+                .map(|s| s.effect.get_name())
+                .collect();
+            status.push_str(&format!("\nPost: {}", stages.join(", ")));
+        }
+        if clock.is_limited() {
+            status.push_str(&format!(
+                "\nGame time limited to {} steps per frame",
+                Clock::MAX_FIXED_STEPS
+            ));
+        }
+        status.push_str(&format!(
+            "\nHistory {} KiB{}{}",
+            self.history.get_memory_usage() / 1024,
+            if self.history.can_undo() {
+                ", can undo"
+            } else {
+                ""
+            },
+            if self.history.can_redo() {
+                ", can redo"
+            } else {
+                ""
+            },
+        ));
+        match &self.message {
+            Some((message, shown)) if shown.elapsed().as_secs_f32() < MESSAGE_SECONDS => {
+                status.push('\n');
+                status.push_str(message);
+            }
+            Some(_) => self.message = None,
+            None => {}
+        }
+        let text = self.renderer.get_mut_text();
+        let style = TextStyle {
+            size: 18.,
+            max_width: Some(HUD_WIDTH),
+            ..Default::default()
+        };
+        let size = text.draw_text(&status, Vec2::splat(10.), &style);
+        let help = TextStyle {
+            size: 14.,
+            color: Vec4::new(0.8, 0.8, 0.8, 1.),
+            ..style
+        };
+        let top = 20. + size.y;
+        top + text.draw_text(HELP, Vec2::new(10., top), &help).y
+    }
+
+    /// Shows `message` in the HUD status for a few seconds.
+    fn show_message(&mut self, message: String) {
+        self.message = Some((message, Instant::now()));
+    }
+
+    fn handle_actions(&mut self, clock: &mut Clock) {
+        let pressed: Vec<_> = self
+            .scene
+            .get_events()
+            .read(&mut self.actions)
+            .filter(|a| a.pressed)
+            .map(|a| a.name)
+            .collect();
+        for name in pressed {
+            let camera = self.scene.get_camera();
+            let view = Ray::new(camera.position, camera.direction);
+            match name {
+                "spawn_crate" => {
+                    let position = view.get_point(5.);
+                    let room = Aabb::from_center_half_extents(position, Vec3::splat(0.75));
+                    if !self.scene.query_aabb(&room).is_empty() {
+                        self.show_message("No room for a crate".to_owned());
+                        continue;
+                    }
+                    match self.prefabs.instantiate(
+                        &mut self.scene,
+                        "crate",
+                        position,
+                        &Overrides::default(),
+                    ) {
+                        Ok(id) => {
+                            self.renderer.get_mut_text().set_label(
+                                id,
+                                format!("crate #{}", id.0),
+                                label_style(),
+                            );
+                            if let Some(command) = Command::spawned(&self.scene, id) {
+                                self.history.push(command);
+                            }
+                        }
+                        Err(e) => eprintln!("Cannot spawn crate: {e}"),
+                    }
+                }
+                "delete" => {
+                    if let Some((id, distance)) = self.scene.raycast(&view, 100.) {
+                        let (start, end) = (view.get_point(1.), view.get_point(distance));
+                        self.scene
+                            .get_mut_debug()
+                            .arrow(start, end, Vec4::new(1., 0.2, 0.2, 1.))
+                            .duration(2.);
+                        self.history.despawn(&mut self.scene, id);
+                    }
+                }
+                "undo" => {
+                    self.history.undo(&mut self.scene);
+                }
+                "redo" => {
+                    self.history.redo(&mut self.scene);
+                }
+                "rotate" => {
+                    let target = self.scene.raycast(&view, 100.);
+                    if let Some(entity) = target.and_then(|(id, _)| self.scene.get_entity(id)) {
+                        let id = entity.get_id();
+                        let mut transform = *entity.get_transform();
+                        transform.set_rotation(
+                            Quat::from_rotation_y(std::f32::consts::FRAC_PI_4)
+                                * transform.get_rotation(),
+                        );
+                        self.history.set_transform(&mut self.scene, id, transform);
+                    }
+                }
+                "recolor" => {
+                    let target = self.scene.raycast(&view, 100.);
+                    if let Some(entity) = target.and_then(|(id, _)| self.scene.get_entity(id)) {
+                        let (id, color) = (entity.get_id(), entity.get_color());
+                        let next = PALETTE
+                            .iter()
+                            .position(|&c| c == color)
+                            .map_or(0, |i| (i + 1) % PALETTE.len());
+                        self.history
+                            .edit(&mut self.scene, id, |e| e.set_color(PALETTE[next]));
+                    }
+                }
+                "stack" => {
+                    if let Some((parent, _)) = self.scene.raycast(&view, 100.) {
+                        // Undone as one step with the edits that finish it.
+                        self.history.begin_group();
+                        let id = self.history.spawn(
+                            &mut self.scene,
+                            Transform::with_pos(Vec3::Y),
+                            false,
+                        );
+                        // Looks like the crate prefab, but without its rigid
+                        // body so it stays on its parent, and at its parent's
+                        // scale.
+                        let prefab = self.prefabs.get("crate").cloned().unwrap_or_default();
+                        let assets = self.scene.get_mut_assets();
+                        let (mesh, material) =
+                            (assets.mesh(&prefab.mesh), assets.material(&prefab.material));
+                        let to = Transform::with_pos(Vec3::Y);
+                        let from = Transform::new(Vec3::Y, Quat::IDENTITY, Vec3::ZERO);
+                        let grow = AnimationClip::tween(&from, &to, 0.4, Easing::BackOut);
+                        self.history.edit(&mut self.scene, id, |e| {
+                            e.set_mesh(mesh);
+                            e.set_material(material);
+                            e.set_color(prefab.color);
+                            e.set_animation(Some(AnimationPlayer::new(
+                                Arc::new(grow),
+                                LoopMode::Once,
+                            )));
+                        });
+                        self.history.set_parent(&mut self.scene, id, Some(parent));
+                        self.history.end_group();
+                        self.show_message(format!("Stacked a crate on #{}", parent.0));
+                    }
+                }
+                "blast" => {
+                    if let Some((_, distance)) = self.scene.raycast(&view, 100.) {
+                        let center = view.get_point(distance);
+                        let sphere = Sphere {
+                            center,
+                            radius: BLAST_RADIUS,
+                        };
+                        let mut pushed = 0;
+                        for id in self.scene.query_sphere(&sphere) {
+                            // Borrowing a static entity mutably would index it again.
+                            let offset = match self.scene.get_entity(id) {
+                                Some(e) if e.get_rigid_body().is_some() => {
+                                    e.get_transform().get_position() - center
+                                }
+                                _ => continue,
+                            };
+                            let falloff = 1. - (offset.length() / BLAST_RADIUS).min(1.);
+                            let direction = (offset.normalize_or_zero() + Vec3::Y).normalize();
+                            let body = self
+                                .scene
+                                .get_mut_entity(id)
+                                .and_then(|e| e.get_mut_rigid_body());
+                            if let Some(body) = body {
+                                body.apply_impulse(direction * BLAST_IMPULSE * falloff);
+                                pushed += 1;
+                            }
+                        }
+                        self.show_message(format!("Blasted {pushed} bodies"));
+                    }
+                }
+                "toggle_label" => {
+                    let target = self.scene.raycast(&view, 100.);
+                    if let Some(entity) = target.and_then(|(id, _)| self.scene.get_entity(id)) {
+                        let id = entity.get_id();
+                        let name = entity
+                            .get_name()
+                            .map_or_else(|| format!("#{}", id.0), str::to_owned);
+                        let text = self.renderer.get_mut_text();
+                        if !text.remove_label(id) {
+                            text.set_label(id, name, label_style());
+                        }
+                    }
+                }
+                "toggle_font" => {
+                    let text = self.renderer.get_mut_text();
+                    let (format, size) = match text.get_font().get_format() {
+                        GlyphFormat::Coverage => (
+                            GlyphFormat::DistanceField { spread: 6 },
+                            DISTANCE_FIELD_FONT_SIZE,
+                        ),
+                        GlyphFormat::DistanceField { .. } => {
+                            (GlyphFormat::Coverage, COVERAGE_FONT_SIZE)
+                        }
+                    };
+                    match Font::load(FONT, size, format) {
+                        Ok(font) => {
+                            text.set_font(font);
+                            self.show_message(format!("{format:?} glyphs at {size} pixels"));
+                        }
+                        Err(e) => eprintln!("Cannot load {FONT}: {e}"),
+                    }
+                }
+                "toggle_dynamic_index" => {
+                    let kind = match self.scene.get_dynamic_index_kind() {
+                        IndexKind::Grid { .. } => IndexKind::Bvh,
+                        IndexKind::Bvh => Scene::DEFAULT_DYNAMIC_INDEX,
+                    };
+                    self.scene.set_dynamic_index(kind);
+                    self.show_message(format!(
+                        "{kind:?} for {} dynamic entities",
+                        self.scene.get_dynamic_index().len()
+                    ));
+                }
+                "toggle_gpu_culling" => {
+                    let culling = match self.renderer.get_culling() {
+                        Culling::Cpu => Culling::Gpu,
+                        Culling::Gpu => Culling::Cpu,
+                    };
+                    self.show_message(format!("Culling on the {culling:?}"));
+                    self.renderer.set_culling(culling);
+                }
+                "toggle_deferred" => {
+                    let path = match self.renderer.get_path() {
+                        RenderPath::Forward => RenderPath::Deferred,
+                        RenderPath::Deferred => RenderPath::Forward,
+                    };
+                    self.show_message(format!("{path:?} rendering"));
+                    self.renderer.set_path(path);
+                }
+                "next_render_mode" => {
+                    let mode = self.renderer.get_mode().next();
+                    self.show_message(format!("{mode:?} render mode"));
+                    self.renderer.set_mode(mode);
+                }
+                "spawn_lights" => {
+                    add_light_field(&mut self.scene);
+                    self.show_message(format!("{} lights", self.scene.get_lights().count()));
+                }
+                "toggle_skybox" => {
+                    let skybox = self.renderer.get_mut_skybox();
+                    skybox.set_enabled(!skybox.is_enabled());
+                    let message =
+                        format!("Skybox {}", if skybox.is_enabled() { "on" } else { "off" });
+                    self.show_message(message);
+                }
+                "toggle_particles" => {
+                    let particles = self.renderer.get_mut_particles();
+                    particles.set_enabled(!particles.is_enabled());
+                    let message = format!(
+                        "Particles {}",
+                        if particles.is_enabled() { "on" } else { "off" }
+                    );
+                    self.show_message(message);
+                }
+                "pause_particles" => {
+                    let particles = self.renderer.get_mut_particles();
+                    particles.set_running(!particles.is_running());
+                    let message = format!(
+                        "Particles {}",
+                        if particles.is_running() {
+                            "resumed"
+                        } else {
+                            "paused"
+                        }
+                    );
+                    self.show_message(message);
+                }
+                "toggle_debug_draw" => {
+                    let debug = self.scene.get_mut_debug();
+                    debug.set_enabled(!debug.is_enabled());
+                    let message = format!(
+                        "Debug draw {}",
+                        if debug.is_enabled() { "on" } else { "off" }
+                    );
+                    self.show_message(message);
+                }
+                "toggle_perf" => {
+                    self.perf.set_visible(!self.perf.is_visible());
+                }
+                "record_perf" => {
+                    if self.perf.is_recording() {
+                        match self.perf.stop_recording() {
+                            Ok(()) => self.show_message("Stopped recording performance".to_owned()),
+                            Err(e) => eprintln!("Cannot save performance samples: {e}"),
+                        }
+                    } else {
+                        let seconds = SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .map_or(0, |d| d.as_secs());
+                        let path = format!("perf-{seconds}.csv");
+                        match self.perf.start_recording(&path) {
+                            Ok(()) => self.show_message(format!("Recording performance to {path}")),
+                            Err(e) => eprintln!("Cannot record to {path}: {e}"),
+                        }
+                    }
+                }
+                "toggle_shadows" => {
+                    let settings = self.renderer.get_mut_shadow_settings();
+                    settings.enabled = !settings.enabled;
+                    let message =
+                        format!("Shadows {}", if settings.enabled { "on" } else { "off" });
+                    self.show_message(message);
+                }
+                "shadow_bias_down" | "shadow_bias_up" => {
+                    let settings = self.renderer.get_mut_shadow_settings();
+                    let factor = if name == "shadow_bias_up" { 2. } else { 0.5 };
+                    settings.depth_bias *= factor;
+                    settings.normal_bias *= factor;
+                    let message = format!(
+                        "Shadow depth bias {}, normal bias {}",
+                        settings.depth_bias, settings.normal_bias
+                    );
+                    self.show_message(message);
+                }
+                "environment_down" | "environment_up" => {
+                    let ibl = self.renderer.get_mut_ibl();
+                    let factor = if name == "environment_up" { 1.25 } else { 0.8 };
+                    ibl.set_intensity(ibl.get_intensity() * factor);
+                    let message = format!("Environment light {:.2}", ibl.get_intensity());
+                    self.show_message(message);
+                }
+                "toggle_post" => {
+                    let post = self.renderer.get_mut_post();
+                    post.set_enabled(!post.is_enabled());
+                    let message = format!(
+                        "Post-processing {}",
+                        if post.is_enabled() { "on" } else { "off" }
+                    );
+                    self.show_message(message);
+                }
+                "pause" => clock.toggle_pause(),
+                "step" => clock.step(),
+                "slower" => clock.set_time_scale(clock.get_time_scale() * 0.5),
+                "faster" => clock.set_time_scale(clock.get_time_scale() * 2.),
+                "reset_time_scale" => clock.set_time_scale(1.),
+                "toggle_texture_filter" => {
+                    let mut settings = *self.renderer.get_sampler_settings();
+                    let filter = match settings.mag_filter {
+                        Filter::Linear => Filter::Nearest,
+                        Filter::Nearest => Filter::Linear,
+                    };
+                    settings.min_filter = filter;
+                    settings.mag_filter = filter;
+                    self.renderer.set_sampler_settings(&settings);
+                    self.show_message(format!("{filter:?} texture filtering"));
+                }
+                "save_scene" => match self.scene.save(&self.scene_path) {
+                    Ok(()) => self.show_message(format!("Saved the scene to {}", self.scene_path)),
+                    Err(e) => eprintln!("Cannot save the scene to {}: {e}", self.scene_path),
+                },
+                "buffer_report" => {
+                    let usage: Vec<_> = self
+                        .renderer
+                        .get_buffer_usage()
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect();
+                    self.show_message(usage.join("\n"));
+                }
+                _ => {
+                    let stage = name
+                        .strip_prefix("toggle_")
+                        .and_then(|effect| self.renderer.get_mut_post().get_mut_stage(effect));
+                    if let Some(stage) = stage {
+                        stage.enabled = !stage.enabled;
+                        let message = format!(
+                            "{} {}",
+                            stage.effect.get_name(),
+                            if stage.enabled { "on" } else { "off" }
+                        );
+                        self.show_message(message);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Labels are half a unit high, wrapped at four.
+fn label_style() -> TextStyle {
+    TextStyle {
+        size: 0.5,
+        color: Vec4::new(1., 1., 0.8, 1.),
+        max_width: Some(4.),
+    }
+}
+
+/// World axes and ground grid, the lights, the bounds of the entity in front
+/// of the camera and of the entities near it, linked to it, and the physics
+/// contacts.
+fn draw_debug(scene: &mut Scene) {
+    let camera = scene.get_camera();
+    let view = Ray::new(camera.position, camera.direction);
+    let target_id = scene.raycast(&view, 100.).map(|(id, _)| id);
+    let target = target_id
+        .and_then(|id| scene.get_entity(id))
+        .map(|e| scene.get_world_bounds(e));
+    let neighbours: Vec<_> = target
+        .map(|bounds| {
+            let sphere = Sphere {
+                center: bounds.get_center(),
+                radius: 10.,
+            };
+            scene.query_sphere(&sphere)
+        })
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|id| Some(scene.get_world_bounds(scene.get_entity(id)?)))
+        .collect();
+    let contacts = scene.get_physics().get_contacts().to_vec();
+    let lights: Vec<_> = scene
+        .get_lights()
+        .map(|(e, light)| (scene.get_world_matrix(e), light.kind, light.color))
+        .collect();
+
+    let debug = scene.get_mut_debug();
+    debug.axes(&Mat4::IDENTITY, 5.).on_top();
+    debug.grid(Vec3::ZERO, 5., 50, Vec4::new(1., 1., 1., 0.2));
+    for (world, kind, color) in lights {
+        let position = world.w_axis.truncate();
+        let color = color.normalize_or_zero().extend(1.);
+        match kind {
+            LightKind::Point { .. } => {
+                let sphere = Sphere {
+                    center: position,
+                    radius: 0.3,
+                };
+                debug.sphere(&sphere, color);
+            }
+            LightKind::Spot {
+                range, outer_angle, ..
+            } => {
+                // The square pyramid around the cone of light.
+                let direction = world.transform_vector3(Vec3::NEG_Z).normalize_or_zero();
+                let projection = Mat4::perspective_rh_gl(
+                    2. * outer_angle.clamp(1., 89.).to_radians(),
+                    1.,
+                    0.1,
+                    range.max(0.2),
+                );
+                let up = direction.any_orthonormal_vector();
+                let view = Mat4::look_at_rh(position, position + direction, up);
+                debug.frustum(&(projection * view), color.truncate().extend(0.5));
+            }
+            LightKind::Directional => {
+                let direction = world.transform_vector3(Vec3::NEG_Z).normalize_or_zero();
+                debug.arrow(position, position + direction * 2., color);
+            }
+        }
+    }
+    for bounds in neighbours.iter() {
+        debug.aabb(bounds, Vec4::new(1., 1., 0., 0.3));
+        if let Some(target) = target {
+            let color = Vec4::new(1., 1., 0., 0.3);
+            debug.line(target.get_center(), bounds.get_center(), color);
+        }
+    }
+    if let Some(bounds) = target {
+        debug.aabb(&bounds, Vec4::new(1., 1., 0., 1.)).on_top();
+    }
+    // Contact normals, longer the deeper the bodies overlap. Those of the
+    // targeted entity are drawn on top.
+    for contact in contacts {
+        let end = contact.point + contact.normal * (0.5 + contact.depth * 10.);
+        if target_id.is_some_and(|id| id == contact.a || id == contact.b) {
+            debug
+                .arrow(contact.point, end, Vec4::new(1., 0.5, 0., 1.))
+                .on_top();
+        } else {
+            debug.arrow(contact.point, end, Vec4::new(0., 1., 1., 0.5));
+        }
+    }
+}
+
+/// A pile of rigid bodies dropped onto a static floor below the grid.
+fn add_falling_cubes(scene: &mut Scene) {
+    let floor = scene.spawn(
+        Transform::new(
+            Vec3::new(0., -160., 0.),
+            Quat::IDENTITY,
+            Vec3::new(60., 1., 60.),
+        ),
+        true,
+    );
+    if let Some(floor) = scene.get_mut_entity(floor) {
+        floor.set_collider(Some(Collider::cube()));
+    }
+
+    let drop_zone = Generator::ScatterBox {
+        count: 300,
+        min: Vec3::new(-20., -150., -20.),
+        max: Vec3::new(20., -130., 20.),
+    };
+    for pos in drop_zone.generate(SCENE_SEED) {
+        let id = scene.spawn(Transform::with_pos(pos), false);
+        if let Some(cube) = scene.get_mut_entity(id) {
+            cube.set_rigid_body(Some(RigidBody::new(1.)));
+            cube.set_collider(Some(Collider::cube()));
+        }
+    }
+}
+
+/// Lamps around the corners of the floor with gems spread on the ground
+/// between them, a stack of crates and a few balls.
+fn add_prefabs(scene: &mut Scene, prefabs: &mut PrefabLibrary) {
+    for (x, z) in [(-25., -25.), (25., -25.), (-25., 25.), (25., 25.)] {
+        prefabs
+            .instantiate(
+                scene,
+                "lamp",
+                Vec3::new(x, -156.5, z),
+                &Overrides::default(),
+            )
+            .expect("Missing lamp prefab");
+    }
+    for i in 0..5 {
+        let overrides = Overrides {
+            name: Some(format!("crate {i}")),
+            rotation: Some(Quat::from_rotation_y(i as f32 * 0.4)),
+            ..Default::default()
+        };
+        prefabs
+            .instantiate(
+                scene,
+                "crate",
+                Vec3::new(0., -150. + 2. * i as f32, 30.),
+                &overrides,
+            )
+            .expect("Missing crate prefab");
+    }
+    let gems = Generator::PoissonDisk {
+        min: Vec3::new(-20., -158.5, -20.),
+        max: Vec3::new(20., -158.4, 20.),
+        radius: 8.,
+        attempts: 30,
+    };
+    for position in gems.generate(SCENE_SEED) {
+        prefabs
+            .instantiate(scene, "gem", position, &Overrides::default())
+            .expect("Missing gem prefab");
+    }
+    prefabs
+        .instantiate(
+            scene,
+            "spotlight",
+            Vec3::new(0., -135., 30.),
+            &Overrides::default(),
+        )
+        .expect("Missing spotlight prefab");
+    let balls = Generator::ScatterSphere {
+        count: 8,
+        center: Vec3::new(0., -140., 0.),
+        radius: 8.,
+    };
+    for position in balls.generate(SCENE_SEED) {
+        prefabs
+            .instantiate(scene, "ball", position, &Overrides::default())
+            .expect("Missing ball prefab");
+    }
+}
+
+/// Small colored point lights scattered through the grid, to compare the
+/// render paths with many lights.
+fn add_light_field(scene: &mut Scene) {
+    const COUNT: usize = 1024;
+    let field = Generator::ScatterBox {
+        count: COUNT,
+        min: Vec3::splat(-125.),
+        max: Vec3::splat(125.),
+    };
+    let seed = SCENE_SEED + scene.get_lights().count() as u64;
+    let mesh = scene.get_mut_assets().mesh("sphere");
+    let material = scene.get_mut_assets().material("emissive");
+    for (i, position) in field.generate(seed).into_iter().enumerate() {
+        let hue = i as f32 * 0.618_034 % 1.;
+        let color = Vec3::from_array(
+            [0., 1. / 3., 2. / 3.]
+                .map(|offset| ((hue + offset) * std::f32::consts::TAU).cos() * 0.5 + 0.5),
+        )
+        .max(Vec3::splat(0.1));
+        let id = scene.spawn(
+            Transform::new(position, Quat::IDENTITY, Vec3::splat(0.4)),
+            true,
+        );
+        if let Some(light) = scene.get_mut_entity(id) {
+            light.set_mesh(mesh);
+            light.set_material(material);
+            light.set_color(color.extend(1.));
+            light.set_light(Some(Light::new(
+                LightKind::Point { range: 12. },
+                color,
+                40.,
+            )));
+        }
+    }
+}
+
+/// A directional light, drawn as a sphere far away in the direction it
+/// shines from.
+fn add_sun(scene: &mut Scene) {
+    let direction = Vec3::new(-0.4, -1., -0.3).normalize();
+    let id = scene.spawn(
+        Transform::new(
+            direction * -400.,
+            Quat::from_rotation_arc(Vec3::NEG_Z, direction),
+            Vec3::splat(30.),
+        ),
+        true,
+    );
+    let mesh = scene.get_mut_assets().mesh("sphere");
+    let material = scene.get_mut_assets().material("emissive");
+    if let Some(sun) = scene.get_mut_entity(id) {
+        sun.set_mesh(mesh);
+        sun.set_material(material);
+        sun.set_color(Vec4::new(1., 0.95, 0.8, 1.));
+        sun.set_light(Some(Light {
+            cast_shadows: true,
+            ..Light::new(LightKind::Directional, Vec3::new(1., 0.95, 0.85), 0.8)
+        }));
+    }
+}
+
+/// Static Blinn-Phong spheres on a shell around the whole scene.
+fn add_shell(scene: &mut Scene) {
+    let shell = Generator::SphereShell {
+        count: 400,
+        center: Vec3::ZERO,
+        radius: 250.,
+        thickness: 20.,
+    };
+    let mesh = scene.get_mut_assets().mesh("sphere");
+    let material = scene.get_mut_assets().material("blinn_phong_glossy");
+    for position in shell.generate(SCENE_SEED) {
+        let id = scene.spawn(
+            Transform::new(position, Quat::IDENTITY, Vec3::splat(3.)),
+            true,
+        );
+        if let Some(sphere) = scene.get_mut_entity(id) {
+            sphere.set_mesh(mesh);
+            sphere.set_material(material);
+        }
+    }
+}
+
+/// A flat spiral of cubes above the grid, winding out from its center once,
+/// bobbing and changing color out of phase.
+fn add_animated_spiral(scene: &mut Scene) {
+    const COUNT: usize = 24;
+    const PERIOD: f32 = 1.5;
+    let spiral = Generator::Spiral {
+        count: COUNT,
+        center: Vec3::new(0., 140., 0.),
+        turns: 1.,
+        radius: 30.,
+        height: 0.,
+        jitter: 0.,
+    };
+    for (i, pos) in spiral.generate(SCENE_SEED).into_iter().enumerate() {
+        // Position keyframes are absolute, so every cube needs its own clip.
+        let clip = AnimationClip {
+            position: Some(Track::new(
+                Interpolation::Linear,
+                vec![
+                    Keyframe::eased(0., pos, Easing::SineInOut),
+                    Keyframe::new(PERIOD, pos + Vec3::new(0., 6., 0.)),
+                ],
+            )),
+            rotation: Some(Track::new(
+                Interpolation::Linear,
+                vec![
+                    Keyframe::new(0., Quat::IDENTITY),
+                    Keyframe::new(PERIOD, Quat::from_rotation_y(std::f32::consts::PI)),
+                ],
+            )),
+            scale: None,
+            color: Some(Track::new(
+                Interpolation::Cubic,
+                vec![
+                    Keyframe::new(0., Vec4::new(1., 0.3, 0.3, 1.)),
+                    Keyframe::new(PERIOD / 2., Vec4::new(0.3, 1., 0.3, 1.)),
+                    Keyframe::new(PERIOD, Vec4::new(0.3, 0.3, 1., 1.)),
+                ],
+            )),
+        };
+        let offset = 2. * PERIOD * i as f32 / COUNT as f32;
+        let id = scene.spawn(Transform::with_pos(pos), false);
+        if let Some(cube) = scene.get_mut_entity(id) {
+            cube.set_animation(Some(
+                AnimationPlayer::new(Arc::new(clip), LoopMode::PingPong).with_time(offset),
+            ));
+        }
+    }
+}
+
 fn main() {
     AppBuilder::build::<MyApp>(Window {
         width: 1400.,
@@ -0,0 +1,228 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use glam::{Vec2, Vec4};
+
+use crate::renderer::{FrameStats, TextRenderer, TextStyle};
+
+/// Timing and renderer counters of one frame.
+#[derive(Clone, Copy, Debug)]
+struct Sample {
+    /// Seconds since the start.
+    time: f64,
+    /// Time since the previous frame.
+    frame_ms: f32,
+    /// CPU time of the update and of issuing the frame.
+    cpu_ms: f32,
+    stats: FrameStats,
+}
+
+/// Frame timing and renderer counters of the last few seconds, drawn as text
+/// and a graph over the scene, and optionally written to a CSV file for
+/// benchmark runs.
+pub struct PerfOverlay {
+    history: VecDeque<Sample>,
+    visible: bool,
+    csv: Option<BufWriter<File>>,
+}
+
+impl PerfOverlay {
+    /// Seconds of history kept and graphed.
+    const HISTORY: f64 = 5.;
+    /// Seconds the averaged timings span.
+    const AVERAGE: f64 = 1.;
+    const GRAPH_SIZE: Vec2 = Vec2::new(300., 80.);
+    /// Frame time at the top of the graph.
+    const GRAPH_MAX_MS: f32 = 50.;
+    /// Frame times of 60 and 30 frames per second, marked on the graph.
+    const TARGETS_MS: [f32; 2] = [1000. / 60., 1000. / 30.];
+    const CSV_HEADER: &'static str = "time_s,frame_ms,fps,cpu_ms,gpu_ms,draw_calls,\
+                                      instances,static_instances,culled,triangles,upload_bytes";
+
+    pub fn new() -> Self {
+        Self {
+            history: VecDeque::new(),
+            visible: false,
+            csv: None,
+        }
+    }
+
+    #[inline]
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    #[inline]
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    #[inline]
+    pub fn is_recording(&self) -> bool {
+        self.csv.is_some()
+    }
+
+    /// Writes every following frame to a new CSV file at `path`, until
+    /// [`PerfOverlay::stop_recording`]. Missing values are left empty.
+    pub fn start_recording(&mut self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut csv = BufWriter::new(File::create(path)?);
+        writeln!(csv, "{}", Self::CSV_HEADER)?;
+        self.csv = Some(csv);
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) -> io::Result<()> {
+        match self.csv.take() {
+            Some(mut csv) => csv.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Adds the frame that ended at `time` seconds, `frame_ms` after the
+    /// previous one.
+    pub fn record(&mut self, time: f64, frame_ms: f32, cpu_ms: f32, stats: &FrameStats) {
+        let sample = Sample {
+            time,
+            frame_ms,
+            cpu_ms,
+            stats: *stats,
+        };
+        if let Some(csv) = self.csv.as_mut() {
+            if let Err(e) = write_row(csv, &sample) {
+                eprintln!("Cannot write performance samples: {e}");
+                self.csv = None;
+            }
+        }
+        self.history.push_back(sample);
+        while self
+            .history
+            .front()
+            .is_some_and(|s| s.time < time - Self::HISTORY)
+        {
+            self.history.pop_front();
+        }
+    }
+
+    /// Queues the overlay at `position` pixels from the top-left of the
+    /// window if visible.
+    pub fn draw(&self, text: &mut TextRenderer, position: Vec2) {
+        let Some(last) = self.history.back() else {
+            return;
+        };
+        if !self.visible {
+            return;
+        }
+        let recent: Vec<_> = self
+            .history
+            .iter()
+            .filter(|s| s.time >= last.time - Self::AVERAGE)
+            .collect();
+        let average = |f: &dyn Fn(&Sample) -> Option<f32>| {
+            let values: Vec<_> = recent.iter().filter_map(|s| f(s)).collect();
+            (!values.is_empty()).then(|| values.iter().sum::<f32>() / values.len() as f32)
+        };
+        let frame_ms = average(&|s| Some(s.frame_ms)).unwrap_or(0.);
+        let cpu_ms = average(&|s| Some(s.cpu_ms)).unwrap_or(0.);
+        let gpu = match average(&|s| s.stats.gpu_ms) {
+            Some(ms) => format!("{ms:.2} ms"),
+            None => "-".to_owned(),
+        };
+        let stats = &last.stats;
+        let culled = match stats.culled {
+            Some(culled) => format!(", {culled} dynamic culled"),
+            None => String::new(),
+        };
+        let triangles = match stats.triangles {
+            Some(triangles) => triangles.to_string(),
+            None => "-".to_owned(),
+        };
+        let report = format!(
+            "{:.0} FPS, {frame_ms:.2} ms\nCPU {cpu_ms:.2} ms, GPU {gpu}\n\
+             {} draw calls, {} instances, {} static{culled}\n\
+             {triangles} triangles, {} KiB uploaded{}",
+            1000. / frame_ms.max(f32::EPSILON),
+            stats.draw_calls,
+            stats.instances,
+            stats.statics,
+            stats.upload_bytes / 1024,
+            if self.is_recording() {
+                "\nRecording"
+            } else {
+                ""
+            },
+        );
+        let style = TextStyle {
+            size: 16.,
+            color: Vec4::new(0.6, 1., 0.6, 1.),
+            ..Default::default()
+        };
+        let size = text.draw_text(&report, position, &style);
+        self.draw_graph(text, position + Vec2::new(0., size.y + 8.));
+    }
+
+    /// Frame times of the history as bars, the CPU part darker, over lines
+    /// at the target frame times.
+    fn draw_graph(&self, text: &mut TextRenderer, position: Vec2) {
+        let size = Self::GRAPH_SIZE;
+        let bottom = position.y + size.y;
+        text.draw_rect(position, position + size, Vec4::new(0., 0., 0., 0.5));
+        let Some(last) = self.history.back() else {
+            return;
+        };
+        let height = |ms: f32| (ms / Self::GRAPH_MAX_MS).min(1.) * size.y;
+        let start = last.time - Self::HISTORY;
+        let mut previous_x = position.x;
+        for sample in self.history.iter() {
+            let x = position.x + ((sample.time - start) / Self::HISTORY) as f32 * size.x;
+            let (left, right) = (previous_x, x.max(previous_x + 1.));
+            previous_x = right;
+            let color = if sample.frame_ms <= Self::TARGETS_MS[0] {
+                Vec4::new(0.2, 0.8, 0.2, 0.8)
+            } else if sample.frame_ms <= Self::TARGETS_MS[1] {
+                Vec4::new(0.9, 0.8, 0.1, 0.8)
+            } else {
+                Vec4::new(0.9, 0.2, 0.1, 0.8)
+            };
+            let top = bottom - height(sample.frame_ms);
+            text.draw_rect(Vec2::new(left, top), Vec2::new(right, bottom), color);
+            let cpu_top = bottom - height(sample.cpu_ms.min(sample.frame_ms));
+            text.draw_rect(
+                Vec2::new(left, cpu_top),
+                Vec2::new(right, bottom),
+                Vec4::new(0.1, 0.3, 0.9, 0.8),
+            );
+        }
+        for target in Self::TARGETS_MS {
+            let y = bottom - height(target);
+            text.draw_rect(
+                Vec2::new(position.x, y),
+                Vec2::new(position.x + size.x, y + 1.),
+                Vec4::new(1., 1., 1., 0.4),
+            );
+        }
+    }
+}
+
+fn write_row(csv: &mut impl Write, sample: &Sample) -> io::Result<()> {
+    let optional = |value: Option<String>| value.unwrap_or_default();
+    let stats = &sample.stats;
+    writeln!(
+        csv,
+        "{:.4},{:.3},{:.1},{:.3},{},{},{},{},{},{},{}",
+        sample.time,
+        sample.frame_ms,
+        1000. / sample.frame_ms.max(f32::EPSILON),
+        sample.cpu_ms,
+        optional(stats.gpu_ms.map(|ms| format!("{ms:.3}"))),
+        stats.draw_calls,
+        stats.instances,
+        stats.statics,
+        optional(stats.culled.map(|c| c.to_string())),
+        optional(stats.triangles.map(|t| t.to_string())),
+        stats.upload_bytes,
+    )
+}
@@ -0,0 +1,347 @@
+use glam::{Mat3, Quat, Vec3};
+
+use crate::{
+    clock::Clock,
+    entity::{Entity, EntityId},
+};
+
+pub use self::{body::RigidBody, collider::Collider};
+
+use self::collider::{world_inverse_inertia, WorldShape};
+
+mod body;
+mod broadphase;
+mod collider;
+mod narrowphase;
+
+/// A contact found during the last physics step. The normal points from `a`
+/// to `b`.
+#[derive(Clone, Copy, Debug)]
+pub struct Contact {
+    pub a: EntityId,
+    pub b: EntityId,
+    pub normal: Vec3,
+    pub depth: f32,
+    pub point: Vec3,
+}
+
+/// Per-step copy of everything the solver needs from an entity.
+struct BodyState {
+    source: Source,
+    shape: WorldShape,
+    collides: bool,
+    position: Vec3,
+    rotation: Quat,
+    velocity: Vec3,
+    angular_velocity: Vec3,
+    inverse_mass: f32,
+    inverse_inertia: Mat3,
+    restitution: f32,
+    friction: f32,
+}
+
+#[derive(Clone, Copy)]
+enum Source {
+    Dynamic(usize),
+    Static(usize),
+}
+
+pub struct PhysicsWorld {
+    pub gravity: Vec3,
+    pub iterations: u32,
+    contacts: Vec<Contact>,
+}
+
+impl PhysicsWorld {
+    pub const TIMESTEP: f32 = Clock::FIXED_DELTA;
+    /// Penetration tolerated before positions are corrected, avoids jitter.
+    const SLOP: f32 = 0.005;
+    const CORRECTION: f32 = 0.4;
+    /// Closing speeds below this bounce with no restitution.
+    const RESTING_SPEED: f32 = 0.5;
+
+    pub fn new() -> Self {
+        Self {
+            gravity: Vec3::new(0., -9.81, 0.),
+            iterations: 8,
+            contacts: vec![],
+        }
+    }
+
+    /// Advances the simulation by `steps` fixed steps of
+    /// [`PhysicsWorld::TIMESTEP`], as counted by the [`Clock`], writing the
+    /// results back into the transforms and rigid bodies of `dynamic`. The
+    /// clock keeps `steps` within [`Clock::MAX_FIXED_STEPS`].
+    ///
+    /// Entities in `statics`, and dynamic entities with a collider but no rigid
+    /// body, collide as immovable obstacles. Bodies are simulated in their
+    /// local space, so they should not have a parent.
+    pub fn simulate(&mut self, steps: u32, dynamic: &mut [Entity], statics: &[Entity]) {
+        self.contacts.clear();
+        for _ in 0..steps {
+            self.step(Self::TIMESTEP, dynamic, statics);
+        }
+    }
+
+    #[inline]
+    pub fn get_contacts(&self) -> &[Contact] {
+        &self.contacts
+    }
+
+    fn step(&mut self, dt: f32, dynamic: &mut [Entity], statics: &[Entity]) {
+        let mut states = Self::gather(dynamic, statics);
+
+        for s in states.iter_mut().filter(|s| s.inverse_mass > 0.) {
+            if let Source::Dynamic(i) = s.source {
+                let body = dynamic[i].get_rigid_body().unwrap();
+                s.velocity += self.gravity * body.gravity_scale * dt;
+                s.velocity *= 1. / (1. + dt * body.linear_damping);
+                s.angular_velocity *= 1. / (1. + dt * body.angular_damping);
+            }
+        }
+
+        let aabbs: Vec<_> = states.iter().map(|s| s.shape.get_aabb()).collect();
+        let movable: Vec<_> = states.iter().map(|s| s.inverse_mass > 0.).collect();
+        let manifolds: Vec<_> = broadphase::sweep_and_prune(&aabbs, &movable)
+            .into_iter()
+            .filter(|&(a, b)| states[a].collides && states[b].collides)
+            .filter_map(|(a, b)| {
+                narrowphase::collide(&states[a].shape, &states[b].shape).map(|m| (a, b, m))
+            })
+            .collect();
+
+        for _ in 0..self.iterations {
+            for (a, b, m) in manifolds.iter() {
+                resolve_velocity(&mut states, *a, *b, m.normal, m.point);
+            }
+        }
+
+        for s in states.iter_mut().filter(|s| s.inverse_mass > 0.) {
+            s.position += s.velocity * dt;
+            let spin = Quat::from_xyzw(
+                s.angular_velocity.x,
+                s.angular_velocity.y,
+                s.angular_velocity.z,
+                0.,
+            ) * s.rotation;
+            s.rotation = (s.rotation + spin * (0.5 * dt)).normalize();
+        }
+
+        for (a, b, m) in manifolds.iter() {
+            let (inv_a, inv_b) = (states[*a].inverse_mass, states[*b].inverse_mass);
+            let correction =
+                (m.depth - Self::SLOP).max(0.) / (inv_a + inv_b) * Self::CORRECTION * m.normal;
+            states[*a].position -= correction * inv_a;
+            states[*b].position += correction * inv_b;
+        }
+
+        for s in states.iter() {
+            if let Source::Dynamic(i) = s.source {
+                let entity = &mut dynamic[i];
+                if let Some(body) = entity.get_mut_rigid_body() {
+                    body.velocity = s.velocity;
+                    body.angular_velocity = s.angular_velocity;
+                    let transform = entity.get_mut_transform();
+                    transform.set_position(s.position);
+                    transform.set_rotation(s.rotation);
+                }
+            }
+        }
+
+        let id_of = |s: &BodyState| match s.source {
+            Source::Dynamic(i) => dynamic[i].get_id(),
+            Source::Static(i) => statics[i].get_id(),
+        };
+        self.contacts
+            .extend(manifolds.iter().map(|(a, b, m)| Contact {
+                a: id_of(&states[*a]),
+                b: id_of(&states[*b]),
+                normal: m.normal,
+                depth: m.depth,
+                point: m.point,
+            }));
+    }
+
+    fn gather(dynamic: &[Entity], statics: &[Entity]) -> Vec<BodyState> {
+        let dynamic = dynamic
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (Source::Dynamic(i), e));
+        let statics = statics
+            .iter()
+            .enumerate()
+            .map(|(i, e)| (Source::Static(i), e));
+
+        dynamic
+            .chain(statics)
+            .filter(|(_, e)| e.get_collider().is_some() || e.get_rigid_body().is_some())
+            .map(|(source, e)| {
+                let transform = e.get_transform();
+                let body = match source {
+                    Source::Dynamic(_) => e.get_rigid_body().copied(),
+                    Source::Static(_) => None,
+                };
+                let inverse_mass = body.map_or(0., |b| b.get_inverse_mass());
+                // Bodies without a collider are integrated but never collide.
+                let collider = e.get_collider().copied();
+                let shape = collider
+                    .unwrap_or(Collider::Sphere { radius: 0. })
+                    .to_world(transform);
+                let inverse_inertia = collider.map_or(Vec3::ZERO, |c| {
+                    c.get_inverse_inertia(inverse_mass, transform.get_scale())
+                });
+                BodyState {
+                    source,
+                    shape,
+                    collides: collider.is_some(),
+                    position: transform.get_position(),
+                    rotation: transform.get_rotation(),
+                    velocity: body.map_or(Vec3::ZERO, |b| b.velocity),
+                    angular_velocity: body.map_or(Vec3::ZERO, |b| b.angular_velocity),
+                    inverse_mass,
+                    inverse_inertia: world_inverse_inertia(
+                        inverse_inertia,
+                        transform.get_rotation(),
+                    ),
+                    restitution: body.map_or(0.2, |b| b.restitution),
+                    friction: body.map_or(0.5, |b| b.friction),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Applies the normal and friction impulses for one contact.
+fn resolve_velocity(states: &mut [BodyState], a: usize, b: usize, normal: Vec3, point: Vec3) {
+    let (sa, sb) = (&states[a], &states[b]);
+    let ra = point - sa.shape.get_center();
+    let rb = point - sb.shape.get_center();
+    let relative = |sa: &BodyState, sb: &BodyState| {
+        (sb.velocity + sb.angular_velocity.cross(rb))
+            - (sa.velocity + sa.angular_velocity.cross(ra))
+    };
+    let effective_mass = |sa: &BodyState, sb: &BodyState, dir: Vec3| {
+        let ka = (sa.inverse_inertia * ra.cross(dir)).cross(ra);
+        let kb = (sb.inverse_inertia * rb.cross(dir)).cross(rb);
+        sa.inverse_mass + sb.inverse_mass + dir.dot(ka + kb)
+    };
+
+    let velocity = relative(sa, sb);
+    let closing = velocity.dot(normal);
+    if closing > 0. {
+        return;
+    }
+    let k = effective_mass(sa, sb, normal);
+    if k <= 0. {
+        return;
+    }
+    let restitution = if -closing < PhysicsWorld::RESTING_SPEED {
+        0.
+    } else {
+        sa.restitution.max(sb.restitution)
+    };
+    let j = -(1. + restitution) * closing / k;
+    apply_impulse(states, a, b, ra, rb, normal * j);
+
+    let (sa, sb) = (&states[a], &states[b]);
+    let velocity = relative(sa, sb);
+    let tangent = velocity - normal * velocity.dot(normal);
+    if tangent.length_squared() < 1e-8 {
+        return;
+    }
+    let tangent = tangent.normalize();
+    let kt = effective_mass(sa, sb, tangent);
+    if kt <= 0. {
+        return;
+    }
+    let friction = (sa.friction * sb.friction).sqrt();
+    let jt = (-velocity.dot(tangent) / kt).clamp(-j * friction, j * friction);
+    apply_impulse(states, a, b, ra, rb, tangent * jt);
+}
+
+fn apply_impulse(states: &mut [BodyState], a: usize, b: usize, ra: Vec3, rb: Vec3, impulse: Vec3) {
+    let sa = &mut states[a];
+    sa.velocity -= impulse * sa.inverse_mass;
+    sa.angular_velocity -= sa.inverse_inertia * ra.cross(impulse);
+    let sb = &mut states[b];
+    sb.velocity += impulse * sb.inverse_mass;
+    sb.angular_velocity += sb.inverse_inertia * rb.cross(impulse);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transform::Transform;
+
+    /// A unit box thrown at a static floor whose top is at zero, with gravity
+    /// and damping off so only the contact changes its velocity.
+    fn bounce(velocity: Vec3, restitution: f32, friction: f32) -> Vec3 {
+        let mut world = PhysicsWorld::new();
+        world.gravity = Vec3::ZERO;
+
+        let mut body = Entity::with_transform(EntityId(0), Transform::with_pos(Vec3::Y * 0.45));
+        body.set_collider(Some(Collider::Aabb {
+            half_extents: Vec3::splat(0.5),
+        }));
+        body.set_rigid_body(Some(RigidBody {
+            velocity,
+            linear_damping: 0.,
+            restitution,
+            friction,
+            ..Default::default()
+        }));
+        let mut floor = Entity::with_transform(
+            EntityId(1),
+            Transform::new(Vec3::NEG_Y, Quat::IDENTITY, Vec3::new(10., 2., 10.)),
+        );
+        floor.set_collider(Some(Collider::cube()));
+
+        let mut dynamic = [body];
+        world.simulate(1, &mut dynamic, &[floor]);
+        let contacts = world.get_contacts();
+        assert_eq!(contacts.len(), 1);
+        assert_eq!((contacts[0].a, contacts[0].b), (EntityId(0), EntityId(1)));
+        assert!(contacts[0].normal.abs_diff_eq(Vec3::NEG_Y, 1e-5));
+        dynamic[0].get_rigid_body().unwrap().velocity
+    }
+
+    #[test]
+    fn bounces_with_restitution() {
+        // The floor restitution is 0.2, the larger one of the pair is used.
+        let velocity = bounce(Vec3::new(0., -5., 0.), 0.5, 0.);
+        assert!(
+            velocity.abs_diff_eq(Vec3::new(0., 2.5, 0.), 1e-4),
+            "{velocity}"
+        );
+        let velocity = bounce(Vec3::new(0., -5., 0.), 0., 0.);
+        assert!(
+            velocity.abs_diff_eq(Vec3::new(0., 1., 0.), 1e-4),
+            "{velocity}"
+        );
+        // Slow contacts come to rest instead of bouncing.
+        let velocity = bounce(Vec3::new(0., -0.3, 0.), 1., 0.);
+        assert!(velocity.abs_diff_eq(Vec3::ZERO, 1e-4), "{velocity}");
+    }
+
+    #[test]
+    fn slows_down_with_friction() {
+        let velocity = bounce(Vec3::new(4., -5., 0.), 0.5, 0.);
+        assert!((velocity.x - 4.).abs() < 1e-4, "{velocity}");
+        // Friction is the geometric mean with the floor's 0.5, at most the
+        // normal impulse of 7.5 times that.
+        let velocity = bounce(Vec3::new(4., -5., 0.), 0.5, 0.5);
+        assert!((velocity.x - 0.25).abs() < 1e-4, "{velocity}");
+        let velocity = bounce(Vec3::new(4., -5., 0.), 0.5, 2.);
+        assert!(velocity.x.abs() < 1e-4, "{velocity}");
+    }
+
+    #[test]
+    fn applies_impulses() {
+        let mut body = RigidBody::new(2.);
+        body.apply_impulse(Vec3::new(4., 0., 0.));
+        assert_eq!(body.velocity, Vec3::new(2., 0., 0.));
+        let mut immovable = RigidBody::new(0.);
+        immovable.apply_impulse(Vec3::ONE);
+        assert_eq!(immovable.velocity, Vec3::ZERO);
+    }
+}
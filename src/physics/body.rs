@@ -0,0 +1,59 @@
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+/// Dynamic state of an entity simulated by the [`PhysicsWorld`](super::PhysicsWorld).
+///
+/// A mass of zero makes the body immovable; it still collides but is never
+/// pushed by other bodies.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RigidBody {
+    pub mass: f32,
+    pub velocity: Vec3,
+    pub angular_velocity: Vec3,
+    pub gravity_scale: f32,
+    /// Fraction of linear velocity lost per second.
+    pub linear_damping: f32,
+    /// Fraction of angular velocity lost per second.
+    pub angular_damping: f32,
+    pub restitution: f32,
+    pub friction: f32,
+}
+
+impl Default for RigidBody {
+    fn default() -> Self {
+        Self {
+            mass: 1.,
+            velocity: Vec3::ZERO,
+            angular_velocity: Vec3::ZERO,
+            gravity_scale: 1.,
+            linear_damping: 0.01,
+            angular_damping: 0.05,
+            restitution: 0.3,
+            friction: 0.5,
+        }
+    }
+}
+
+impl RigidBody {
+    pub fn new(mass: f32) -> Self {
+        Self {
+            mass,
+            ..Default::default()
+        }
+    }
+
+    #[inline]
+    pub fn get_inverse_mass(&self) -> f32 {
+        if self.mass > 0. {
+            1. / self.mass
+        } else {
+            0.
+        }
+    }
+
+    /// Changes the velocity as if `impulse` hit the center of mass.
+    pub fn apply_impulse(&mut self, impulse: Vec3) {
+        self.velocity += impulse * self.get_inverse_mass();
+    }
+}
@@ -0,0 +1,53 @@
+use crate::bounds::Aabb;
+
+/// Sweep and prune along the x axis. Returns every pair of indices whose
+/// boxes overlap, skipping pairs where neither side can move.
+pub(super) fn sweep_and_prune(aabbs: &[Aabb], movable: &[bool]) -> Vec<(usize, usize)> {
+    let mut order: Vec<usize> = (0..aabbs.len()).collect();
+    order.sort_unstable_by(|&a, &b| aabbs[a].min.x.total_cmp(&aabbs[b].min.x));
+
+    let mut pairs = vec![];
+    let mut active: Vec<usize> = vec![];
+    for &i in order.iter() {
+        let current = &aabbs[i];
+        active.retain(|&j| aabbs[j].max.x >= current.min.x);
+        for &j in active.iter() {
+            if (movable[i] || movable[j]) && current.intersects(&aabbs[j]) {
+                pairs.push((j.min(i), j.max(i)));
+            }
+        }
+        active.push(i);
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec3;
+
+    use super::*;
+
+    fn aabb(min: Vec3) -> Aabb {
+        Aabb::new(min, min + Vec3::ONE)
+    }
+
+    #[test]
+    fn pairs_overlapping_boxes() {
+        let aabbs = [
+            aabb(Vec3::new(3., 0., 0.)),
+            aabb(Vec3::ZERO),
+            aabb(Vec3::new(0.5, 0.5, 0.5)),
+            // Overlaps along x only.
+            aabb(Vec3::new(0.5, 5., 0.)),
+            aabb(Vec3::new(3.5, 0., 0.)),
+            aabb(Vec3::new(1.2, 0.9, 0.)),
+        ];
+        let mut pairs = sweep_and_prune(&aabbs, &[true; 6]);
+        pairs.sort_unstable();
+        assert_eq!(pairs, [(0, 4), (1, 2), (2, 5)]);
+
+        // Neither side of (0, 4) nor (1, 2) can move.
+        let movable = [false, false, false, false, false, true];
+        assert_eq!(sweep_and_prune(&aabbs, &movable), [(2, 5)]);
+    }
+}
@@ -0,0 +1,116 @@
+use glam::{Mat3, Quat, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::{bounds::Aabb, transform::Transform};
+
+/// Collision shape in the entity's local space. Sizes are multiplied by the
+/// entity's scale.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Collider {
+    Sphere {
+        radius: f32,
+    },
+    /// Box that always stays aligned to the world axes, whatever the rotation.
+    Aabb {
+        half_extents: Vec3,
+    },
+    /// Box that follows the entity's rotation.
+    Obb {
+        half_extents: Vec3,
+    },
+}
+
+impl Collider {
+    /// Oriented box matching the unit cube mesh.
+    pub fn cube() -> Self {
+        Collider::Obb {
+            half_extents: Vec3::splat(0.5),
+        }
+    }
+
+    pub(super) fn to_world(self, transform: &Transform) -> WorldShape {
+        let center = transform.get_position();
+        let scale = transform.get_scale().abs();
+        match self {
+            Collider::Sphere { radius } => WorldShape::Sphere {
+                center,
+                radius: radius * scale.max_element(),
+            },
+            Collider::Aabb { half_extents } => WorldShape::Box {
+                center,
+                axes: Mat3::IDENTITY,
+                half_extents: half_extents * scale,
+            },
+            Collider::Obb { half_extents } => WorldShape::Box {
+                center,
+                axes: Mat3::from_quat(transform.get_rotation()),
+                half_extents: half_extents * scale,
+            },
+        }
+    }
+
+    /// Inverse inertia tensor in local space for a body of the given mass.
+    pub(super) fn get_inverse_inertia(&self, inverse_mass: f32, scale: Vec3) -> Vec3 {
+        if inverse_mass == 0. {
+            return Vec3::ZERO;
+        }
+        match *self {
+            Collider::Sphere { radius } => {
+                let r = radius * scale.abs().max_element();
+                Vec3::splat(inverse_mass / (0.4 * r * r))
+            }
+            // Axis aligned boxes never rotate.
+            Collider::Aabb { .. } => Vec3::ZERO,
+            Collider::Obb { half_extents } => {
+                let size = 2. * half_extents * scale.abs();
+                let sq = size * size;
+                12. * inverse_mass / Vec3::new(sq.y + sq.z, sq.x + sq.z, sq.x + sq.y)
+            }
+        }
+    }
+}
+
+/// A collider posed in world space.
+pub(super) enum WorldShape {
+    Sphere {
+        center: Vec3,
+        radius: f32,
+    },
+    Box {
+        center: Vec3,
+        axes: Mat3,
+        half_extents: Vec3,
+    },
+}
+
+impl WorldShape {
+    pub fn get_center(&self) -> Vec3 {
+        match *self {
+            WorldShape::Sphere { center, .. } | WorldShape::Box { center, .. } => center,
+        }
+    }
+
+    pub fn get_aabb(&self) -> Aabb {
+        match *self {
+            WorldShape::Sphere { center, radius } => {
+                Aabb::from_center_half_extents(center, Vec3::splat(radius))
+            }
+            WorldShape::Box {
+                center,
+                axes,
+                half_extents,
+            } => {
+                let extent = axes.x_axis.abs() * half_extents.x
+                    + axes.y_axis.abs() * half_extents.y
+                    + axes.z_axis.abs() * half_extents.z;
+                Aabb::from_center_half_extents(center, extent)
+            }
+        }
+    }
+}
+
+/// Rotates a local space inverse inertia into world space.
+pub(super) fn world_inverse_inertia(local: Vec3, rotation: Quat) -> Mat3 {
+    let r = Mat3::from_quat(rotation);
+    r * Mat3::from_diagonal(local) * r.transpose()
+}
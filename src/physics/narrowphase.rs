@@ -0,0 +1,322 @@
+use glam::{Mat3, Vec3};
+
+use super::collider::WorldShape;
+
+/// Penetration between two shapes. The normal points from the first shape to
+/// the second one.
+pub(super) struct Manifold {
+    pub normal: Vec3,
+    pub depth: f32,
+    pub point: Vec3,
+}
+
+pub(super) fn collide(a: &WorldShape, b: &WorldShape) -> Option<Manifold> {
+    match (a, b) {
+        (
+            WorldShape::Sphere {
+                center: ca,
+                radius: ra,
+            },
+            WorldShape::Sphere {
+                center: cb,
+                radius: rb,
+            },
+        ) => sphere_sphere(*ca, *ra, *cb, *rb),
+        (
+            WorldShape::Box {
+                center,
+                axes,
+                half_extents,
+            },
+            WorldShape::Sphere {
+                center: sphere,
+                radius,
+            },
+        ) => box_sphere(*center, axes, *half_extents, *sphere, *radius),
+        (
+            WorldShape::Sphere {
+                center: sphere,
+                radius,
+            },
+            WorldShape::Box {
+                center,
+                axes,
+                half_extents,
+            },
+        ) => box_sphere(*center, axes, *half_extents, *sphere, *radius).map(|m| Manifold {
+            normal: -m.normal,
+            ..m
+        }),
+        (
+            WorldShape::Box {
+                center: ca,
+                axes: aa,
+                half_extents: ha,
+            },
+            WorldShape::Box {
+                center: cb,
+                axes: ab,
+                half_extents: hb,
+            },
+        ) => box_box(*ca, aa, *ha, *cb, ab, *hb),
+    }
+}
+
+fn sphere_sphere(ca: Vec3, ra: f32, cb: Vec3, rb: f32) -> Option<Manifold> {
+    let d = cb - ca;
+    let distance = d.length();
+    if distance > ra + rb {
+        return None;
+    }
+    let normal = if distance > f32::EPSILON {
+        d / distance
+    } else {
+        Vec3::Y
+    };
+    let depth = ra + rb - distance;
+    Some(Manifold {
+        normal,
+        depth,
+        point: ca + normal * (ra - depth * 0.5),
+    })
+}
+
+fn box_sphere(
+    center: Vec3,
+    axes: &Mat3,
+    half_extents: Vec3,
+    sphere: Vec3,
+    radius: f32,
+) -> Option<Manifold> {
+    let local = axes.transpose() * (sphere - center);
+    let closest = local.clamp(-half_extents, half_extents);
+
+    if closest == local {
+        // The sphere center is inside the box, push out through the nearest face.
+        let gap = half_extents - local.abs();
+        let axis = if gap.x < gap.y && gap.x < gap.z {
+            0
+        } else if gap.y < gap.z {
+            1
+        } else {
+            2
+        };
+        let sign = if local[axis] < 0. { -1. } else { 1. };
+        return Some(Manifold {
+            normal: axes.col(axis) * sign,
+            depth: radius + gap[axis],
+            point: sphere,
+        });
+    }
+
+    let diff = local - closest;
+    let distance = diff.length();
+    if distance > radius {
+        return None;
+    }
+    Some(Manifold {
+        normal: *axes * (diff / distance),
+        depth: radius - distance,
+        point: center + *axes * closest,
+    })
+}
+
+/// Separating axis test between two oriented boxes.
+fn box_box(ca: Vec3, aa: &Mat3, ha: Vec3, cb: Vec3, ab: &Mat3, hb: Vec3) -> Option<Manifold> {
+    let d = cb - ca;
+    let project = |axes: &Mat3, half: Vec3, axis: Vec3| {
+        axes.x_axis.dot(axis).abs() * half.x
+            + axes.y_axis.dot(axis).abs() * half.y
+            + axes.z_axis.dot(axis).abs() * half.z
+    };
+
+    let mut best = (f32::MAX, Vec3::ZERO);
+    let mut test = |axis: Vec3| -> bool {
+        let length = axis.length();
+        // Cross products of nearly parallel edges carry no information.
+        if length < 1e-5 {
+            return true;
+        }
+        let axis = axis / length;
+        let overlap = project(aa, ha, axis) + project(ab, hb, axis) - d.dot(axis).abs();
+        if overlap < 0. {
+            return false;
+        }
+        if overlap < best.0 {
+            best = (overlap, if d.dot(axis) < 0. { -axis } else { axis });
+        }
+        true
+    };
+
+    for i in 0..3 {
+        if !test(aa.col(i)) || !test(ab.col(i)) {
+            return None;
+        }
+    }
+    for i in 0..3 {
+        for j in 0..3 {
+            if !test(aa.col(i).cross(ab.col(j))) {
+                return None;
+            }
+        }
+    }
+
+    let (depth, normal) = best;
+    // Average the corners of each box that lie inside the other one. Face
+    // contacts then land in the middle of the touching area instead of on a
+    // single corner; edge contacts fall back to the deepest corner of `b`.
+    let mut sum = Vec3::ZERO;
+    let mut count = 0;
+    for (center, axes, half, other_center, other_axes, other_half) in
+        [(ca, aa, ha, cb, ab, hb), (cb, ab, hb, ca, aa, ha)]
+    {
+        for corner in corners(center, axes, half) {
+            let local = other_axes.transpose() * (corner - other_center);
+            if local.abs().cmple(other_half + Vec3::splat(1e-4)).all() {
+                sum += corner;
+                count += 1;
+            }
+        }
+    }
+    let point = if count > 0 {
+        sum / count as f32
+    } else {
+        let mut support = cb;
+        for i in 0..3 {
+            let axis = ab.col(i);
+            support -= axis * hb[i] * axis.dot(normal).signum();
+        }
+        support + normal * depth * 0.5
+    };
+    Some(Manifold {
+        normal,
+        depth,
+        point,
+    })
+}
+
+fn corners(center: Vec3, axes: &Mat3, half: Vec3) -> [Vec3; 8] {
+    let mut corners = [center; 8];
+    for (i, corner) in corners.iter_mut().enumerate() {
+        for axis in 0..3 {
+            let sign = if i & (1 << axis) == 0 { -1. } else { 1. };
+            *corner += axes.col(axis) * half[axis] * sign;
+        }
+    }
+    corners
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Quat;
+
+    use super::*;
+
+    fn sphere(center: Vec3, radius: f32) -> WorldShape {
+        WorldShape::Sphere { center, radius }
+    }
+
+    fn cube(center: Vec3, rotation: Quat) -> WorldShape {
+        WorldShape::Box {
+            center,
+            axes: Mat3::from_quat(rotation),
+            half_extents: Vec3::ONE,
+        }
+    }
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!(a.abs_diff_eq(b, 1e-4), "{a} != {b}");
+    }
+
+    #[test]
+    fn collides_spheres() {
+        let m = collide(&sphere(Vec3::ZERO, 1.), &sphere(Vec3::new(1.5, 0., 0.), 1.)).unwrap();
+        assert_near(m.normal, Vec3::X);
+        assert!((m.depth - 0.5).abs() < 1e-5);
+        assert_near(m.point, Vec3::new(0.75, 0., 0.));
+
+        assert!(collide(&sphere(Vec3::ZERO, 1.), &sphere(Vec3::new(0., 2.5, 0.), 1.)).is_none());
+    }
+
+    #[test]
+    fn collides_boxes_with_spheres() {
+        let m = collide(
+            &cube(Vec3::ZERO, Quat::IDENTITY),
+            &sphere(Vec3::new(1.5, 0., 0.), 1.),
+        )
+        .unwrap();
+        assert_near(m.normal, Vec3::X);
+        assert!((m.depth - 0.5).abs() < 1e-5);
+        assert_near(m.point, Vec3::X);
+
+        // The normal still points from the first shape to the second one.
+        let m = collide(
+            &sphere(Vec3::new(0., -1.5, 0.), 1.),
+            &cube(Vec3::ZERO, Quat::IDENTITY),
+        )
+        .unwrap();
+        assert_near(m.normal, Vec3::Y);
+        assert!((m.depth - 0.5).abs() < 1e-5);
+
+        // A center inside the box is pushed out through the nearest face.
+        let m = collide(
+            &cube(Vec3::ZERO, Quat::IDENTITY),
+            &sphere(Vec3::new(0., 0., -0.8), 0.5),
+        )
+        .unwrap();
+        assert_near(m.normal, Vec3::NEG_Z);
+        assert!((m.depth - 0.7).abs() < 1e-5);
+
+        let rotated = cube(
+            Vec3::ZERO,
+            Quat::from_rotation_z(std::f32::consts::FRAC_PI_4),
+        );
+        let m = collide(&rotated, &sphere(Vec3::new(1.5, 1.5, 0.), 1.5)).unwrap();
+        assert_near(m.normal, Vec3::new(1., 1., 0.).normalize());
+        assert!((m.depth - (1.5 - (1.5 * 2f32.sqrt() - 1.))).abs() < 1e-4);
+
+        assert!(collide(&rotated, &sphere(Vec3::new(2.5, 0., 0.), 1.)).is_none());
+    }
+
+    #[test]
+    fn collides_boxes() {
+        let m = collide(
+            &cube(Vec3::ZERO, Quat::IDENTITY),
+            &cube(Vec3::new(1.8, 0.5, 0.), Quat::IDENTITY),
+        )
+        .unwrap();
+        assert_near(m.normal, Vec3::X);
+        assert!((m.depth - 0.2).abs() < 1e-5);
+        // In the middle of the overlapping faces.
+        assert!((m.point.x - 0.9).abs() < 1e-4);
+
+        let m = collide(
+            &cube(Vec3::new(0., 1.9, 0.), Quat::IDENTITY),
+            &cube(Vec3::ZERO, Quat::IDENTITY),
+        )
+        .unwrap();
+        assert_near(m.normal, Vec3::NEG_Y);
+        assert!((m.depth - 0.1).abs() < 1e-5);
+
+        // A corner of the rotated box pokes into the face of the other one.
+        let rotated = Quat::from_rotation_z(std::f32::consts::FRAC_PI_4);
+        let m = collide(
+            &cube(Vec3::ZERO, Quat::IDENTITY),
+            &cube(Vec3::new(2.2, 0., 0.), rotated),
+        )
+        .unwrap();
+        assert_near(m.normal, Vec3::X);
+        assert!((m.depth - (1. + 2f32.sqrt() - 2.2)).abs() < 1e-4);
+
+        assert!(collide(
+            &cube(Vec3::ZERO, Quat::IDENTITY),
+            &cube(Vec3::new(2.5, 0., 0.), rotated)
+        )
+        .is_none());
+        assert!(collide(
+            &cube(Vec3::ZERO, Quat::IDENTITY),
+            &cube(Vec3::new(1., 2.1, 1.), Quat::IDENTITY)
+        )
+        .is_none());
+    }
+}
@@ -0,0 +1,491 @@
+//! Named entity templates loaded from RON files.
+//!
+//! A prefab file is a map from prefab names to their definitions:
+//!
+//! ```text
+//! {
+//!     "lamp": (
+//!         scale: (0.3, 6.0, 0.3),
+//!         color: (0.4, 0.4, 0.4, 1.0),
+//!         is_static: true,
+//!         components: [Collider(Aabb(half_extents: (0.5, 0.5, 0.5)))],
+//!         children: [(prefab: "bulb", position: (0.0, 0.6, 0.0))],
+//!     ),
+//!     "bulb": (color: (1.0, 0.9, 0.4, 1.0)),
+//! }
+//! ```
+//!
+//! Every field is optional. Children refer to other prefabs by name and are
+//! placed relative to their parent. Components use the same notation as scene
+//! files, except for animations: their clips live in a scene file's clip
+//! table, which prefab files do not have.
+//!
+//! Instances remember which prefab they came from, so a
+//! [`PrefabLibrary::reload_if_changed`] that picks up an edited file updates
+//! every live instance in place: the root keeps its id and position, its
+//! other properties are reapplied, moving it between the static and dynamic
+//! entities if `is_static` changed, and its children are spawned again.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt, fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use glam::{Quat, Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    asset::AssetNames,
+    entity::EntityId,
+    scene::{Component, Scene},
+    transform::Transform,
+};
+
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Prefab {
+    pub mesh: String,
+    pub material: String,
+    pub rotation: Quat,
+    pub scale: Vec3,
+    pub color: Vec4,
+    pub is_static: bool,
+    pub components: Vec<Component>,
+    pub children: Vec<PrefabChild>,
+}
+
+impl Default for Prefab {
+    fn default() -> Self {
+        Self {
+            mesh: AssetNames::DEFAULT_MESH.to_owned(),
+            material: AssetNames::DEFAULT_MATERIAL.to_owned(),
+            rotation: Quat::IDENTITY,
+            scale: Vec3::ONE,
+            color: Vec4::ONE,
+            is_static: false,
+            components: vec![],
+            children: vec![],
+        }
+    }
+}
+
+/// Another prefab spawned as a child, relative to its parent.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct PrefabChild {
+    pub prefab: String,
+    #[serde(default)]
+    pub position: Vec3,
+    #[serde(default)]
+    pub rotation: Quat,
+    #[serde(default = "one")]
+    pub scale: Vec3,
+}
+
+fn one() -> Vec3 {
+    Vec3::ONE
+}
+
+/// Per-instance replacements for prefab properties.
+#[derive(Clone, Default)]
+pub struct Overrides {
+    pub name: Option<String>,
+    pub mesh: Option<String>,
+    pub material: Option<String>,
+    pub rotation: Option<Quat>,
+    pub scale: Option<Vec3>,
+    pub color: Option<Vec4>,
+}
+
+#[derive(Debug)]
+pub enum PrefabError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Unknown(String),
+    Cycle(String),
+    Animation(String),
+}
+
+impl fmt::Display for PrefabError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PrefabError::Io(e) => write!(f, "io error: {e}"),
+            PrefabError::Parse(e) => write!(f, "cannot parse prefabs: {e}"),
+            PrefabError::Unknown(name) => write!(f, "unknown prefab {name:?}"),
+            PrefabError::Cycle(name) => write!(f, "prefab {name:?} contains itself"),
+            PrefabError::Animation(name) => {
+                write!(
+                    f,
+                    "prefab {name:?} has an animation, which prefabs cannot hold"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for PrefabError {}
+
+impl From<std::io::Error> for PrefabError {
+    fn from(e: std::io::Error) -> Self {
+        PrefabError::Io(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for PrefabError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        PrefabError::Parse(e)
+    }
+}
+
+/// Entities spawned by one [`PrefabLibrary::instantiate`] call.
+struct Instance {
+    prefab: String,
+    root: EntityId,
+    overrides: Overrides,
+    /// Every descendant, parents before their children.
+    children: Vec<EntityId>,
+}
+
+pub struct PrefabLibrary {
+    path: Option<PathBuf>,
+    modified: Option<SystemTime>,
+    prefabs: HashMap<String, Prefab>,
+    instances: Vec<Instance>,
+}
+
+impl PrefabLibrary {
+    /// Loads the prefabs in `path`, which is then watched by
+    /// [`PrefabLibrary::reload_if_changed`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, PrefabError> {
+        let path = path.as_ref();
+        let modified = fs::metadata(path)?.modified().ok();
+        Ok(Self {
+            path: Some(path.to_owned()),
+            modified,
+            prefabs: parse(&fs::read_to_string(path)?)?,
+            instances: vec![],
+        })
+    }
+
+    #[inline]
+    pub fn get(&self, name: &str) -> Option<&Prefab> {
+        self.prefabs.get(name)
+    }
+
+    /// Spawns the prefab `name` and its children with the root at `position`.
+    pub fn instantiate(
+        &mut self,
+        scene: &mut Scene,
+        name: &str,
+        position: Vec3,
+        overrides: &Overrides,
+    ) -> Result<EntityId, PrefabError> {
+        if !self.prefabs.contains_key(name) {
+            return Err(PrefabError::Unknown(name.to_owned()));
+        }
+        let mut children = vec![];
+        let root = self.spawn(scene, name, position, overrides, None, &mut children);
+        self.instances.push(Instance {
+            prefab: name.to_owned(),
+            root,
+            overrides: overrides.clone(),
+            children,
+        });
+        Ok(root)
+    }
+
+    /// Reloads the prefab file if it was modified since it was last read and
+    /// updates the live instances. Returns whether anything was reloaded.
+    ///
+    /// A file that fails to load leaves the current prefabs in place.
+    pub fn reload_if_changed(&mut self, scene: &mut Scene) -> Result<bool, PrefabError> {
+        let Some(path) = self.path.as_ref() else {
+            return Ok(false);
+        };
+        let modified = fs::metadata(path)?.modified().ok();
+        if modified == self.modified {
+            return Ok(false);
+        }
+        // Remembered before parsing so a broken file is reported only once.
+        self.modified = modified;
+        self.prefabs = parse(&fs::read_to_string(path)?)?;
+        self.refresh(scene);
+        Ok(true)
+    }
+
+    /// Reapplies the current definitions to every live instance. Instances
+    /// whose root was despawned or whose prefab no longer exists are dropped.
+    fn refresh(&mut self, scene: &mut Scene) {
+        let mut instances = std::mem::take(&mut self.instances);
+        instances
+            .retain(|i| scene.get_entity(i.root).is_some() && self.prefabs.contains_key(&i.prefab));
+        for instance in instances.iter_mut() {
+            for &child in instance.children.iter().rev() {
+                scene.despawn(child);
+            }
+            instance.children.clear();
+
+            let prefab = &self.prefabs[&instance.prefab];
+            scene.set_static(instance.root, prefab.is_static);
+            apply(scene, instance.root, prefab, &instance.overrides);
+            for child in prefab.children.iter() {
+                self.spawn_child(scene, child, instance.root, &mut instance.children);
+            }
+        }
+        self.instances = instances;
+    }
+
+    fn spawn(
+        &self,
+        scene: &mut Scene,
+        name: &str,
+        position: Vec3,
+        overrides: &Overrides,
+        parent: Option<EntityId>,
+        spawned: &mut Vec<EntityId>,
+    ) -> EntityId {
+        let prefab = &self.prefabs[name];
+        let id = scene.spawn(Transform::with_pos(position), prefab.is_static);
+        apply(scene, id, prefab, overrides);
+        if parent.is_some() {
+            scene.set_parent(id, parent);
+            spawned.push(id);
+        }
+        for child in prefab.children.iter() {
+            self.spawn_child(scene, child, id, spawned);
+        }
+        id
+    }
+
+    fn spawn_child(
+        &self,
+        scene: &mut Scene,
+        child: &PrefabChild,
+        parent: EntityId,
+        spawned: &mut Vec<EntityId>,
+    ) {
+        let prefab = &self.prefabs[&child.prefab];
+        let overrides = Overrides {
+            rotation: Some(child.rotation * prefab.rotation),
+            scale: Some(child.scale * prefab.scale),
+            ..Default::default()
+        };
+        self.spawn(
+            scene,
+            &child.prefab,
+            child.position,
+            &overrides,
+            Some(parent),
+            spawned,
+        );
+    }
+}
+
+/// Sets everything but the position of entity `id` from `prefab`.
+fn apply(scene: &mut Scene, id: EntityId, prefab: &Prefab, overrides: &Overrides) {
+    let assets = scene.get_mut_assets();
+    let mesh = assets.mesh(overrides.mesh.as_deref().unwrap_or(&prefab.mesh));
+    let material = assets.material(overrides.material.as_deref().unwrap_or(&prefab.material));
+    let Some(entity) = scene.get_mut_entity(id) else {
+        return;
+    };
+
+    entity.set_mesh(mesh);
+    entity.set_material(material);
+    let transform = entity.get_mut_transform();
+    transform.set_rotation(overrides.rotation.unwrap_or(prefab.rotation));
+    transform.set_scale(overrides.scale.unwrap_or(prefab.scale));
+    entity.set_color(prefab.color);
+    entity.set_name(None);
+    entity.set_rigid_body(None);
+    entity.set_collider(None);
+    entity.set_animation(None);
+    entity.set_light(None);
+    for component in prefab.components.iter() {
+        component.apply(entity, &[]);
+    }
+    if let Some(name) = overrides.name.as_ref() {
+        entity.set_name(Some(name.clone()));
+    }
+    if let Some(color) = overrides.color {
+        entity.set_color(color);
+    }
+}
+
+fn parse(text: &str) -> Result<HashMap<String, Prefab>, PrefabError> {
+    let prefabs = ron::from_str(text)?;
+    validate(&prefabs)?;
+    Ok(prefabs)
+}
+
+/// Checks that children only refer to existing prefabs and never to one of
+/// their ancestors, and that no prefab has an animation.
+fn validate(prefabs: &HashMap<String, Prefab>) -> Result<(), PrefabError> {
+    fn visit<'a>(
+        prefabs: &'a HashMap<String, Prefab>,
+        name: &'a str,
+        path: &mut Vec<&'a str>,
+        done: &mut HashSet<&'a str>,
+    ) -> Result<(), PrefabError> {
+        if done.contains(name) {
+            return Ok(());
+        }
+        if path.contains(&name) {
+            return Err(PrefabError::Cycle(name.to_owned()));
+        }
+        let prefab = prefabs
+            .get(name)
+            .ok_or_else(|| PrefabError::Unknown(name.to_owned()))?;
+        path.push(name);
+        for child in prefab.children.iter() {
+            visit(prefabs, &child.prefab, path, done)?;
+        }
+        path.pop();
+        done.insert(name);
+        Ok(())
+    }
+
+    for (name, prefab) in prefabs.iter() {
+        let animated = prefab
+            .components
+            .iter()
+            .any(|c| matches!(c, Component::Animation(_)));
+        if animated {
+            return Err(PrefabError::Animation(name.clone()));
+        }
+    }
+
+    let mut done = HashSet::new();
+    for name in prefabs.keys() {
+        visit(prefabs, name, &mut vec![], &mut done)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{entity::Entity, physics::Collider};
+
+    const PREFABS: &str = r#"{
+        "lamp": (
+            scale: (0.5, 4.0, 0.5),
+            color: (0.4, 0.4, 0.4, 1.0),
+            is_static: true,
+            components: [Collider(Aabb(half_extents: (0.5, 0.5, 0.5)))],
+            children: [(prefab: "bulb", position: (0.0, 0.6, 0.0), scale: (2.0, 2.0, 2.0))],
+        ),
+        "bulb": (mesh: "sphere", scale: (0.5, 0.5, 0.5), color: (1.0, 0.9, 0.4, 1.0)),
+    }"#;
+
+    fn library(text: &str) -> PrefabLibrary {
+        PrefabLibrary {
+            path: None,
+            modified: None,
+            prefabs: parse(text).unwrap(),
+            instances: vec![],
+        }
+    }
+
+    fn children(scene: &Scene, parent: EntityId) -> Vec<&Entity> {
+        scene
+            .get_static_entities()
+            .iter()
+            .chain(scene.get_dynamic_entities())
+            .filter(|e| e.get_parent() == Some(parent))
+            .collect()
+    }
+
+    #[test]
+    fn parses_prefabs() {
+        let prefabs = parse(PREFABS).unwrap();
+        let bulb = &prefabs["bulb"];
+        assert_eq!(bulb.mesh, "sphere");
+        assert_eq!(bulb.material, AssetNames::DEFAULT_MATERIAL);
+        assert!(!bulb.is_static);
+        assert_eq!(prefabs["lamp"].children[0].rotation, Quat::IDENTITY);
+
+        let unknown = parse(r#"{"a": (children: [(prefab: "b")])}"#);
+        assert!(matches!(unknown, Err(PrefabError::Unknown(name)) if name == "b"));
+        let cycle =
+            parse(r#"{"a": (children: [(prefab: "b")]), "b": (children: [(prefab: "a")])}"#);
+        assert!(matches!(cycle, Err(PrefabError::Cycle(_))));
+        let animated = parse(
+            r#"{"a": (components: [Animation((clip: 0, loop_mode: Loop, speed: 1.0, playing: true, time: 0.0))])}"#,
+        );
+        assert!(matches!(animated, Err(PrefabError::Animation(name)) if name == "a"));
+        assert!(matches!(parse("{"), Err(PrefabError::Parse(_))));
+    }
+
+    #[test]
+    fn instantiates_with_children_and_overrides() {
+        let mut prefabs = library(PREFABS);
+        let mut scene = Scene::new();
+        let overrides = Overrides {
+            name: Some("left lamp".to_owned()),
+            color: Some(Vec4::ONE),
+            ..Default::default()
+        };
+        let lamp = prefabs
+            .instantiate(&mut scene, "lamp", Vec3::X, &overrides)
+            .unwrap();
+        assert!(prefabs
+            .instantiate(&mut scene, "missing", Vec3::X, &overrides)
+            .is_err());
+
+        assert_eq!(scene.is_static(lamp), Some(true));
+        let root = scene.get_entity(lamp).unwrap();
+        assert_eq!(root.get_name(), Some("left lamp"));
+        assert_eq!(root.get_color(), Vec4::ONE);
+        assert_eq!(root.get_transform().get_position(), Vec3::X);
+        assert_eq!(root.get_transform().get_scale(), Vec3::new(0.5, 4., 0.5));
+        assert!(matches!(root.get_collider(), Some(Collider::Aabb { .. })));
+
+        let bulbs = children(&scene, lamp);
+        assert_eq!(bulbs.len(), 1);
+        let bulb = bulbs[0];
+        // Overrides only apply to the root, the child scale is combined with
+        // the one of its own prefab.
+        assert_eq!(bulb.get_name(), None);
+        assert_eq!(bulb.get_color(), Vec4::new(1., 0.9, 0.4, 1.));
+        assert_eq!(bulb.get_transform().get_position(), Vec3::new(0., 0.6, 0.));
+        assert_eq!(bulb.get_transform().get_scale(), Vec3::ONE);
+        assert_eq!(scene.is_static(bulb.get_id()), Some(false));
+        assert_eq!(
+            scene.get_assets().get_mesh_name(bulb.get_mesh()),
+            Some("sphere")
+        );
+    }
+
+    #[test]
+    fn reloads_instances() {
+        let path = std::env::temp_dir().join(format!("prefabs-{}.ron", std::process::id()));
+        fs::write(&path, PREFABS).unwrap();
+        let mut prefabs = PrefabLibrary::load(&path).unwrap();
+        let mut scene = Scene::new();
+        let lamp = prefabs
+            .instantiate(&mut scene, "lamp", Vec3::X, &Overrides::default())
+            .unwrap();
+        let old_bulb = children(&scene, lamp)[0].get_id();
+        assert!(!prefabs.reload_if_changed(&mut scene).unwrap());
+
+        let edited = PREFABS
+            .replace("is_static: true", "is_static: false")
+            .replace("(0.4, 0.4, 0.4, 1.0)", "(0.1, 0.2, 0.3, 1.0)");
+        fs::write(&path, edited).unwrap();
+        // Timestamps may not have moved on a coarse clock.
+        prefabs.modified = None;
+        let reloaded = prefabs.reload_if_changed(&mut scene);
+        fs::remove_file(&path).unwrap();
+        assert!(reloaded.unwrap());
+
+        assert_eq!(scene.is_static(lamp), Some(false));
+        let root = scene.get_entity(lamp).unwrap();
+        assert_eq!(root.get_color(), Vec4::new(0.1, 0.2, 0.3, 1.));
+        assert_eq!(root.get_transform().get_position(), Vec3::X);
+        assert!(scene.get_entity(old_bulb).is_none());
+        assert_eq!(children(&scene, lamp).len(), 1);
+        assert!(scene.get_static_entities().is_empty());
+    }
+}
@@ -1,102 +1,827 @@
-use glam::Mat4;
+use std::{collections::HashSet, fmt, mem::size_of, time::Instant};
 
-use crate::{application::Window, glcall, scene::Scene};
+use glam::{Mat4, Vec3, Vec4};
 
-use self::{mesh::Mesh, shader::ShaderProgram, vertex_array::VertexArray};
+use crate::{
+    application::Window,
+    asset::AssetNames,
+    bounds::{Aabb, Frustum},
+    camera::Camera,
+    entity::Entity,
+    glcall,
+    scene::Scene,
+};
 
+pub use self::{
+    culling::Culling,
+    deferred::RenderPath,
+    lighting::{BlinnPhongMaterial, Material, MaterialTextures, PbrMaterial},
+    particles::ParticleSystem,
+    render_mode::RenderMode,
+    shadow::ShadowSettings,
+    stats::FrameStats,
+    text::{Font, GlyphFormat, TextRenderer, TextStyle},
+};
+
+use self::{
+    culling::{CullTarget, GpuCuller},
+    debug::DebugRenderer,
+    deferred::Deferred,
+    framebuffer::Framebuffer,
+    ibl::Ibl,
+    lighting::Lighting,
+    mesh::Mesh,
+    post::PostProcessing,
+    render_mode::Visualizer,
+    shader::ShaderProgram,
+    shadow::ShadowMaps,
+    skybox::Skybox,
+    stats::GpuTimer,
+    stream_buffer::StreamBuffer,
+    texture::{Sampler, SamplerSettings, Texture2D},
+    vertex_array::VertexArray,
+};
+
+mod culling;
+mod debug;
+mod deferred;
+mod framebuffer;
+mod ibl;
 mod index_buffer;
-mod mesh;
+mod lighting;
+pub mod mesh;
+mod particles;
+mod post;
+mod render_mode;
 pub mod shader;
+mod shadow;
+mod skybox;
+mod stats;
+mod stream_buffer;
+mod text;
+pub mod texture;
 mod vertex_array;
 pub mod vertex_buffer;
 
+/// Per-instance data read by the vertex shader.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct Instance {
+    pub model: Mat4,
+    pub color: Vec4,
+    /// Index into the renderer's materials.
+    pub material: u32,
+    /// Zero to leave the instance out of shadow maps.
+    pub casts_shadow: u32,
+    /// Id of the entity, told apart by color in [`RenderMode::InstanceId`].
+    pub entity: u32,
+    pub _padding: u32,
+}
+
+/// Everything drawn with one mesh. Static instances live in the batch's own
+/// buffer, dynamic ones in a slice of the shared stream buffer. With CPU
+/// culling each set is drawn with one instanced call, with GPU culling both
+/// are culled into `cull` and drawn with one indirect call.
+struct Batch {
+    name: String,
+    vao: VertexArray,
+    mesh: Mesh,
+    textures: MaterialTextures,
+    cull: CullTarget,
+    static_count: usize,
+    /// First instance in the current stream buffer region.
+    dynamic_start: usize,
+    /// Dynamic instances in the stream, visible ones first. With CPU
+    /// culling only the first `dynamic_count` are drawn to the screen, the
+    /// others only cast shadows.
+    dynamic_casters: usize,
+    dynamic_count: usize,
+}
+
 pub struct Renderer {
-    count: i32,
-    last_static_index: isize,
+    batches: Vec<Batch>,
+    /// Batch of every mesh id of the scene, resolved by name.
+    batch_of: Vec<usize>,
+    static_count: usize,
+    static_version: u64,
+    stream: StreamBuffer<Instance>,
+    culling: Culling,
+    culler: GpuCuller,
+    materials: Vec<(String, Material)>,
+    /// Renderer material of every material id the scene registered so far.
+    material_of: Vec<u32>,
+    lighting: Lighting,
+    shadows: ShadowMaps,
+    ibl: Ibl,
+    skybox: Skybox,
+    /// Bound in place of missing material maps.
+    white: Texture2D,
+    /// Bound in place of a missing normal map.
+    flat_normal: Texture2D,
+    sampler: Sampler,
+    path: RenderPath,
+    mode: RenderMode,
+    visualizer: Visualizer,
+    deferred: Deferred,
+    /// Target of the lit scene, read by the post-processing chain.
+    hdr: Framebuffer,
+    post: PostProcessing,
+    debug: DebugRenderer,
+    text: TextRenderer,
+    particles: ParticleSystem,
+    stats: FrameStats,
+    gpu_timer: GpuTimer,
     shader: ShaderProgram,
-    vao: VertexArray,
-    _mesh: Mesh,
     projection: Mat4,
+    aspect: f32,
+    /// Window size in pixels.
+    size: (i32, i32),
+}
+
+impl Batch {
+    /// Draws the static instances and the first `dynamic` instances of the
+    /// current stream region. Returns the number of draw calls.
+    fn draw(&self, stream: &StreamBuffer<Instance>, dynamic: usize) -> u32 {
+        self.vao.bind();
+        let mut calls = 0;
+        if self.static_count > 0 {
+            self.vao
+                .bind_instances(self.vao.instanced_buffer.get_id(), 0);
+            draw_instances(&self.vao, self.static_count);
+            calls += 1;
+        }
+        if dynamic > 0 {
+            let offset = self.dynamic_start * size_of::<Instance>();
+            self.vao
+                .bind_instances(stream.get_id(), stream.get_offset() + offset as isize);
+            draw_instances(&self.vao, dynamic);
+            calls += 1;
+        }
+        calls
+    }
+
+    /// Draws the instances that passed culling, on the CPU or the GPU.
+    /// Returns the number of draw calls.
+    fn draw_visible(&self, stream: &StreamBuffer<Instance>, culling: Culling) -> u32 {
+        match culling {
+            Culling::Gpu => {
+                if self.static_count + self.dynamic_count == 0 {
+                    return 0;
+                }
+                self.vao.bind();
+                self.vao.bind_instances(self.cull.get_visible_id(), 0);
+                self.cull.draw();
+                1
+            }
+            Culling::Cpu => self.draw(stream, self.dynamic_count),
+        }
+    }
+
+    /// Writes `data` after the first `start` instances, growing the buffer if
+    /// needed.
+    fn upload(&mut self, data: &[Instance], start: usize) {
+        self.vao.reserve_instances(start + data.len(), start);
+        let offset = (start * size_of::<Instance>()) as isize;
+        if let Err(e) = self.vao.instanced_buffer.set_data(data, offset) {
+            panic!("Cannot upload instances of mesh {:?}: {e}", self.name);
+        }
+    }
+}
+
+/// Instance buffer usage of one mesh.
+#[derive(Clone, Debug)]
+pub struct BufferUsage {
+    pub mesh: String,
+    pub instances: usize,
+    pub capacity: usize,
+}
+
+impl fmt::Display for BufferUsage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let size = size_of::<Instance>();
+        write!(
+            f,
+            "{}: {} / {} instances ({} / {} KiB)",
+            self.mesh,
+            self.instances,
+            self.capacity,
+            self.instances * size / 1024,
+            self.capacity * size / 1024
+        )
+    }
 }
 
 impl Renderer {
-    const MAX_COUNT: isize = (100 as isize).pow(3);
+    /// Instances a mesh has room for before its buffer first grows.
+    const INITIAL_CAPACITY: isize = 1024;
+    /// Frames the CPU may write ahead of the GPU.
+    const STREAM_REGIONS: usize = 3;
+    /// Vertical field of view in degrees.
+    const FOV: f32 = 45.;
+    const NEAR: f32 = 0.1;
+    const FAR: f32 = 1000.;
+    /// Texture unit of the shadow maps.
+    const SHADOW_UNIT: u32 = 0;
+    /// First of the texture units of the five material maps.
+    const MATERIAL_UNIT: u32 = 1;
+    /// First of the texture units of the four image based lighting maps.
+    const IBL_UNIT: u32 = 6;
+    const PARTICLE_COUNT: usize = 1 << 19;
 
-    pub fn cube(window: &Window) -> Self {
-        let mesh = Mesh::cube();
-        let vao = VertexArray::new(&mesh, Self::MAX_COUNT);
+    /// Creates a renderer with the built-in `cube` and `sphere` meshes, the
+    /// `default`, `matte`, `glossy`, `metal` and `emissive` PBR materials,
+    /// and the `blinn_phong`, `blinn_phong_matte` and `blinn_phong_glossy`
+    /// materials.
+    pub fn new(window: &Window) -> Self {
         let shader = ShaderProgram::new(
             include_str!("../resources/vertex.glsl"),
-            include_str!("../resources/fragment.glsl"),
+            concat!(
+                include_str!("../resources/lighting.glsl"),
+                include_str!("../resources/material.glsl"),
+                include_str!("../resources/fragment.glsl"),
+            ),
         );
 
-        let projection = Mat4::perspective_rh(
-            (45 as f32).to_radians(),
-            window.get_aspect_ratio(),
-            0.1,
-            1000.,
-        );
+        let aspect = window.get_aspect_ratio();
+        let projection = Self::perspective(aspect);
+        let size = (window.width as i32, window.height as i32);
 
-        Self {
-            count: 0,
-            last_static_index: 0,
+        let mut renderer = Self {
+            batches: vec![],
+            batch_of: vec![],
+            static_count: 0,
+            static_version: 0,
+            stream: StreamBuffer::new(Self::INITIAL_CAPACITY as usize, Self::STREAM_REGIONS),
+            culling: Culling::Cpu,
+            culler: GpuCuller::new(),
+            materials: vec![],
+            material_of: vec![],
+            lighting: Lighting::new(),
+            shadows: ShadowMaps::new(),
+            ibl: Ibl::new(),
+            skybox: Skybox::new(),
+            white: Texture2D::from_rgba(1, 1, &[255; 4], false),
+            flat_normal: Texture2D::from_rgba(1, 1, &[128, 128, 255, 255], false),
+            sampler: Sampler::new(&SamplerSettings::default()),
+            path: RenderPath::Forward,
+            mode: RenderMode::Shaded,
+            visualizer: Visualizer::new(),
+            deferred: Deferred::new(size.0, size.1),
+            hdr: Framebuffer::new(size.0, size.1, &[gl::RGBA16F], true),
+            post: PostProcessing::new(size.0, size.1),
+            debug: DebugRenderer::new(),
+            text: TextRenderer::new(),
+            particles: ParticleSystem::new(Self::PARTICLE_COUNT),
+            stats: FrameStats::default(),
+            gpu_timer: GpuTimer::new(),
             shader,
-            vao,
-            _mesh: mesh,
             projection,
+            aspect,
+            size,
+        };
+        renderer.add_mesh(AssetNames::DEFAULT_MESH, Mesh::cube());
+        renderer.add_mesh("sphere", Mesh::sphere(24, 16));
+        renderer.add_material(AssetNames::DEFAULT_MATERIAL, Material::default());
+        renderer.add_material(
+            "matte",
+            Material::Pbr(PbrMaterial {
+                roughness: 0.9,
+                ..Default::default()
+            }),
+        );
+        renderer.add_material(
+            "glossy",
+            Material::Pbr(PbrMaterial {
+                roughness: 0.1,
+                ..Default::default()
+            }),
+        );
+        renderer.add_material(
+            "metal",
+            Material::Pbr(PbrMaterial {
+                metallic: 1.,
+                roughness: 0.3,
+                ..Default::default()
+            }),
+        );
+        renderer.add_material(
+            "emissive",
+            Material::Pbr(PbrMaterial {
+                base_color: Vec4::new(0., 0., 0., 1.),
+                emissive: Vec3::splat(4.),
+                ..Default::default()
+            }),
+        );
+        renderer.add_material(
+            "blinn_phong",
+            Material::BlinnPhong(BlinnPhongMaterial::default()),
+        );
+        renderer.add_material(
+            "blinn_phong_matte",
+            Material::BlinnPhong(BlinnPhongMaterial {
+                specular: 0.05,
+                shininess: 8.,
+                ..Default::default()
+            }),
+        );
+        renderer.add_material(
+            "blinn_phong_glossy",
+            Material::BlinnPhong(BlinnPhongMaterial {
+                ambient: 0.1,
+                specular: 1.,
+                shininess: 128.,
+            }),
+        );
+        renderer
+    }
+
+    /// Registers `mesh` for the entities whose mesh is called `name`.
+    /// Entities with a mesh the renderer does not know are drawn as cubes.
+    pub fn add_mesh(&mut self, name: &str, mesh: Mesh) {
+        let batch = Batch {
+            name: name.to_owned(),
+            vao: VertexArray::new(&mesh, Self::INITIAL_CAPACITY),
+            mesh,
+            textures: MaterialTextures::default(),
+            cull: CullTarget::new(),
+            static_count: 0,
+            dynamic_start: 0,
+            dynamic_count: 0,
+            dynamic_casters: 0,
+        };
+        match self.batches.iter().position(|b| b.name == name) {
+            Some(index) => self.batches[index] = batch,
+            None => self.batches.push(batch),
         }
+        // Names are resolved again and static instances sent again.
+        self.batch_of.clear();
+        self.static_version = u64::MAX;
     }
 
-    pub fn on_resize(&mut self, window: &Window) {
-        self.projection = Mat4::perspective_rh(
-            (45 as f32).to_radians(),
-            window.get_aspect_ratio(),
-            0.1,
-            1000.,
-        );
+    /// Names of the registered meshes and their local bounds.
+    pub fn get_mesh_bounds(&self) -> impl Iterator<Item = (&str, &Aabb)> {
+        self.batches
+            .iter()
+            .map(|b| (b.name.as_str(), b.mesh.get_bounds()))
     }
 
-    pub fn draw(&mut self, scene: &mut Scene) {
-        self.vao.bind();
+    /// Sets the material maps of every entity drawn with the mesh called
+    /// `name`. Returns false if there is no such mesh.
+    pub fn set_textures(&mut self, name: &str, textures: MaterialTextures) -> bool {
+        match self.batches.iter_mut().find(|b| b.name == name) {
+            Some(batch) => {
+                batch.textures = textures;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Registers `material` for the entities whose material is called `name`.
+    /// Entities with a material the renderer does not know use the default.
+    pub fn add_material(&mut self, name: &str, material: Material) {
+        match self.materials.iter().position(|(n, _)| n == name) {
+            Some(index) => self.materials[index].1 = material,
+            None => self.materials.push((name.to_owned(), material)),
+        }
+        let materials: Vec<_> = self.materials.iter().map(|(_, m)| *m).collect();
+        self.lighting.set_materials(&materials);
+        // Instances store the material index, so names are resolved again
+        // and static instances sent again.
+        self.material_of.clear();
+        self.static_version = u64::MAX;
+    }
 
-        let data: Vec<_> = scene
-            .get_mut_static_entities()
+    /// Static instance buffer usage of every mesh as of the last frame,
+    /// followed by the usage of one region of the dynamic stream buffer.
+    pub fn get_buffer_usage(&self) -> Vec<BufferUsage> {
+        let mut usage: Vec<_> = self
+            .batches
             .iter()
-            .map(|e| e.get_transform().get_matrix())
+            .map(|b| BufferUsage {
+                mesh: b.name.clone(),
+                instances: b.static_count,
+                capacity: b.vao.get_instance_capacity(),
+            })
             .collect();
+        usage.push(BufferUsage {
+            mesh: "dynamic".to_owned(),
+            instances: self.batches.iter().map(|b| b.dynamic_count).sum(),
+            capacity: self.stream.get_capacity(),
+        });
+        usage
+    }
+
+    /// Volume `camera` sees with the current projection.
+    #[inline]
+    pub fn get_frustum(&self, camera: &Camera) -> Frustum {
+        Frustum::from_matrix(&(self.projection * camera.get_matrix()))
+    }
+
+    /// What the last frame cost.
+    #[inline]
+    pub fn get_stats(&self) -> &FrameStats {
+        &self.stats
+    }
+
+    #[inline]
+    pub fn get_culling(&self) -> Culling {
+        self.culling
+    }
+
+    #[inline]
+    pub fn set_culling(&mut self, culling: Culling) {
+        self.culling = culling;
+    }
+
+    #[inline]
+    pub fn get_path(&self) -> RenderPath {
+        self.path
+    }
+
+    #[inline]
+    pub fn set_path(&mut self, path: RenderPath) {
+        self.path = path;
+    }
+
+    #[inline]
+    pub fn get_mode(&self) -> RenderMode {
+        self.mode
+    }
+
+    #[inline]
+    pub fn set_mode(&mut self, mode: RenderMode) {
+        self.mode = mode;
+    }
 
-        self.count += scene.get_mut_static_entities().len() as i32;
+    #[inline]
+    pub fn get_shadow_settings(&self) -> &ShadowSettings {
+        self.shadows.get_settings()
+    }
+
+    #[inline]
+    pub fn get_mut_shadow_settings(&mut self) -> &mut ShadowSettings {
+        self.shadows.get_mut_settings()
+    }
+
+    /// How material maps are sampled.
+    #[inline]
+    pub fn get_sampler_settings(&self) -> &SamplerSettings {
+        self.sampler.get_settings()
+    }
+
+    pub fn set_sampler_settings(&mut self, settings: &SamplerSettings) {
+        self.sampler = Sampler::new(settings);
+    }
+
+    #[inline]
+    pub fn get_post(&self) -> &PostProcessing {
+        &self.post
+    }
+
+    #[inline]
+    pub fn get_mut_post(&mut self) -> &mut PostProcessing {
+        &mut self.post
+    }
+
+    #[inline]
+    pub fn get_mut_ibl(&mut self) -> &mut Ibl {
+        &mut self.ibl
+    }
+
+    /// HUD text and entity labels.
+    #[inline]
+    pub fn get_mut_text(&mut self) -> &mut TextRenderer {
+        &mut self.text
+    }
+
+    /// Particles simulated on the GPU, drawn in the lit render modes.
+    #[inline]
+    pub fn get_mut_particles(&mut self) -> &mut ParticleSystem {
+        &mut self.particles
+    }
+
+    #[inline]
+    pub fn get_mut_skybox(&mut self) -> &mut Skybox {
+        &mut self.skybox
+    }
+
+    pub fn on_resize(&mut self, window: &Window) {
+        self.aspect = window.get_aspect_ratio();
+        self.projection = Self::perspective(self.aspect);
+        self.size = (window.width as i32, window.height as i32);
+        self.hdr.resize(self.size.0, self.size.1);
+        self.deferred.resize(self.size.0, self.size.1);
+        self.post.resize(self.size.0, self.size.1);
+    }
+
+    fn perspective(aspect: f32) -> Mat4 {
+        Mat4::perspective_rh(Self::FOV.to_radians(), aspect, Self::NEAR, Self::FAR)
+    }
+
+    /// Maps the meshes the scene registered since the last frame to batches.
+    fn resolve_meshes(&mut self, assets: &AssetNames) {
+        for name in assets.get_meshes()[self.batch_of.len()..].iter() {
+            let batch = self.batches.iter().position(|b| &b.name == name);
+            if batch.is_none() {
+                eprintln!("Unknown mesh {name:?}, drawing it as a cube");
+            }
+            self.batch_of.push(batch.unwrap_or(0));
+        }
+    }
+
+    /// Maps the materials the scene registered since the last frame to
+    /// renderer materials.
+    fn resolve_materials(&mut self, assets: &AssetNames) {
+        for name in assets.get_materials()[self.material_of.len()..].iter() {
+            let material = self.materials.iter().position(|(n, _)| n == name);
+            if material.is_none() {
+                eprintln!("Unknown material {name:?}, using the default one");
+            }
+            self.material_of.push(material.unwrap_or(0) as u32);
+        }
+    }
+
+    /// Splits `entities` by batch.
+    fn group<'a>(&self, entities: impl Iterator<Item = &'a Entity>) -> Vec<Vec<&'a Entity>> {
+        let mut groups = vec![vec![]; self.batches.len()];
+        for e in entities {
+            let batch = self.batch_of.get(e.get_mesh().0 as usize).copied();
+            groups[batch.unwrap_or(0)].push(e);
+        }
+        groups
+    }
+
+    pub fn draw(&mut self, scene: &mut Scene) {
+        let started = Instant::now();
+        self.gpu_timer.begin();
+        let mut stats = FrameStats::default();
+        self.resolve_meshes(scene.get_assets());
+        self.resolve_materials(scene.get_assets());
 
-        self.last_static_index += self
-            .vao
-            .instanced_buffer
-            .set_data(&data, self.last_static_index);
-        scene.get_mut_static_entities().clear();
+        // Static entities are uploaded once; only newly appended ones are sent
+        // unless the scene reports that existing ones changed.
+        if scene.get_static_version() != self.static_version {
+            self.static_version = scene.get_static_version();
+            self.static_count = 0;
+            for batch in self.batches.iter_mut() {
+                batch.static_count = 0;
+            }
+        }
+        let groups = self.group(scene.get_static_entities()[self.static_count..].iter());
+        self.static_count = scene.get_static_entities().len();
+        for (batch, entities) in self.batches.iter_mut().zip(groups) {
+            let data: Vec<_> = entities
+                .iter()
+                .map(|e| instance(scene, &self.material_of, e))
+                .collect();
+            batch.upload(&data, batch.static_count);
+            batch.static_count += data.len();
+            stats.upload_bytes += data.len() * size_of::<Instance>();
+        }
 
-        let mut count = self.count;
-        let data: Vec<_> = scene
-            .get_dynamic_entities()
+        // Dynamic entities are re-sent every frame, written straight into the
+        // mapped stream buffer. Culling on the CPU only sends the visible ones,
+        // and after them the ones inside a shadow map, which may cast shadows
+        // into view. Static ones are not culled on the CPU, which would mean
+        // sending them again every frame too.
+        let camera = scene.get_camera();
+        let view_matrix = camera.get_matrix();
+        let frustum = Frustum::from_matrix(&(self.projection * view_matrix));
+        self.shadows
+            .update(scene, Self::FOV, self.aspect, Self::NEAR);
+        let casting = self.shadows.get_layers().iter().any(|&layer| layer >= 0);
+        let groups: Vec<(Vec<_>, Vec<_>)> = match self.culling {
+            Culling::Cpu => {
+                let mut visible = vec![];
+                scene
+                    .get_dynamic_index()
+                    .query_frustum(&frustum, &mut visible);
+                if casting {
+                    let mut casters = vec![];
+                    for frustum in self.shadows.get_frusta() {
+                        scene
+                            .get_dynamic_index()
+                            .query_frustum(&frustum, &mut casters);
+                    }
+                    let mut seen: HashSet<_> = visible.iter().copied().collect();
+                    let hidden = casters
+                        .into_iter()
+                        .filter(|id| seen.insert(*id))
+                        .filter_map(|id| scene.get_entity(id));
+                    let shown = self.group(visible.iter().filter_map(|id| scene.get_entity(*id)));
+                    shown.into_iter().zip(self.group(hidden)).collect()
+                } else {
+                    let shown = self.group(visible.iter().filter_map(|id| scene.get_entity(*id)));
+                    shown.into_iter().map(|g| (g, vec![])).collect()
+                }
+            }
+            Culling::Gpu => {
+                let all = self.group(scene.get_dynamic_entities().iter());
+                all.into_iter().map(|g| (g, vec![])).collect()
+            }
+        };
+        self.stream
+            .begin_frame(groups.iter().map(|(s, h)| s.len() + h.len()).sum());
+        let mut start = 0;
+        for (batch, (shown, hidden)) in self.batches.iter_mut().zip(groups) {
+            let instances = shown
+                .iter()
+                .chain(hidden.iter())
+                .map(|e| instance(scene, &self.material_of, e));
+            batch.dynamic_start = start;
+            batch.dynamic_count = shown.len();
+            batch.dynamic_casters = self.stream.write(start, instances);
+            start += batch.dynamic_casters;
+        }
+        stats.upload_bytes += start * size_of::<Instance>();
+        stats.instances = self
+            .batches
             .iter()
-            .map(|e| e.get_transform().get_matrix())
-            .collect();
-        count += scene.get_dynamic_entities().len() as i32;
+            .map(|b| b.static_count + b.dynamic_count)
+            .sum();
+        stats.statics = self.batches.iter().map(|b| b.static_count).sum();
+        if self.culling == Culling::Cpu {
+            let shown: usize = self.batches.iter().map(|b| b.dynamic_count).sum();
+            stats.culled = Some(scene.get_dynamic_entities().len() - shown);
+            let triangles = self
+                .batches
+                .iter()
+                .map(|b| (b.static_count + b.dynamic_count) * b.vao.get_index_size() as usize / 3);
+            stats.triangles = Some(triangles.sum());
+        }
+
+        if self.culling == Culling::Gpu {
+            let first = self.stream.get_offset() as usize / size_of::<Instance>();
+            for batch in self.batches.iter_mut() {
+                let count = batch.static_count + batch.dynamic_count;
+                self.culler
+                    .begin(&frustum, &batch.mesh, &mut batch.cull, count);
+                self.culler
+                    .cull(batch.vao.instanced_buffer.get_id(), 0, batch.static_count);
+                self.culler.cull(
+                    self.stream.get_id(),
+                    first + batch.dynamic_start,
+                    batch.dynamic_count,
+                );
+            }
+            self.culler.finish();
+        }
+
+        self.shadows.render(|| {
+            for batch in self.batches.iter() {
+                stats.draw_calls += batch.draw(&self.stream, batch.dynamic_casters);
+            }
+        });
 
-        self.vao
-            .instanced_buffer
-            .set_data(&data, self.last_static_index);
+        self.lighting.set_lights(scene, self.shadows.get_layers());
+        self.lighting.bind();
 
-        self.shader.activate();
-        let view_matrix = scene.get_mut_camera().get_matrix();
-        self.shader.set_mat4("view", &view_matrix);
-        self.shader.set_mat4("projection", &self.projection);
+        // The scene is drawn in HDR. Lit, the post-processing chain brings it
+        // to the screen, the other modes are shown as they are.
+        self.hdr.bind();
         unsafe {
-            glcall!(gl::DrawElementsInstanced(
-                gl::TRIANGLES,
-                self.vao.get_index_size(),
-                gl::UNSIGNED_INT,
-                std::ptr::null(),
-                count,
-            ));
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
         }
+        let view_projection = self.projection * view_matrix;
+        match self.mode {
+            RenderMode::Shaded | RenderMode::WireframeOnShaded => {
+                stats.draw_calls += self.draw_lit(camera, &view_matrix);
+                self.particles.draw(&view_projection);
+                if self.mode == RenderMode::WireframeOnShaded {
+                    stats.draw_calls += self.draw_visualized(&view_matrix);
+                }
+                self.post.apply(&self.hdr, self.size.0, self.size.1);
+            }
+            mode => {
+                stats.draw_calls += self.draw_visualized(&view_matrix);
+                self.visualizer
+                    .present(mode, &self.hdr, self.size.0, self.size.1);
+            }
+        }
+        if let Some(depth) = self.hdr.get_depth() {
+            self.debug.draw(scene.get_debug(), &view_projection, depth);
+        }
+        self.text.draw(scene, &view_projection, self.size);
+        self.stream.end_frame();
+
+        self.gpu_timer.end();
+        stats.gpu_ms = self.gpu_timer.get_last_ms();
+        stats.cpu_ms = started.elapsed().as_secs_f32() * 1000.;
+        self.stats = stats;
+    }
+
+    /// Shades the visible instances along the render path, then draws the
+    /// sky behind them. Returns the number of draw calls of the instances.
+    fn draw_lit(&mut self, camera: &Camera, view_matrix: &Mat4) -> u32 {
+        let shader = match self.path {
+            RenderPath::Forward => {
+                self.shader.activate();
+                bind_lighting(
+                    &mut self.shader,
+                    camera,
+                    &self.lighting,
+                    &self.shadows,
+                    &self.ibl,
+                );
+                &mut self.shader
+            }
+            RenderPath::Deferred => self.deferred.begin(),
+        };
+        shader.set_mat4("view", view_matrix);
+        shader.set_mat4("projection", &self.projection);
+        let mut calls = 0;
+        for batch in self.batches.iter() {
+            let textures = &batch.textures;
+            let maps = [
+                ("u_BaseColor", &textures.base_color, &self.white),
+                (
+                    "u_MetallicRoughness",
+                    &textures.metallic_roughness,
+                    &self.white,
+                ),
+                ("u_NormalMap", &textures.normal, &self.flat_normal),
+                ("u_Occlusion", &textures.occlusion, &self.white),
+                ("u_Emissive", &textures.emissive, &self.white),
+            ];
+            for (unit, (name, texture, fallback)) in (Self::MATERIAL_UNIT..).zip(maps) {
+                let texture = texture.as_ref().unwrap_or(fallback);
+                shader.set_texture(name, unit, texture, &self.sampler);
+            }
+            calls += batch.draw_visible(&self.stream, self.culling);
+        }
+        if self.path == RenderPath::Deferred {
+            let (lighting, shadows, ibl) = (&self.lighting, &self.shadows, &self.ibl);
+            self.deferred
+                .shade(&self.hdr, view_matrix, &self.projection, |shader| {
+                    bind_lighting(shader, camera, lighting, shadows, ibl)
+                });
+        }
+        if self.skybox.is_enabled() {
+            self.skybox.draw(
+                self.ibl.get_environment(),
+                view_matrix,
+                &self.projection,
+                self.ibl.get_intensity(),
+            );
+        }
+        calls
+    }
+
+    /// Draws the visible instances with the shader of the current mode.
+    /// Returns the number of draw calls.
+    fn draw_visualized(&mut self, view_matrix: &Mat4) -> u32 {
+        self.visualizer
+            .begin(self.mode, view_matrix, &self.projection, Self::NEAR);
+        let calls = self
+            .batches
+            .iter()
+            .map(|batch| batch.draw_visible(&self.stream, self.culling))
+            .sum();
+        self.visualizer.end();
+        calls
+    }
+}
+
+/// Sets the uniforms of the shading code shared by both render paths on the
+/// active `shader`.
+fn bind_lighting(
+    shader: &mut ShaderProgram,
+    camera: &Camera,
+    lighting: &Lighting,
+    shadows: &ShadowMaps,
+    ibl: &Ibl,
+) {
+    shader.set_vec3("u_ViewPos", camera.position);
+    shader.set_vec3("u_ViewDir", camera.direction.normalize());
+    shader.set_uint("u_LightCount", lighting.get_light_count() as u32);
+    shadows.bind(shader, Renderer::SHADOW_UNIT);
+    ibl.bind(shader, Renderer::IBL_UNIT);
+}
+
+fn instance(scene: &Scene, material_of: &[u32], e: &Entity) -> Instance {
+    Instance {
+        model: scene.get_world_matrix(e),
+        color: e.get_color(),
+        material: material_of
+            .get(e.get_material().0 as usize)
+            .copied()
+            .unwrap_or(0),
+        // A light's own geometry would block it.
+        casts_shadow: e.get_light().is_none() as u32,
+        entity: e.get_id().0,
+        _padding: 0,
+    }
+}
+
+fn draw_instances(vao: &VertexArray, count: usize) {
+    unsafe {
+        glcall!(gl::DrawElementsInstanced(
+            gl::TRIANGLES,
+            vao.get_index_size(),
+            gl::UNSIGNED_INT,
+            std::ptr::null(),
+            count as i32,
+        ));
     }
 }
@@ -0,0 +1,151 @@
+use std::mem::size_of;
+
+use gl::types::GLuint;
+
+use crate::{bounds::Frustum, glcall};
+
+use super::{
+    mesh::Mesh,
+    shader::ShaderProgram,
+    vertex_buffer::{Buffer, Dynamic},
+    Instance,
+};
+
+/// How the renderer decides which instances to draw.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Culling {
+    /// Visible dynamic entities are found with the scene's spatial index and
+    /// only those are uploaded. Static instances stay in their batch's buffer
+    /// and are all drawn.
+    Cpu,
+    /// Every static and dynamic instance is tested by a compute shader, which
+    /// writes the visible ones and the draw command, so no count is read back.
+    Gpu,
+}
+
+/// Layout of `glDrawElementsIndirect` arguments.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct DrawElementsIndirectCommand {
+    count: u32,
+    instance_count: u32,
+    first_index: u32,
+    base_vertex: i32,
+    base_instance: u32,
+}
+
+/// Output of the culling shader for one mesh.
+pub struct CullTarget {
+    visible: Buffer<Dynamic>,
+    command: Buffer<Dynamic>,
+}
+
+impl CullTarget {
+    pub fn new() -> Self {
+        Self {
+            visible: Buffer::<Dynamic>::instanced::<Instance>(1),
+            command: Buffer::<Dynamic>::instanced::<DrawElementsIndirectCommand>(1),
+        }
+    }
+
+    #[inline]
+    pub fn get_visible_id(&self) -> GLuint {
+        self.visible.get_id()
+    }
+
+    /// Issues the draw written by the culling pass. The vertex array has to
+    /// be bound with its instances pointing at the visible buffer.
+    pub fn draw(&self) {
+        unsafe {
+            gl::BindBuffer(gl::DRAW_INDIRECT_BUFFER, self.command.get_id());
+            glcall!(gl::DrawElementsIndirect(
+                gl::TRIANGLES,
+                gl::UNSIGNED_INT,
+                std::ptr::null()
+            ));
+        }
+    }
+}
+
+/// Compute pass appending the instances inside the frustum to a
+/// [`CullTarget`].
+pub struct GpuCuller {
+    shader: ShaderProgram,
+}
+
+impl GpuCuller {
+    const GROUP_SIZE: usize = 64;
+
+    pub fn new() -> Self {
+        Self {
+            shader: ShaderProgram::compute(include_str!("../../resources/cull.comp")),
+        }
+    }
+
+    /// Starts culling into `target` for `mesh`, with room for `capacity`
+    /// visible instances.
+    pub fn begin(
+        &mut self,
+        frustum: &Frustum,
+        mesh: &Mesh,
+        target: &mut CullTarget,
+        capacity: usize,
+    ) {
+        let capacity = (capacity * size_of::<Instance>()) as isize;
+        if target.visible.get_capacity() < capacity {
+            target
+                .visible
+                .resize(capacity.max(target.visible.get_capacity() * 2), 0);
+        }
+        let command = DrawElementsIndirectCommand {
+            count: mesh.index_buffer.count as u32,
+            instance_count: 0,
+            first_index: 0,
+            base_vertex: 0,
+            base_instance: 0,
+        };
+        if let Err(e) = target.command.set_data(&[command], 0) {
+            panic!("Cannot reset the draw command: {e}");
+        }
+
+        self.shader.activate();
+        self.shader.set_vec4_array("u_Planes", frustum.get_planes());
+        let bounds = mesh.get_bounds();
+        self.shader.set_vec3("u_Center", bounds.get_center());
+        self.shader
+            .set_vec3("u_HalfExtents", bounds.get_half_extents());
+        unsafe {
+            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 1, target.visible.get_id());
+            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 2, target.command.get_id());
+        }
+    }
+
+    /// Culls `count` instances of `buffer` starting at instance `first`,
+    /// into the target of the last [`GpuCuller::begin`].
+    pub fn cull(&mut self, buffer: GLuint, first: usize, count: usize) {
+        if count == 0 {
+            return;
+        }
+        self.shader.set_uint("u_First", first as u32);
+        self.shader.set_uint("u_Count", count as u32);
+        unsafe {
+            gl::BindBufferBase(gl::SHADER_STORAGE_BUFFER, 0, buffer);
+            glcall!(gl::DispatchCompute(
+                count.div_ceil(Self::GROUP_SIZE) as u32,
+                1,
+                1
+            ));
+        }
+    }
+
+    /// Makes the culling results visible to the following draws.
+    pub fn finish(&self) {
+        unsafe {
+            gl::MemoryBarrier(
+                gl::SHADER_STORAGE_BARRIER_BIT
+                    | gl::COMMAND_BARRIER_BIT
+                    | gl::VERTEX_ATTRIB_ARRAY_BARRIER_BIT,
+            );
+        }
+    }
+}
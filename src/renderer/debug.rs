@@ -0,0 +1,128 @@
+use std::mem::size_of;
+
+use gl::types::GLuint;
+use glam::{Mat4, Vec4};
+
+use crate::{debug::DebugDraw, glcall};
+
+use super::{
+    shader::ShaderProgram,
+    texture::{Sampler, Texture2D},
+    vertex_array,
+    vertex_buffer::{Buffer, Dynamic},
+};
+
+/// Vertex of the line buffer.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct LineVertex {
+    /// Position, and 1 if the line is depth tested.
+    position: Vec4,
+    color: Vec4,
+}
+
+/// Draws the shapes of a [`DebugDraw`] as lines, batched into one buffer
+/// drawn with a single call.
+///
+/// The lines are drawn over the post-processed image, so they keep their
+/// colors, and are depth tested against the depth of the scene in the
+/// fragment shader.
+pub struct DebugRenderer {
+    shader: ShaderProgram,
+    vao: GLuint,
+    buffer: Buffer<Dynamic>,
+    /// Vertices the buffer has room for.
+    capacity: usize,
+    vertices: Vec<LineVertex>,
+}
+
+impl Drop for DebugRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+impl DebugRenderer {
+    const INITIAL_CAPACITY: usize = 4096;
+    const DEPTH_UNIT: u32 = 0;
+
+    pub fn new() -> Self {
+        let mut vao = 0;
+        unsafe {
+            gl::CreateVertexArrays(1, &mut vao);
+            for index in 0..2 {
+                gl::EnableVertexArrayAttrib(vao, index);
+                gl::VertexArrayAttribBinding(vao, index, 0);
+                gl::VertexArrayAttribFormat(vao, index, 4, gl::FLOAT, gl::FALSE, index * 16);
+            }
+        }
+        let renderer = Self {
+            shader: ShaderProgram::new(
+                include_str!("../../resources/v_debug.glsl"),
+                include_str!("../../resources/f_debug.glsl"),
+            ),
+            vao,
+            buffer: Buffer::<Dynamic>::instanced::<LineVertex>(Self::INITIAL_CAPACITY as isize),
+            capacity: Self::INITIAL_CAPACITY,
+            vertices: vec![],
+        };
+        renderer.attach_buffer();
+        renderer
+    }
+
+    fn attach_buffer(&self) {
+        unsafe {
+            gl::VertexArrayVertexBuffer(
+                self.vao,
+                0,
+                self.buffer.get_id(),
+                0,
+                size_of::<LineVertex>() as i32,
+            );
+        }
+    }
+
+    /// Draws the lines of `debug` into the bound framebuffer, seen through
+    /// `view_projection`. `depth` holds the depth of the scene drawn there.
+    pub fn draw(&mut self, debug: &DebugDraw, view_projection: &Mat4, depth: &Texture2D) {
+        self.vertices.clear();
+        for (lines, depth_test) in debug.get_lines() {
+            let flag = depth_test as u32 as f32;
+            self.vertices.extend(lines.iter().map(|v| LineVertex {
+                position: v.position.extend(flag),
+                color: v.color,
+            }));
+        }
+        if self.vertices.is_empty() {
+            return;
+        }
+        if self.vertices.len() > self.capacity {
+            self.capacity = self.vertices.len().next_power_of_two();
+            self.buffer
+                .resize((self.capacity * size_of::<LineVertex>()) as isize, 0);
+            self.attach_buffer();
+        }
+        if let Err(e) = self.buffer.set_data(&self.vertices, 0) {
+            panic!("Cannot upload debug lines: {e}");
+        }
+
+        self.shader.activate();
+        self.shader.set_mat4("u_ViewProjection", view_projection);
+        depth.bind(Self::DEPTH_UNIT);
+        Sampler::unbind(Self::DEPTH_UNIT);
+        self.shader.set_int("u_Depth", Self::DEPTH_UNIT as i8);
+        vertex_array::bind_id(self.vao);
+        unsafe {
+            // The scene depth is compared in the fragment shader, the one of
+            // the bound framebuffer may not match it.
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            glcall!(gl::DrawArrays(gl::LINES, 0, self.vertices.len() as i32));
+            gl::Disable(gl::BLEND);
+            gl::Enable(gl::DEPTH_TEST);
+        }
+    }
+}
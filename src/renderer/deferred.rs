@@ -0,0 +1,131 @@
+use glam::Mat4;
+
+use crate::glcall;
+
+use super::{framebuffer::Framebuffer, shader::ShaderProgram};
+
+/// How the main pass shades the scene.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderPath {
+    /// Every fragment is lit by every light as it is drawn.
+    Forward,
+    /// The surfaces are drawn into a G-buffer first, then lit once per pixel
+    /// by the lights reaching its screen tile, suiting many small lights.
+    Deferred,
+}
+
+/// G-buffer and shaders of the deferred path.
+///
+/// The G-buffer holds base color and metalness, world normal and roughness,
+/// emission and occlusion, and the shading model with its Blinn-Phong
+/// parameters, plus depth, from which positions are rebuilt.
+pub struct Deferred {
+    gbuffer: Framebuffer,
+    geometry: ShaderProgram,
+    lighting: ShaderProgram,
+}
+
+impl Deferred {
+    /// Tile size of the lighting compute shader.
+    const TILE_SIZE: u32 = 16;
+    /// First texture unit of the G-buffer maps, after the ones the shared
+    /// lighting code uses.
+    const GBUFFER_UNIT: u32 = 10;
+
+    pub fn new(width: i32, height: i32) -> Self {
+        Self {
+            gbuffer: Framebuffer::new(
+                width,
+                height,
+                &[gl::RGBA8, gl::RGBA16F, gl::RGBA16F, gl::RGBA16F],
+                true,
+            ),
+            geometry: ShaderProgram::new(
+                include_str!("../../resources/vertex.glsl"),
+                concat!(
+                    include_str!("../../resources/lighting.glsl"),
+                    include_str!("../../resources/material.glsl"),
+                    include_str!("../../resources/f_gbuffer.glsl"),
+                ),
+            ),
+            lighting: ShaderProgram::compute(concat!(
+                include_str!("../../resources/lighting.glsl"),
+                include_str!("../../resources/deferred.comp"),
+            )),
+        }
+    }
+
+    /// Follows the screen size.
+    pub fn resize(&mut self, width: i32, height: i32) {
+        self.gbuffer.resize(width, height);
+    }
+
+    /// Binds and clears the G-buffer, and returns the shader drawing into it,
+    /// which takes the same inputs as the forward one.
+    pub fn begin(&mut self) -> &mut ShaderProgram {
+        self.gbuffer.bind();
+        unsafe {
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+        }
+        self.geometry.activate();
+        &mut self.geometry
+    }
+
+    /// Lights the G-buffer into the first color attachment of `target` and
+    /// copies its depth there too, so later passes can test against it.
+    ///
+    /// `bind_lighting` sets the uniforms of the shared lighting code on the
+    /// lighting shader.
+    pub fn shade(
+        &mut self,
+        target: &Framebuffer,
+        view: &Mat4,
+        projection: &Mat4,
+        bind_lighting: impl FnOnce(&mut ShaderProgram),
+    ) {
+        let (width, height) = (self.gbuffer.get_width(), self.gbuffer.get_height());
+        self.gbuffer.blit_depth(target);
+
+        self.lighting.activate();
+        bind_lighting(&mut self.lighting);
+        let maps = ["u_GBaseColor", "u_GNormal", "u_GEmissive", "u_GShading"];
+        for (i, name) in maps.into_iter().enumerate() {
+            let unit = Self::GBUFFER_UNIT + i as u32;
+            self.gbuffer.get_color(i).bind(unit);
+            self.lighting.set_int(name, unit as i8);
+        }
+        let depth_unit = Self::GBUFFER_UNIT + maps.len() as u32;
+        if let Some(depth) = self.gbuffer.get_depth() {
+            depth.bind(depth_unit);
+        }
+        self.lighting.set_int("u_GDepth", depth_unit as i8);
+        self.lighting.set_mat4("u_View", view);
+        self.lighting
+            .set_mat4("u_InverseProjection", &projection.inverse());
+        self.lighting
+            .set_mat4("u_InverseViewProjection", &(*projection * *view).inverse());
+
+        unsafe {
+            glcall!(gl::BindImageTexture(
+                0,
+                target.get_color(0).get_id(),
+                0,
+                gl::FALSE,
+                0,
+                gl::WRITE_ONLY,
+                gl::RGBA16F
+            ));
+            glcall!(gl::DispatchCompute(
+                (width as u32).div_ceil(Self::TILE_SIZE),
+                (height as u32).div_ceil(Self::TILE_SIZE),
+                1
+            ));
+            gl::MemoryBarrier(
+                gl::TEXTURE_FETCH_BARRIER_BIT
+                    | gl::FRAMEBUFFER_BARRIER_BIT
+                    | gl::SHADER_IMAGE_ACCESS_BARRIER_BIT,
+            );
+        }
+        target.bind();
+    }
+}
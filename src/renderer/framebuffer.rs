@@ -0,0 +1,161 @@
+use gl::types::{GLenum, GLuint};
+
+use super::texture::Texture2D;
+
+/// Offscreen render target. Its attachments are textures, so later passes can
+/// read what was drawn into it.
+pub struct Framebuffer {
+    id: GLuint,
+    width: i32,
+    height: i32,
+    formats: Vec<GLenum>,
+    colors: Vec<Texture2D>,
+    depth: Option<Texture2D>,
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.id);
+        }
+    }
+}
+
+impl Framebuffer {
+    /// Creates a framebuffer with one color attachment per sized internal
+    /// format in `formats`, and a 32 bit float depth attachment if `depth`.
+    pub fn new(width: i32, height: i32, formats: &[GLenum], depth: bool) -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::CreateFramebuffers(1, &mut id);
+        }
+        let mut framebuffer = Self {
+            id,
+            width: width.max(1),
+            height: height.max(1),
+            formats: formats.to_vec(),
+            colors: vec![],
+            depth: depth.then(|| Texture2D::with_format(1, 1, 1, gl::DEPTH_COMPONENT32F)),
+        };
+        framebuffer.attach();
+        framebuffer
+    }
+
+    /// Recreates the attachments at the current size. Their content is lost.
+    fn attach(&mut self) {
+        let (width, height) = (self.width, self.height);
+        self.colors = self
+            .formats
+            .iter()
+            .map(|&format| Texture2D::with_format(width, height, 1, format))
+            .collect();
+        let buffers: Vec<_> = (0..self.colors.len() as u32)
+            .map(|i| gl::COLOR_ATTACHMENT0 + i)
+            .collect();
+        unsafe {
+            for (buffer, texture) in buffers.iter().zip(self.colors.iter()) {
+                gl::NamedFramebufferTexture(self.id, *buffer, texture.get_id(), 0);
+            }
+            gl::NamedFramebufferDrawBuffers(self.id, buffers.len() as i32, buffers.as_ptr());
+        }
+        if let Some(depth) = self.depth.as_mut() {
+            *depth = Texture2D::with_format(width, height, 1, gl::DEPTH_COMPONENT32F);
+            unsafe {
+                gl::NamedFramebufferTexture(self.id, gl::DEPTH_ATTACHMENT, depth.get_id(), 0);
+            }
+        }
+
+        let status = unsafe { gl::CheckNamedFramebufferStatus(self.id, gl::FRAMEBUFFER) };
+        assert!(
+            status == gl::FRAMEBUFFER_COMPLETE,
+            "Incomplete framebuffer: {status:#x}"
+        );
+    }
+
+    /// Reallocates the attachments if the size changed.
+    pub fn resize(&mut self, width: i32, height: i32) {
+        let (width, height) = (width.max(1), height.max(1));
+        if (width, height) != (self.width, self.height) {
+            self.width = width;
+            self.height = height;
+            self.attach();
+        }
+    }
+
+    /// Draws into the framebuffer, over all of it.
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.id);
+            gl::Viewport(0, 0, self.width, self.height);
+        }
+    }
+
+    /// Draws into the window again, over `width` by `height` pixels.
+    pub fn bind_default(width: i32, height: i32) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(0, 0, width, height);
+        }
+    }
+
+    /// Copies the first color attachment to the window, scaled to `width` by
+    /// `height` pixels.
+    pub fn blit_to_default(&self, width: i32, height: i32) {
+        unsafe {
+            gl::BlitNamedFramebuffer(
+                self.id,
+                0,
+                0,
+                0,
+                self.width,
+                self.height,
+                0,
+                0,
+                width,
+                height,
+                gl::COLOR_BUFFER_BIT,
+                gl::LINEAR,
+            );
+        }
+    }
+
+    /// Copies the depth attachment to the one of `target`, of the same size.
+    pub fn blit_depth(&self, target: &Framebuffer) {
+        unsafe {
+            gl::BlitNamedFramebuffer(
+                self.id,
+                target.id,
+                0,
+                0,
+                self.width,
+                self.height,
+                0,
+                0,
+                target.width,
+                target.height,
+                gl::DEPTH_BUFFER_BIT,
+                gl::NEAREST,
+            );
+        }
+    }
+
+    #[inline]
+    pub fn get_color(&self, index: usize) -> &Texture2D {
+        &self.colors[index]
+    }
+
+    #[inline]
+    pub fn get_depth(&self) -> Option<&Texture2D> {
+        self.depth.as_ref()
+    }
+
+    #[inline]
+    pub fn get_width(&self) -> i32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn get_height(&self) -> i32 {
+        self.height
+    }
+}
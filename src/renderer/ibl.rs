@@ -0,0 +1,187 @@
+use std::path::Path;
+
+use gl::types::{GLenum, GLuint};
+
+use crate::glcall;
+
+use super::{
+    shader::ShaderProgram,
+    texture::{Cubemap, Sampler, Texture2D},
+};
+
+/// Image based lighting from an environment surrounding the scene: diffuse
+/// irradiance and prefiltered specular reflections of the environment, and
+/// the BRDF lookup table of the split sum approximation. The environment
+/// itself is the sky and serves sharp reflections.
+pub struct Ibl {
+    environment: Cubemap,
+    irradiance: Cubemap,
+    /// Reflections for increasing roughness down the mip levels.
+    prefiltered: Cubemap,
+    brdf: Texture2D,
+    intensity: f32,
+    irradiance_shader: ShaderProgram,
+    prefilter_shader: ShaderProgram,
+}
+
+impl Ibl {
+    const ENVIRONMENT_SIZE: i32 = 512;
+    const IRRADIANCE_SIZE: i32 = 32;
+    const PREFILTERED_SIZE: i32 = 128;
+    const PREFILTERED_LEVELS: i32 = 5;
+    const BRDF_SIZE: i32 = 256;
+    const FORMAT: GLenum = gl::RGBA16F;
+    /// Local size of the baking compute shaders along X and Y.
+    const GROUP_SIZE: i32 = 8;
+    /// Radiance of the uniform environment used until one is loaded.
+    const DEFAULT_RADIANCE: f32 = 0.2;
+    /// Face size of that environment.
+    const DEFAULT_SIZE: i32 = 16;
+
+    /// Generates the BRDF lookup table and bakes a uniform grey environment.
+    pub fn new() -> Self {
+        unsafe {
+            // Filters across cube faces instead of clamping to each one.
+            gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+        }
+        let brdf = Texture2D::with_format(Self::BRDF_SIZE, Self::BRDF_SIZE, 1, gl::RG16F);
+        let brdf_shader = ShaderProgram::compute(include_str!("../../resources/brdf.comp"));
+        brdf_shader.activate();
+        bind_image(brdf.get_id(), 0, gl::RG16F);
+        dispatch(Self::BRDF_SIZE, 1);
+        unsafe {
+            for wrap in [gl::TEXTURE_WRAP_S, gl::TEXTURE_WRAP_T] {
+                gl::TextureParameteri(brdf.get_id(), wrap, gl::CLAMP_TO_EDGE as i32);
+            }
+            gl::TextureParameteri(brdf.get_id(), gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TextureParameteri(brdf.get_id(), gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+        }
+
+        let radiance = Self::DEFAULT_RADIANCE;
+        let uniform = Texture2D::from_rgba_f32(1, 1, &[radiance, radiance, radiance, 1.]);
+        let mut ibl = Self {
+            environment: Cubemap::from_equirect(&uniform, Self::DEFAULT_SIZE),
+            irradiance: Cubemap::new(Self::IRRADIANCE_SIZE, 1, Self::FORMAT),
+            prefiltered: Cubemap::new(
+                Self::PREFILTERED_SIZE,
+                Self::PREFILTERED_LEVELS,
+                Self::FORMAT,
+            ),
+            brdf,
+            intensity: 1.,
+            irradiance_shader: ShaderProgram::compute(include_str!(
+                "../../resources/irradiance.comp"
+            )),
+            prefilter_shader: ShaderProgram::compute(include_str!(
+                "../../resources/prefilter.comp"
+            )),
+        };
+        ibl.bake();
+        ibl
+    }
+
+    /// Loads an equirectangular Radiance HDR environment and bakes it.
+    pub fn load_environment(&mut self, path: impl AsRef<Path>) -> image::ImageResult<()> {
+        self.set_environment(Cubemap::load_equirect(path, Self::ENVIRONMENT_SIZE)?);
+        Ok(())
+    }
+
+    /// Loads an environment from six sRGB face images and bakes it, see
+    /// [`Cubemap::load`].
+    pub fn load_environment_faces<P: AsRef<Path>>(
+        &mut self,
+        faces: [P; 6],
+    ) -> image::ImageResult<()> {
+        self.set_environment(Cubemap::load(faces)?);
+        Ok(())
+    }
+
+    /// Replaces the environment, which needs a full mip chain, and bakes its
+    /// lighting.
+    pub fn set_environment(&mut self, environment: Cubemap) {
+        self.environment = environment;
+        self.bake();
+    }
+
+    fn bake(&mut self) {
+        let size = self.environment.get_size();
+        // Sampling a level with about as many texels as the irradiance map
+        // keeps the convolution from missing small bright spots.
+        let source_level = (size / Self::IRRADIANCE_SIZE).max(1).ilog2();
+        self.irradiance_shader.activate();
+        self.environment.bind(0);
+        self.irradiance_shader.set_int("u_Environment", 0);
+        self.irradiance_shader
+            .set_float("u_SourceLevel", source_level as f32);
+        bind_image(self.irradiance.get_id(), 0, Self::FORMAT);
+        dispatch(Self::IRRADIANCE_SIZE, 6);
+
+        self.prefilter_shader.activate();
+        self.prefilter_shader.set_int("u_Environment", 0);
+        self.prefilter_shader.set_float("u_SourceSize", size as f32);
+        for level in 0..Self::PREFILTERED_LEVELS {
+            let roughness = level as f32 / (Self::PREFILTERED_LEVELS - 1) as f32;
+            self.prefilter_shader.set_float("u_Roughness", roughness);
+            bind_image(self.prefiltered.get_id(), level, Self::FORMAT);
+            dispatch(Self::PREFILTERED_SIZE >> level, 6);
+        }
+    }
+
+    /// Environment cube map the lighting was baked from.
+    #[inline]
+    pub fn get_environment(&self) -> &Cubemap {
+        &self.environment
+    }
+
+    #[inline]
+    pub fn get_intensity(&self) -> f32 {
+        self.intensity
+    }
+
+    /// Scales the light coming from the environment.
+    #[inline]
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.intensity = intensity;
+    }
+
+    /// Binds the maps to texture units `first_unit` to `first_unit + 3` and
+    /// sets the uniforms `shader` needs to look them up.
+    pub fn bind(&self, shader: &mut ShaderProgram, first_unit: u32) {
+        self.irradiance.bind(first_unit);
+        self.prefiltered.bind(first_unit + 1);
+        self.brdf.bind(first_unit + 2);
+        Sampler::unbind(first_unit + 2);
+        self.environment.bind(first_unit + 3);
+        shader.set_int("u_Irradiance", first_unit as i8);
+        shader.set_int("u_Prefiltered", (first_unit + 1) as i8);
+        shader.set_int("u_Brdf", (first_unit + 2) as i8);
+        shader.set_int("u_Environment", (first_unit + 3) as i8);
+        shader.set_float("u_PrefilteredLevels", self.prefiltered.get_levels() as f32);
+        shader.set_float("u_IblIntensity", self.intensity);
+    }
+}
+
+/// Binds `level` of `texture`, every layer of it, to image unit 0.
+fn bind_image(texture: GLuint, level: i32, format: GLenum) {
+    unsafe {
+        glcall!(gl::BindImageTexture(
+            0,
+            texture,
+            level,
+            gl::TRUE,
+            0,
+            gl::WRITE_ONLY,
+            format
+        ));
+    }
+}
+
+/// Runs the bound compute shader over `layers` square images `size` texels
+/// wide and makes its writes visible to later texture reads.
+fn dispatch(size: i32, layers: u32) {
+    let groups = (size.max(1) as u32).div_ceil(Ibl::GROUP_SIZE as u32);
+    unsafe {
+        glcall!(gl::DispatchCompute(groups, groups, layers));
+        gl::MemoryBarrier(gl::TEXTURE_FETCH_BARRIER_BIT | gl::SHADER_IMAGE_ACCESS_BARRIER_BIT);
+    }
+}
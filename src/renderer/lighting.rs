@@ -0,0 +1,276 @@
+use std::mem::size_of_val;
+
+use glam::{Mat4, Vec3, Vec4};
+
+use crate::{
+    light::{Light, LightKind},
+    scene::Scene,
+};
+
+use super::{
+    texture::Texture2D,
+    vertex_buffer::{Buffer, Dynamic},
+};
+
+/// Shading model and parameters of the entities using a material.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Material {
+    /// Ambient, Lambert diffuse and Blinn-Phong specular lighting, without
+    /// environment lighting.
+    BlinnPhong(BlinnPhongMaterial),
+    /// Cook-Torrance with metallic-roughness parameters and image based
+    /// lighting.
+    Pbr(PbrMaterial),
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material::Pbr(PbrMaterial::default())
+    }
+}
+
+/// Blinn-Phong shading parameters. Only the base color map of
+/// [`MaterialTextures`] applies.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlinnPhongMaterial {
+    /// Fraction of the base color visible without any light.
+    pub ambient: f32,
+    /// Strength of the highlights.
+    pub specular: f32,
+    /// Blinn-Phong exponent, higher values give smaller highlights.
+    pub shininess: f32,
+}
+
+impl Default for BlinnPhongMaterial {
+    fn default() -> Self {
+        Self {
+            ambient: 0.15,
+            specular: 0.5,
+            shininess: 32.,
+        }
+    }
+}
+
+/// Metallic-roughness shading parameters. Every factor is multiplied with
+/// the matching map of [`MaterialTextures`].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PbrMaterial {
+    /// Multiplied with the vertex and entity colors.
+    pub base_color: Vec4,
+    /// 0 for dielectrics, 1 for metals.
+    pub metallic: f32,
+    /// 0 for mirror-like surfaces, 1 for fully rough ones.
+    pub roughness: f32,
+    /// Light emitted regardless of lighting, in linear HDR units. Also
+    /// multiplied with the entity color, so one material suits any tint.
+    pub emissive: Vec3,
+    /// How much the occlusion map darkens ambient light.
+    pub occlusion_strength: f32,
+    /// Scales the tangent space XY of the normal map.
+    pub normal_scale: f32,
+}
+
+impl Default for PbrMaterial {
+    fn default() -> Self {
+        Self {
+            base_color: Vec4::ONE,
+            metallic: 0.,
+            roughness: 0.5,
+            emissive: Vec3::ZERO,
+            occlusion_strength: 1.,
+            normal_scale: 1.,
+        }
+    }
+}
+
+/// Maps modulating the [`Material`] of everything drawn with a mesh. They
+/// belong to meshes rather than materials since they follow their UV layout.
+///
+/// Channels follow glTF: occlusion in red, roughness in green and metalness
+/// in blue. Base color and emissive maps hold sRGB colors.
+#[derive(Default)]
+pub struct MaterialTextures {
+    pub base_color: Option<Texture2D>,
+    pub metallic_roughness: Option<Texture2D>,
+    /// Tangent space normals.
+    pub normal: Option<Texture2D>,
+    pub occlusion: Option<Texture2D>,
+    pub emissive: Option<Texture2D>,
+}
+
+/// Layout of a material in the shader storage block.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct GpuMaterial {
+    base_color: [f32; 4],
+    emissive: [f32; 3],
+    metallic: f32,
+    roughness: f32,
+    occlusion_strength: f32,
+    normal_scale: f32,
+    model: u32,
+    ambient: f32,
+    specular: f32,
+    shininess: f32,
+    _padding: f32,
+}
+
+impl GpuMaterial {
+    const PBR: u32 = 0;
+    const BLINN_PHONG: u32 = 1;
+
+    fn new(material: &Material) -> Self {
+        match material {
+            Material::BlinnPhong(m) => Self {
+                base_color: [1.; 4],
+                emissive: [0.; 3],
+                metallic: 0.,
+                roughness: 1.,
+                occlusion_strength: 0.,
+                normal_scale: 0.,
+                model: Self::BLINN_PHONG,
+                ambient: m.ambient,
+                specular: m.specular,
+                shininess: m.shininess.max(1.),
+                _padding: 0.,
+            },
+            Material::Pbr(m) => Self {
+                base_color: m.base_color.to_array(),
+                emissive: m.emissive.to_array(),
+                metallic: m.metallic.clamp(0., 1.),
+                // Perfectly smooth surfaces would give infinitely thin
+                // highlights.
+                roughness: m.roughness.clamp(0.03, 1.),
+                occlusion_strength: m.occlusion_strength,
+                normal_scale: m.normal_scale,
+                model: Self::PBR,
+                ambient: 0.,
+                specular: 0.,
+                shininess: 0.,
+                _padding: 0.,
+            },
+        }
+    }
+}
+
+/// Layout of a light in the shader storage block.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct GpuLight {
+    position: [f32; 3],
+    kind: u32,
+    direction: [f32; 3],
+    range: f32,
+    /// Color times intensity.
+    color: [f32; 3],
+    cos_inner: f32,
+    cos_outer: f32,
+    /// First shadow map layer, or -1.
+    shadow: i32,
+    _padding: [f32; 2],
+}
+
+impl GpuLight {
+    const DIRECTIONAL: u32 = 0;
+    const POINT: u32 = 1;
+    const SPOT: u32 = 2;
+
+    fn new(light: &Light, world: &Mat4, shadow: i32) -> Self {
+        let (kind, range, inner, outer) = match light.kind {
+            LightKind::Directional => (Self::DIRECTIONAL, 0., 0., 0.),
+            LightKind::Point { range } => (Self::POINT, range, 0., 0.),
+            LightKind::Spot {
+                range,
+                inner_angle,
+                outer_angle,
+            } => (Self::SPOT, range, inner_angle, outer_angle.max(inner_angle)),
+        };
+        Self {
+            position: world.w_axis.truncate().to_array(),
+            kind,
+            direction: world
+                .transform_vector3(Vec3::NEG_Z)
+                .normalize_or_zero()
+                .to_array(),
+            range,
+            color: (light.color * light.intensity).to_array(),
+            cos_inner: inner.to_radians().cos(),
+            cos_outer: outer.to_radians().cos(),
+            shadow,
+            _padding: [0.; 2],
+        }
+    }
+}
+
+/// Scene lights and renderer materials, kept in shader storage buffers for
+/// the shading pass. Lights are bound at 0 and materials at 1.
+pub struct Lighting {
+    lights: Buffer<Dynamic>,
+    light_count: usize,
+    materials: Buffer<Dynamic>,
+}
+
+impl Lighting {
+    const LIGHT_BINDING: u32 = 0;
+    const MATERIAL_BINDING: u32 = 1;
+
+    pub fn new() -> Self {
+        Self {
+            lights: Buffer::<Dynamic>::instanced::<GpuLight>(16),
+            light_count: 0,
+            materials: Buffer::<Dynamic>::instanced::<GpuMaterial>(16),
+        }
+    }
+
+    #[inline]
+    pub fn get_light_count(&self) -> usize {
+        self.light_count
+    }
+
+    /// Uploads every light of `scene` at its current world transform, with
+    /// the shadow map layers of [`ShadowMaps::get_layers`](super::shadow::ShadowMaps::get_layers).
+    pub fn set_lights(&mut self, scene: &Scene, shadows: &[i32]) {
+        let data: Vec<_> = scene
+            .get_lights()
+            .enumerate()
+            .map(|(i, (e, light))| {
+                let shadow = shadows.get(i).copied().unwrap_or(-1);
+                GpuLight::new(light, &scene.get_world_matrix(e), shadow)
+            })
+            .collect();
+        upload(&mut self.lights, &data, "lights");
+        self.light_count = data.len();
+    }
+
+    /// Uploads the materials, indexed by the instance material index.
+    pub fn set_materials(&mut self, materials: &[Material]) {
+        let data: Vec<_> = materials.iter().map(GpuMaterial::new).collect();
+        upload(&mut self.materials, &data, "materials");
+    }
+
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindBufferBase(
+                gl::SHADER_STORAGE_BUFFER,
+                Self::LIGHT_BINDING,
+                self.lights.get_id(),
+            );
+            gl::BindBufferBase(
+                gl::SHADER_STORAGE_BUFFER,
+                Self::MATERIAL_BINDING,
+                self.materials.get_id(),
+            );
+        }
+    }
+}
+
+/// Replaces the content of `buffer` with `data`, growing it if needed.
+fn upload<T>(buffer: &mut Buffer<Dynamic>, data: &[T], what: &str) {
+    let size = size_of_val(data) as isize;
+    if size > buffer.get_capacity() {
+        buffer.resize(size.max(buffer.get_capacity() * 2), 0);
+    }
+    if let Err(e) = buffer.set_data(data, 0) {
+        panic!("Cannot upload {what}: {e}");
+    }
+}
@@ -1,4 +1,9 @@
+use std::{collections::HashMap, f32::consts::PI, fs, io, path::Path, str::SplitWhitespace};
+
 use gl::types::*;
+use glam::Vec3;
+
+use crate::bounds::Aabb;
 
 use super::{
     index_buffer::IndexBuffer,
@@ -7,6 +12,8 @@ use super::{
 
 type Pos = [f32; 3];
 type Color = [f32; 3];
+type Normal = [f32; 3];
+type Uv = [f32; 2];
 
 const POS_DATA: [Pos; 8] = [
     [-0.5, -0.5, 0.5],
@@ -30,37 +37,225 @@ const COLOR_DATA: [Color; 8] = [
     [0.0, 1.0, 0.0],
 ];
 
-const ELEMENT_DATA: [GLuint; 36] = [
-    // front
-    0, 1, 2, 2, 3, 0, // right
-    1, 5, 6, 6, 2, 1, // back
-    7, 6, 5, 5, 4, 7, // left
-    4, 0, 3, 3, 7, 4, // bottom
-    4, 5, 1, 1, 0, 4, // top
-    3, 2, 6, 6, 7, 3,
+/// Corners of each face, counter-clockwise seen from outside starting at the
+/// bottom left, and its normal. Corners are repeated per face so every face
+/// gets its own normal and texture coordinates.
+const FACE_DATA: [([usize; 4], Normal); 6] = [
+    ([0, 1, 2, 3], [0., 0., 1.]),  // front
+    ([1, 5, 6, 2], [1., 0., 0.]),  // right
+    ([7, 6, 5, 4], [0., 0., -1.]), // back
+    ([4, 0, 3, 7], [-1., 0., 0.]), // left
+    ([4, 5, 1, 0], [0., -1., 0.]), // bottom
+    ([3, 2, 6, 7], [0., 1., 0.]),  // top
 ];
 
 pub struct Mesh {
     pub vb_list: Vec<Buffer<Static>>,
     pub index_buffer: IndexBuffer,
+    bounds: Aabb,
 }
 
 impl Mesh {
-    fn new<const N: usize, R>(
-        pos_data: &[[R; N]],
-        color_data: &[[R; N]],
+    fn new(
+        pos_data: &[Pos],
+        color_data: &[Color],
+        normal_data: &[Normal],
+        uv_data: &[Uv],
         index_data: &[GLuint],
     ) -> Self {
+        let bounds = pos_data
+            .iter()
+            .map(|&p| Vec3::from(p))
+            .fold(None, |b: Option<Aabb>, p| {
+                Some(b.map_or(Aabb::new(p, p), |b| Aabb::new(b.min.min(p), b.max.max(p))))
+            })
+            .unwrap_or(Aabb::UNIT);
         Self {
             vb_list: vec![
                 Buffer::<Static>::new(pos_data),
                 Buffer::<Static>::new(color_data),
+                Buffer::<Static>::new(normal_data),
+                Buffer::<Static>::new(uv_data),
             ],
             index_buffer: IndexBuffer::new(index_data),
+            bounds,
         }
     }
 
+    /// Bounds of the vertices in model space.
+    #[inline]
+    pub fn get_bounds(&self) -> &Aabb {
+        &self.bounds
+    }
+
     pub fn cube() -> Self {
-        Self::new(&POS_DATA, &COLOR_DATA, &ELEMENT_DATA)
+        let mut positions = vec![];
+        let mut colors = vec![];
+        let mut normals = vec![];
+        let mut uvs = vec![];
+        let mut indices = vec![];
+        for (corners, normal) in FACE_DATA.iter() {
+            let first = positions.len() as GLuint;
+            for (&corner, uv) in corners.iter().zip([[0., 0.], [1., 0.], [1., 1.], [0., 1.]]) {
+                positions.push(POS_DATA[corner]);
+                colors.push(COLOR_DATA[corner]);
+                normals.push(*normal);
+                uvs.push(uv);
+            }
+            indices.extend([0, 1, 2, 2, 3, 0].map(|i| first + i));
+        }
+        Self::new(&positions, &colors, &normals, &uvs, &indices)
     }
+
+    /// UV sphere of diameter one, colored by its normals. Texture coordinates
+    /// wrap around once horizontally and run from pole to pole vertically.
+    pub fn sphere(segments: u32, rings: u32) -> Self {
+        let mut positions = vec![];
+        let mut uvs = vec![];
+        for ring in 0..=rings {
+            let theta = PI * ring as f32 / rings as f32;
+            for segment in 0..=segments {
+                let phi = 2. * PI * segment as f32 / segments as f32;
+                let p = Vec3::new(
+                    theta.sin() * phi.cos(),
+                    theta.cos(),
+                    theta.sin() * phi.sin(),
+                );
+                positions.push((p * 0.5).to_array());
+                uvs.push([
+                    segment as f32 / segments as f32,
+                    1. - ring as f32 / rings as f32,
+                ]);
+            }
+        }
+
+        let mut indices = vec![];
+        let row = segments + 1;
+        for ring in 0..rings {
+            for segment in 0..segments {
+                let a = ring * row + segment;
+                let b = a + row;
+                indices.extend_from_slice(&[a, a + 1, b, b, a + 1, b + 1]);
+            }
+        }
+
+        let colors: Vec<_> = positions
+            .iter()
+            .map(|&p| (Vec3::from(p) + 0.5).to_array())
+            .collect();
+        let normals: Vec<_> = positions
+            .iter()
+            .map(|&p| Vec3::from(p).normalize_or_zero().to_array())
+            .collect();
+        Self::new(&positions, &colors, &normals, &uvs, &indices)
+    }
+
+    /// Loads the triangles of a Wavefront OBJ file, shaded and colored with
+    /// smoothed normals. Polygons are split into fans, everything but vertex
+    /// positions, texture coordinates and faces is ignored.
+    pub fn load_obj(path: impl AsRef<Path>) -> io::Result<Self> {
+        let (positions, uvs, indices) = parse_obj(&fs::read_to_string(path)?)?;
+
+        // Smoothed by position, so texture seams do not show in the shading.
+        let key = |i: GLuint| positions[i as usize].map(f32::to_bits);
+        let mut smoothed: HashMap<_, Vec3> = HashMap::new();
+        for triangle in indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(positions[triangle[i] as usize]));
+            let normal = (b - a).cross(c - a);
+            for &i in triangle.iter() {
+                *smoothed.entry(key(i)).or_default() += normal;
+            }
+        }
+        let normals: Vec<_> = (0..positions.len() as GLuint)
+            .map(|i| {
+                let normal = smoothed.get(&key(i)).copied().unwrap_or_default();
+                normal.normalize_or_zero().to_array()
+            })
+            .collect();
+        let colors: Vec<_> = normals
+            .iter()
+            .map(|&n| (Vec3::from(n) * 0.5 + 0.5).to_array())
+            .collect();
+        Ok(Self::new(&positions, &colors, &normals, &uvs, &indices))
+    }
+}
+
+/// Positions, texture coordinates and triangle indices of an OBJ file. Each
+/// distinct pair of position and texture coordinates becomes one vertex.
+fn parse_obj(text: &str) -> io::Result<(Vec<Pos>, Vec<Uv>, Vec<GLuint>)> {
+    let invalid = |line: usize| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid obj data on line {}", line + 1),
+        )
+    };
+
+    let mut file_positions = vec![];
+    let mut file_uvs = vec![];
+    let mut vertices = HashMap::new();
+    let mut positions = vec![];
+    let mut uvs = vec![];
+    let mut indices = vec![];
+    for (number, line) in text.lines().enumerate() {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("v") => {
+                file_positions.push(parse_floats::<3>(&mut words).ok_or_else(|| invalid(number))?)
+            }
+            Some("vt") => {
+                file_uvs.push(parse_floats::<2>(&mut words).ok_or_else(|| invalid(number))?)
+            }
+            Some("f") => {
+                // `v`, `v/vt`, `v//vn` or `v/vt/vn`, one based or negative
+                // to count from the last one.
+                let face = words
+                    .map(|w| {
+                        let mut parts = w.split('/');
+                        let position = resolve(parts.next()?, file_positions.len())?;
+                        let uv = match parts.next() {
+                            Some(part) if !part.is_empty() => Some(resolve(part, file_uvs.len())?),
+                            _ => None,
+                        };
+                        Some((position, uv))
+                    })
+                    .collect::<Option<Vec<_>>>()
+                    .filter(|face| face.len() >= 3)
+                    .ok_or_else(|| invalid(number))?;
+                let face: Vec<GLuint> = face
+                    .into_iter()
+                    .map(|(position, uv)| {
+                        *vertices.entry((position, uv)).or_insert_with(|| {
+                            positions.push(file_positions[position]);
+                            uvs.push(uv.map_or([0.; 2], |uv| file_uvs[uv]));
+                            (positions.len() - 1) as GLuint
+                        })
+                    })
+                    .collect();
+                for i in 1..face.len() - 1 {
+                    indices.extend_from_slice(&[face[0], face[i], face[i + 1]]);
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok((positions, uvs, indices))
+}
+
+fn parse_floats<const N: usize>(words: &mut SplitWhitespace) -> Option<[f32; N]> {
+    let mut values = [0.; N];
+    for value in values.iter_mut() {
+        *value = words.next()?.parse().ok()?;
+    }
+    Some(values)
+}
+
+/// Zero based index of an OBJ reference into `count` elements.
+fn resolve(word: &str, count: usize) -> Option<usize> {
+    let index: i64 = word.parse().ok()?;
+    let index = if index < 0 {
+        count as i64 + index
+    } else {
+        index - 1
+    };
+    (0..count as i64).contains(&index).then_some(index as usize)
 }
@@ -0,0 +1,178 @@
+use std::f32::consts::PI;
+
+use gl::types::GLuint;
+use glam::{Mat4, Vec3};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+use crate::glcall;
+
+use super::{
+    shader::ShaderProgram,
+    vertex_array,
+    vertex_buffer::{Buffer, Dynamic},
+};
+
+/// Layout of a particle in the shader storage buffer. `vec3` members are
+/// aligned to 16 bytes by std430.
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct Particle {
+    position: Vec3,
+    _padding: f32,
+    velocity: Vec3,
+    _padding2: f32,
+}
+
+/// Points pulled towards a point of mass, simulated by the vertex shader as
+/// they are drawn: every draw advances the particles by the last delta.
+pub struct ParticleSystem {
+    shader: ShaderProgram,
+    particles: Buffer<Dynamic>,
+    count: usize,
+    /// Points are read from the storage buffer, but a VAO must be bound.
+    vao: GLuint,
+    point_of_mass: Vec3,
+    /// Whether the point of mass pulls.
+    active: bool,
+    /// Whether the particles move at all.
+    running: bool,
+    enabled: bool,
+    delta: f32,
+}
+
+impl Drop for ParticleSystem {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+impl ParticleSystem {
+    const BINDING: GLuint = 0;
+    const SEED: u64 = 0xA7;
+    /// Radius of the sphere the particles start in.
+    const RADIUS: f32 = 30.;
+    /// Speed of the initial swirl around the vertical axis at the edge.
+    const SWIRL: f32 = 4.;
+
+    /// Allocates `count` particles, resting in a swirling sphere around the
+    /// origin. Starts hidden.
+    pub fn new(count: usize) -> Self {
+        let mut vao = 0;
+        unsafe {
+            gl::CreateVertexArrays(1, &mut vao);
+        }
+        let particles = Buffer::<Dynamic>::instanced::<Particle>(count as isize);
+        let mut system = Self {
+            shader: ShaderProgram::new(
+                include_str!("../../resources/v_particle.glsl"),
+                include_str!("../../resources/f_particle.glsl"),
+            ),
+            particles,
+            count,
+            vao,
+            point_of_mass: Vec3::ZERO,
+            active: false,
+            running: true,
+            enabled: false,
+            delta: 0.,
+        };
+        system.seed();
+        system
+    }
+
+    /// Scatters the particles in a sphere around the origin, circling the
+    /// vertical axis.
+    fn seed(&mut self) {
+        let mut rng = ChaCha8Rng::seed_from_u64(Self::SEED);
+        let data: Vec<_> = (0..self.count)
+            .map(|_| {
+                let z: f32 = rng.gen_range(-1.0..=1.0);
+                let angle = rng.gen_range(0.0..2. * PI);
+                let r = (1. - z * z).sqrt();
+                let direction = Vec3::new(r * angle.cos(), z, r * angle.sin());
+                let position = direction * rng.gen::<f32>().cbrt() * Self::RADIUS;
+                let velocity = Vec3::Y.cross(position) / Self::RADIUS * Self::SWIRL;
+                Particle {
+                    position,
+                    _padding: 0.,
+                    velocity,
+                    _padding2: 0.,
+                }
+            })
+            .collect();
+        if let Err(e) = self.particles.set_data(&data, 0) {
+            panic!("Cannot seed particles: {e}");
+        }
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    #[inline]
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    #[inline]
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    /// Freezes or resumes the particles.
+    #[inline]
+    pub fn set_running(&mut self, running: bool) {
+        self.running = running;
+    }
+
+    /// Moves the point of mass to `position`, pulling only if `active`.
+    #[inline]
+    pub fn set_point_of_mass(&mut self, position: Vec3, active: bool) {
+        self.point_of_mass = position;
+        self.active = active;
+    }
+
+    /// Sets the seconds the next draw advances the particles by.
+    #[inline]
+    pub fn update(&mut self, delta: f32) {
+        self.delta = delta;
+    }
+
+    /// Simulates and draws the particles into the bound framebuffer,
+    /// blended additively and tested against its depth without writing it.
+    pub fn draw(&mut self, view_projection: &Mat4) {
+        if !self.enabled {
+            return;
+        }
+        self.shader.activate();
+        self.shader.set_float("dT", self.delta);
+        self.shader.set_vec3("pointOfMass", self.point_of_mass);
+        self.shader
+            .set_float("isActive", if self.active { 1. } else { 0. });
+        self.shader
+            .set_float("isRunning", if self.running { 1. } else { 0. });
+        self.shader.set_mat4("projViewMatrix", view_projection);
+        vertex_array::bind_id(self.vao);
+        unsafe {
+            gl::BindBufferBase(
+                gl::SHADER_STORAGE_BUFFER,
+                Self::BINDING,
+                self.particles.get_id(),
+            );
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE);
+            gl::DepthMask(gl::FALSE);
+            glcall!(gl::DrawArrays(gl::POINTS, 0, self.count as i32));
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::BLEND);
+            // The next frame reads the positions written by this one.
+            gl::MemoryBarrier(gl::SHADER_STORAGE_BARRIER_BIT);
+        }
+        // Drawing again in the same frame must not step twice.
+        self.delta = 0.;
+    }
+}
@@ -0,0 +1,448 @@
+use std::path::Path;
+
+use gl::types::GLuint;
+use glam::Vec2;
+
+use crate::glcall;
+
+use super::{
+    framebuffer::Framebuffer,
+    shader::ShaderProgram,
+    texture::{Filter, Sampler, SamplerSettings, Texture2D, Wrap},
+    vertex_array,
+};
+
+/// Full-screen pass of the post-processing chain.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PostEffect {
+    /// Adds a blurred copy of the parts brighter than `threshold`, blurred
+    /// `passes` times at half resolution.
+    Bloom {
+        threshold: f32,
+        intensity: f32,
+        passes: u32,
+    },
+    /// Maps HDR colors to [0, 1] with the ACES filmic curve.
+    ToneMapping { exposure: f32 },
+    /// Looks colors up in the color grading LUT, blending the result with
+    /// the original color by `strength`.
+    ColorGrading { strength: f32 },
+    /// Darkens the corners, starting at `radius` from the center of the
+    /// screen and fading in over `softness`.
+    Vignette { radius: f32, softness: f32 },
+    /// Encodes linear colors for the display.
+    Gamma { gamma: f32 },
+    /// Fast approximate anti-aliasing, best run last on gamma encoded colors.
+    Fxaa,
+}
+
+impl PostEffect {
+    /// Name of the effect, as used by [`PostProcessing::get_mut_stage`].
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            PostEffect::Bloom { .. } => "bloom",
+            PostEffect::ToneMapping { .. } => "tone_mapping",
+            PostEffect::ColorGrading { .. } => "color_grading",
+            PostEffect::Vignette { .. } => "vignette",
+            PostEffect::Gamma { .. } => "gamma",
+            PostEffect::Fxaa => "fxaa",
+        }
+    }
+}
+
+/// An effect of the chain, skipped while disabled.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PostStage {
+    pub effect: PostEffect,
+    pub enabled: bool,
+}
+
+impl PostStage {
+    pub fn new(effect: PostEffect) -> Self {
+        Self {
+            effect,
+            enabled: true,
+        }
+    }
+}
+
+struct Shaders {
+    bright: ShaderProgram,
+    blur: ShaderProgram,
+    bloom: ShaderProgram,
+    tone_mapping: ShaderProgram,
+    color_grading: ShaderProgram,
+    vignette: ShaderProgram,
+    gamma: ShaderProgram,
+    fxaa: ShaderProgram,
+}
+
+impl Shaders {
+    fn new() -> Self {
+        let vertex = include_str!("../../resources/v_post.glsl");
+        Self {
+            bright: ShaderProgram::new(vertex, include_str!("../../resources/f_bright.glsl")),
+            blur: ShaderProgram::new(vertex, include_str!("../../resources/f_blur.glsl")),
+            bloom: ShaderProgram::new(vertex, include_str!("../../resources/f_bloom.glsl")),
+            tone_mapping: ShaderProgram::new(
+                vertex,
+                include_str!("../../resources/f_tone_mapping.glsl"),
+            ),
+            color_grading: ShaderProgram::new(
+                vertex,
+                include_str!("../../resources/f_color_grading.glsl"),
+            ),
+            vignette: ShaderProgram::new(vertex, include_str!("../../resources/f_vignette.glsl")),
+            gamma: ShaderProgram::new(vertex, include_str!("../../resources/f_gamma.glsl")),
+            fxaa: ShaderProgram::new(vertex, include_str!("../../resources/f_fxaa.glsl")),
+        }
+    }
+}
+
+/// Chain of full-screen effects turning the HDR scene into the image on
+/// screen. Every enabled stage reads the output of the previous one, the
+/// last one draws to the window.
+pub struct PostProcessing {
+    stages: Vec<PostStage>,
+    enabled: bool,
+    /// Intermediate results, written alternately.
+    targets: [Framebuffer; 2],
+    /// Half resolution bright parts, blurred back and forth.
+    bloom: [Framebuffer; 2],
+    /// 3D color grading lookup table.
+    lut: GLuint,
+    lut_size: i32,
+    sampler: Sampler,
+    /// Full-screen triangles need no vertex data, but a bound VAO.
+    vao: GLuint,
+    shaders: Shaders,
+}
+
+impl PostProcessing {
+    const FORMAT: gl::types::GLenum = gl::RGBA16F;
+    const SOURCE_UNIT: u32 = 0;
+    const EXTRA_UNIT: u32 = 1;
+    /// Size of the identity LUT used until one is loaded.
+    const LUT_SIZE: i32 = 16;
+
+    /// Creates the default chain for a `width` by `height` screen: bloom,
+    /// tone mapping, color grading, vignette, gamma and FXAA.
+    pub fn new(width: i32, height: i32) -> Self {
+        let mut vao = 0;
+        let mut lut = 0;
+        unsafe {
+            gl::CreateVertexArrays(1, &mut vao);
+            gl::CreateTextures(gl::TEXTURE_3D, 1, &mut lut);
+        }
+        let mut post = Self {
+            stages: [
+                PostEffect::Bloom {
+                    threshold: 1.,
+                    intensity: 0.6,
+                    passes: 3,
+                },
+                PostEffect::ToneMapping { exposure: 1. },
+                PostEffect::ColorGrading { strength: 1. },
+                PostEffect::Vignette {
+                    radius: 0.75,
+                    softness: 0.45,
+                },
+                PostEffect::Gamma { gamma: 2.2 },
+                PostEffect::Fxaa,
+            ]
+            .into_iter()
+            .map(PostStage::new)
+            .collect(),
+            enabled: true,
+            targets: [(); 2].map(|_| Framebuffer::new(width, height, &[Self::FORMAT], false)),
+            bloom: [(); 2].map(|_| Framebuffer::new(width / 2, height / 2, &[Self::FORMAT], false)),
+            lut,
+            lut_size: 0,
+            sampler: Sampler::new(&SamplerSettings {
+                min_filter: Filter::Linear,
+                mag_filter: Filter::Linear,
+                mipmap_filter: None,
+                wrap_s: Wrap::ClampToEdge,
+                wrap_t: Wrap::ClampToEdge,
+                anisotropy: 1.,
+            }),
+            vao,
+            shaders: Shaders::new(),
+        };
+        post.set_lut(Self::LUT_SIZE, &identity_lut(Self::LUT_SIZE));
+        post
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Disabling the chain copies the HDR scene to the screen untouched.
+    #[inline]
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Stages in the order they run.
+    #[inline]
+    pub fn get_stages(&self) -> &[PostStage] {
+        &self.stages
+    }
+
+    /// First stage whose effect is called `name`.
+    pub fn get_mut_stage(&mut self, name: &str) -> Option<&mut PostStage> {
+        self.stages.iter_mut().find(|s| s.effect.get_name() == name)
+    }
+
+    /// Loads a color grading LUT from a strip of square slices, as wide as
+    /// the square of its height. Red grows along each slice, green down the
+    /// strip and blue from one slice to the next.
+    pub fn load_lut(&mut self, path: impl AsRef<Path>) -> image::ImageResult<()> {
+        let image = image::open(path)?.into_rgba8();
+        let size = image.height();
+        if size < 2 || image.width() != size * size {
+            return Err(image::ImageError::Parameter(
+                image::error::ParameterError::from_kind(
+                    image::error::ParameterErrorKind::DimensionMismatch,
+                ),
+            ));
+        }
+        let mut data = Vec::with_capacity((size * size * size * 4) as usize);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    data.extend_from_slice(&image.get_pixel(b * size + r, g).0);
+                }
+            }
+        }
+        self.set_lut(size as i32, &data);
+        Ok(())
+    }
+
+    /// Replaces the LUT with a `size` cubed RGBA8 one.
+    fn set_lut(&mut self, size: i32, data: &[u8]) {
+        if size != self.lut_size {
+            unsafe {
+                gl::DeleteTextures(1, &self.lut);
+                gl::CreateTextures(gl::TEXTURE_3D, 1, &mut self.lut);
+                glcall!(gl::TextureStorage3D(
+                    self.lut,
+                    1,
+                    gl::RGBA8,
+                    size,
+                    size,
+                    size
+                ));
+                for wrap in [gl::TEXTURE_WRAP_S, gl::TEXTURE_WRAP_T, gl::TEXTURE_WRAP_R] {
+                    gl::TextureParameteri(self.lut, wrap, gl::CLAMP_TO_EDGE as i32);
+                }
+                gl::TextureParameteri(self.lut, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+                gl::TextureParameteri(self.lut, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            }
+            self.lut_size = size;
+        }
+        unsafe {
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            glcall!(gl::TextureSubImage3D(
+                self.lut,
+                0,
+                0,
+                0,
+                0,
+                size,
+                size,
+                size,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                data.as_ptr().cast()
+            ));
+        }
+    }
+
+    /// Follows the screen size.
+    pub fn resize(&mut self, width: i32, height: i32) {
+        for target in self.targets.iter_mut() {
+            target.resize(width, height);
+        }
+        for target in self.bloom.iter_mut() {
+            target.resize(width / 2, height / 2);
+        }
+    }
+
+    /// Runs the enabled stages on the first color attachment of `source` and
+    /// draws the result to the `width` by `height` window.
+    pub fn apply(&mut self, source: &Framebuffer, width: i32, height: i32) {
+        let stages: Vec<_> = self
+            .stages
+            .iter()
+            .filter(|s| self.enabled && s.enabled)
+            .map(|s| s.effect)
+            .collect();
+        if stages.is_empty() {
+            source.blit_to_default(width, height);
+            Framebuffer::bind_default(width, height);
+            return;
+        }
+
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+        }
+        vertex_array::bind_id(self.vao);
+        let Self {
+            targets,
+            bloom,
+            lut,
+            lut_size,
+            sampler,
+            shaders,
+            ..
+        } = self;
+        // Target holding the latest result, or `None` for the source.
+        let mut current: Option<usize> = None;
+        for (i, effect) in stages.iter().enumerate() {
+            let input = match current {
+                Some(index) => targets[index].get_color(0),
+                None => source.get_color(0),
+            };
+            let shader = match *effect {
+                PostEffect::Bloom {
+                    threshold,
+                    intensity,
+                    passes,
+                } => {
+                    blur_bright(shaders, bloom, sampler, input, threshold, passes);
+                    shaders.bloom.activate();
+                    shaders.bloom.set_texture(
+                        "u_Bloom",
+                        Self::EXTRA_UNIT,
+                        bloom[0].get_color(0),
+                        sampler,
+                    );
+                    shaders.bloom.set_float("u_Intensity", intensity);
+                    &mut shaders.bloom
+                }
+                PostEffect::ToneMapping { exposure } => {
+                    shaders.tone_mapping.activate();
+                    shaders.tone_mapping.set_float("u_Exposure", exposure);
+                    &mut shaders.tone_mapping
+                }
+                PostEffect::ColorGrading { strength } => {
+                    unsafe {
+                        gl::BindTextureUnit(Self::EXTRA_UNIT, *lut);
+                    }
+                    // The LUT has its own wrapping along the third axis.
+                    Sampler::unbind(Self::EXTRA_UNIT);
+                    let shader = &mut shaders.color_grading;
+                    shader.activate();
+                    shader.set_int("u_Lut", Self::EXTRA_UNIT as i8);
+                    shader.set_float("u_LutSize", *lut_size as f32);
+                    shader.set_float("u_Strength", strength);
+                    shader
+                }
+                PostEffect::Vignette { radius, softness } => {
+                    shaders.vignette.activate();
+                    shaders.vignette.set_float("u_Radius", radius);
+                    shaders.vignette.set_float("u_Softness", softness);
+                    &mut shaders.vignette
+                }
+                PostEffect::Gamma { gamma } => {
+                    shaders.gamma.activate();
+                    shaders.gamma.set_float("u_Gamma", gamma);
+                    &mut shaders.gamma
+                }
+                PostEffect::Fxaa => {
+                    shaders.fxaa.activate();
+                    let texel = Vec2::new(input.get_width() as f32, input.get_height() as f32);
+                    shaders.fxaa.set_vec2("u_Texel", texel.recip());
+                    &mut shaders.fxaa
+                }
+            };
+            shader.set_texture("u_Source", Self::SOURCE_UNIT, input, sampler);
+
+            let next = if current == Some(0) { 1 } else { 0 };
+            if i + 1 < stages.len() {
+                targets[next].bind();
+            } else {
+                Framebuffer::bind_default(width, height);
+            }
+            draw_triangle();
+            current = Some(next);
+        }
+
+        Sampler::unbind(Self::SOURCE_UNIT);
+        Sampler::unbind(Self::EXTRA_UNIT);
+        unsafe {
+            gl::Enable(gl::DEPTH_TEST);
+        }
+    }
+}
+
+impl Drop for PostProcessing {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.lut);
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+/// Leaves the parts of `input` brighter than `threshold`, blurred `passes`
+/// times, in the first bloom target.
+fn blur_bright(
+    shaders: &mut Shaders,
+    bloom: &[Framebuffer; 2],
+    sampler: &Sampler,
+    input: &Texture2D,
+    threshold: f32,
+    passes: u32,
+) {
+    shaders.bright.activate();
+    shaders.bright.set_float("u_Threshold", threshold);
+    shaders
+        .bright
+        .set_texture("u_Source", PostProcessing::SOURCE_UNIT, input, sampler);
+    bloom[0].bind();
+    draw_triangle();
+
+    // Separable gaussian, horizontal into the second target then vertical
+    // back into the first.
+    shaders.blur.activate();
+    let texel = Vec2::new(bloom[0].get_width() as f32, bloom[0].get_height() as f32).recip();
+    for _ in 0..passes {
+        for (from, to, direction) in [(0, 1, Vec2::X), (1, 0, Vec2::Y)] {
+            shaders.blur.set_vec2("u_Direction", direction * texel);
+            shaders.blur.set_texture(
+                "u_Source",
+                PostProcessing::SOURCE_UNIT,
+                bloom[from].get_color(0),
+                sampler,
+            );
+            bloom[to].bind();
+            draw_triangle();
+        }
+    }
+}
+
+fn draw_triangle() {
+    unsafe {
+        glcall!(gl::DrawArrays(gl::TRIANGLES, 0, 3));
+    }
+}
+
+/// RGBA8 LUT leaving colors unchanged.
+fn identity_lut(size: i32) -> Vec<u8> {
+    let scale = 255. / (size - 1) as f32;
+    let mut data = Vec::with_capacity((size * size * size * 4) as usize);
+    for b in 0..size {
+        for g in 0..size {
+            for r in 0..size {
+                for channel in [r, g, b] {
+                    data.push((channel as f32 * scale).round() as u8);
+                }
+                data.push(255);
+            }
+        }
+    }
+    data
+}
@@ -0,0 +1,169 @@
+use gl::types::GLuint;
+use glam::Mat4;
+
+use crate::glcall;
+
+use super::{framebuffer::Framebuffer, shader::ShaderProgram, vertex_array};
+
+/// What the main pass shows, to inspect the scene.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RenderMode {
+    /// The lit scene.
+    Shaded,
+    /// Triangle edges in the instance colors.
+    Wireframe,
+    /// Triangle edges drawn over the lit scene.
+    WireframeOnShaded,
+    /// World space vertex normals as colors.
+    Normals,
+    /// Distance from the camera plane, from black to white.
+    Depth,
+    /// A distinct color per entity.
+    InstanceId,
+    /// How many fragments land on each pixel, from blue to white.
+    Overdraw,
+}
+
+impl RenderMode {
+    const ALL: [RenderMode; 7] = [
+        RenderMode::Shaded,
+        RenderMode::Wireframe,
+        RenderMode::WireframeOnShaded,
+        RenderMode::Normals,
+        RenderMode::Depth,
+        RenderMode::InstanceId,
+        RenderMode::Overdraw,
+    ];
+
+    /// The mode after this one, back to [`RenderMode::Shaded`] after the last.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&m| m == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// Value of `u_Mode` in the visualization shader.
+    fn shader_mode(self) -> u32 {
+        match self {
+            RenderMode::Shaded | RenderMode::Wireframe => 0,
+            RenderMode::WireframeOnShaded => 1,
+            RenderMode::Normals => 2,
+            RenderMode::Depth => 3,
+            RenderMode::InstanceId => 4,
+            RenderMode::Overdraw => 5,
+        }
+    }
+}
+
+/// Shaders of the render modes other than [`RenderMode::Shaded`].
+///
+/// Except for the wireframe over the lit scene, they replace the lighting
+/// and are shown as they are, without post-processing.
+pub struct Visualizer {
+    shader: ShaderProgram,
+    heatmap: ShaderProgram,
+    /// Full-screen triangles need no vertex data, but a bound VAO.
+    vao: GLuint,
+}
+
+impl Drop for Visualizer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+impl Visualizer {
+    /// Distance shown white in the depth view. The far plane would leave
+    /// most scenes black.
+    const DEPTH_RANGE: f32 = 250.;
+    /// Fragments per pixel shown white in the overdraw view.
+    const MAX_OVERDRAW: f32 = 16.;
+    const SOURCE_UNIT: u32 = 0;
+
+    pub fn new() -> Self {
+        let mut vao = 0;
+        unsafe {
+            gl::CreateVertexArrays(1, &mut vao);
+        }
+        Self {
+            shader: ShaderProgram::new(
+                include_str!("../../resources/vertex.glsl"),
+                include_str!("../../resources/f_visualize.glsl"),
+            ),
+            heatmap: ShaderProgram::new(
+                include_str!("../../resources/v_post.glsl"),
+                include_str!("../../resources/f_heatmap.glsl"),
+            ),
+            vao,
+        }
+    }
+
+    /// Sets up the pipeline and the shader for drawing the instances in
+    /// `mode`. `near` is the distance of the near plane.
+    /// [`Visualizer::end`] restores the state.
+    pub fn begin(&mut self, mode: RenderMode, view: &Mat4, projection: &Mat4, near: f32) {
+        unsafe {
+            match mode {
+                RenderMode::Wireframe => {
+                    gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
+                }
+                RenderMode::WireframeOnShaded => {
+                    // Pulled towards the camera so the edges win against
+                    // the surfaces they lie on.
+                    gl::PolygonMode(gl::FRONT_AND_BACK, gl::LINE);
+                    gl::Enable(gl::POLYGON_OFFSET_LINE);
+                    gl::PolygonOffset(-1., -1.);
+                    gl::DepthFunc(gl::LEQUAL);
+                    gl::DepthMask(gl::FALSE);
+                }
+                RenderMode::Overdraw => {
+                    gl::Disable(gl::DEPTH_TEST);
+                    gl::Enable(gl::BLEND);
+                    gl::BlendFunc(gl::ONE, gl::ONE);
+                }
+                _ => {}
+            }
+        }
+        self.shader.activate();
+        self.shader.set_uint("u_Mode", mode.shader_mode());
+        self.shader.set_mat4("view", view);
+        self.shader.set_mat4("projection", projection);
+        self.shader.set_float("u_Near", near);
+        self.shader.set_float("u_DepthRange", Self::DEPTH_RANGE);
+    }
+
+    /// Restores the state [`Visualizer::begin`] changed.
+    pub fn end(&mut self) {
+        unsafe {
+            gl::PolygonMode(gl::FRONT_AND_BACK, gl::FILL);
+            gl::Disable(gl::POLYGON_OFFSET_LINE);
+            gl::DepthFunc(gl::LESS);
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::BLEND);
+            gl::Enable(gl::DEPTH_TEST);
+        }
+    }
+
+    /// Shows the first color attachment of `source` in the `width` by
+    /// `height` window: as a heatmap for [`RenderMode::Overdraw`], as it is
+    /// otherwise.
+    pub fn present(&mut self, mode: RenderMode, source: &Framebuffer, width: i32, height: i32) {
+        if mode != RenderMode::Overdraw {
+            source.blit_to_default(width, height);
+            Framebuffer::bind_default(width, height);
+            return;
+        }
+        Framebuffer::bind_default(width, height);
+        self.heatmap.activate();
+        source.get_color(0).bind(Self::SOURCE_UNIT);
+        self.heatmap.set_int("u_Source", Self::SOURCE_UNIT as i8);
+        self.heatmap.set_float("u_MaxCount", Self::MAX_OVERDRAW);
+        vertex_array::bind_id(self.vao);
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+            glcall!(gl::DrawArrays(gl::TRIANGLES, 0, 3));
+            gl::Enable(gl::DEPTH_TEST);
+        }
+    }
+}
@@ -1,10 +1,12 @@
 use gl::types::*;
-use glam::Mat4;
+use glam::{Mat4, Vec2, Vec3, Vec4};
 use std::collections::HashMap;
 use std::ffi::CString;
 use std::ptr;
 use std::str;
 
+use super::texture::{Sampler, Texture2D};
+
 struct Shader {
     id: GLuint,
 }
@@ -53,15 +55,23 @@ impl ShaderProgram {
         // let a = std::fs::read_to_string(vertex_path).expect("Unable to read vertex shader!!");
         // let b = std::fs::read_to_string(fragment_path).expect("Unable to read fragment shader");
 
-        let vs = Shader::new(&vertex_src, gl::VERTEX_SHADER);
-        let fs = Shader::new(&fragment_src, gl::FRAGMENT_SHADER);
+        let vs = Shader::new(vertex_src, gl::VERTEX_SHADER);
+        let fs = Shader::new(fragment_src, gl::FRAGMENT_SHADER);
 
-        let id = link_program(&vs, &fs);
+        let id = link_program(&[&vs, &fs]);
 
-        return Self {
+        Self {
             id,
             locations: HashMap::new(),
-        };
+        }
+    }
+
+    pub fn compute(src: &str) -> Self {
+        let cs = Shader::new(src, gl::COMPUTE_SHADER);
+        Self {
+            id: link_program(&[&cs]),
+            locations: HashMap::new(),
+        }
     }
 
     pub fn activate(&self) {
@@ -89,25 +99,87 @@ impl ShaderProgram {
         }
     }
 
-    #[allow(dead_code)]
     pub fn set_int(&mut self, name: &'static str, value: i8) {
         unsafe {
             gl::Uniform1i(self.get_location(name), value as GLint);
         }
     }
 
-    #[allow(dead_code)]
     pub fn set_float(&mut self, name: &'static str, value: f32) {
         unsafe {
             gl::Uniform1f(self.get_location(name), value as GLfloat);
         }
     }
 
+    pub fn set_uint(&mut self, name: &'static str, value: u32) {
+        unsafe {
+            gl::Uniform1ui(self.get_location(name), value);
+        }
+    }
+
+    pub fn set_float_array(&mut self, name: &'static str, values: &[f32]) {
+        unsafe {
+            gl::Uniform1fv(
+                self.get_location(name),
+                values.len() as GLsizei,
+                values.as_ptr(),
+            );
+        }
+    }
+
+    pub fn set_vec2(&mut self, name: &'static str, value: Vec2) {
+        unsafe {
+            gl::Uniform2f(self.get_location(name), value.x, value.y);
+        }
+    }
+
+    pub fn set_vec3(&mut self, name: &'static str, value: Vec3) {
+        unsafe {
+            gl::Uniform3f(self.get_location(name), value.x, value.y, value.z);
+        }
+    }
+
+    pub fn set_vec4_array(&mut self, name: &'static str, values: &[Vec4]) {
+        unsafe {
+            gl::Uniform4fv(
+                self.get_location(name),
+                values.len() as GLsizei,
+                values.as_ptr().cast(),
+            );
+        }
+    }
+
     pub fn set_mat4(&mut self, name: &'static str, mat4: &Mat4) {
         unsafe {
             gl::UniformMatrix4fv(self.get_location(name), 1, 0, mat4.to_cols_array().as_ptr());
         }
     }
+
+    /// Binds `texture` and `sampler` to texture unit `unit` and points the
+    /// sampler uniform `name` at it.
+    pub fn set_texture(
+        &mut self,
+        name: &'static str,
+        unit: u32,
+        texture: &Texture2D,
+        sampler: &Sampler,
+    ) {
+        texture.bind(unit);
+        sampler.bind(unit);
+        self.set_int(name, unit as i8);
+    }
+
+    pub fn set_mat4_array(&mut self, name: &'static str, values: &[Mat4]) {
+        let data: Vec<f32> = values.iter().flat_map(Mat4::to_cols_array).collect();
+        unsafe {
+            gl::UniformMatrix4fv(
+                self.get_location(name),
+                values.len() as GLsizei,
+                0,
+                data.as_ptr(),
+            );
+        }
+    }
 }
 
 unsafe fn check_compile_status(id_shader: &GLuint) {
@@ -119,23 +191,22 @@ unsafe fn check_compile_status(id_shader: &GLuint) {
     if status != (gl::TRUE as GLint) {
         let mut len = 0;
         gl::GetShaderiv(*id_shader, gl::INFO_LOG_LENGTH, &mut len);
-        let mut buf = Vec::with_capacity(len as usize);
-        buf.set_len((len as usize) - 1); // subtract 1 to skip the trailing null character
+        let mut buf = vec![0u8; len as usize];
         gl::GetShaderInfoLog(*id_shader, len, &mut len, buf.as_mut_ptr() as *mut _);
+        buf.truncate(len as usize); // the returned length excludes the trailing null character
         panic!(
             "{}",
-            str::from_utf8(&buf)
-                .ok()
-                .expect("ShaderInfoLog not valid utf8")
+            str::from_utf8(&buf).expect("ShaderInfoLog not valid utf8")
         );
     }
 }
 
-fn link_program(vs: &Shader, fs: &Shader) -> GLuint {
+fn link_program(shaders: &[&Shader]) -> GLuint {
     unsafe {
         let program = gl::CreateProgram();
-        gl::AttachShader(program, vs.id);
-        gl::AttachShader(program, fs.id);
+        for shader in shaders {
+            gl::AttachShader(program, shader.id);
+        }
         gl::LinkProgram(program);
         // Get the link status
         let mut status = gl::FALSE as GLint;
@@ -145,19 +216,12 @@ fn link_program(vs: &Shader, fs: &Shader) -> GLuint {
         if status != (gl::TRUE as GLint) {
             let mut len: GLint = 0;
             gl::GetProgramiv(program, gl::INFO_LOG_LENGTH, &mut len);
-            let mut buf = Vec::with_capacity(len as usize);
-            buf.set_len((len as usize) - 1); // subtract 1 to skip the trailing null character
-            gl::GetProgramInfoLog(
-                program,
-                len,
-                ptr::null_mut(),
-                buf.as_mut_ptr() as *mut GLchar,
-            );
+            let mut buf = vec![0u8; len as usize];
+            gl::GetProgramInfoLog(program, len, &mut len, buf.as_mut_ptr() as *mut GLchar);
+            buf.truncate(len as usize); // the returned length excludes the trailing null character
             panic!(
                 "{}",
-                str::from_utf8(&buf)
-                    .ok()
-                    .expect("ProgramInfoLog not valid utf8")
+                str::from_utf8(&buf).expect("ProgramInfoLog not valid utf8")
             );
         }
         program
@@ -0,0 +1,343 @@
+use gl::types::GLuint;
+use glam::{Mat4, Vec3};
+
+use crate::{bounds::Frustum, camera::Camera, glcall, light::LightKind, scene::Scene};
+
+use super::{shader::ShaderProgram, texture::Sampler};
+
+/// Tunables of the shadow pass.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ShadowSettings {
+    pub enabled: bool,
+    /// Subtracted from the depth of a fragment before comparing it with the
+    /// shadow map, in shadow map depth units.
+    pub depth_bias: f32,
+    /// Distance in world units fragments are pushed along their normal
+    /// before looking them up.
+    pub normal_bias: f32,
+    /// Slope scaled polygon offset applied while rendering the maps.
+    pub slope_bias: f32,
+    /// Samples `2 * pcf_radius + 1` texels wide are averaged per lookup.
+    pub pcf_radius: u32,
+    /// Distance from the camera covered by the directional light cascades.
+    pub distance: f32,
+    /// Blend between uniform (0) and logarithmic (1) cascade splits.
+    pub split_lambda: f32,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            depth_bias: 0.0005,
+            normal_bias: 0.05,
+            slope_bias: 2.,
+            pcf_radius: 1,
+            distance: 200.,
+            split_lambda: 0.75,
+        }
+    }
+}
+
+/// Depth maps of the shadow casting lights, stored as layers of one array
+/// texture: the cascades of the first directional light come first, then one
+/// layer per spot light.
+///
+/// Point lights and lights past the first directional and
+/// [`ShadowMaps::MAX_SPOTS`] spot lights do not cast shadows.
+pub struct ShadowMaps {
+    texture: GLuint,
+    framebuffer: GLuint,
+    shader: ShaderProgram,
+    settings: ShadowSettings,
+    /// First layer of every scene light, or -1, in [`Scene::get_lights`] order.
+    layers: Vec<i32>,
+    matrices: [Mat4; ShadowMaps::LAYERS],
+    /// Whether each layer has to be rendered this frame.
+    active: [bool; ShadowMaps::LAYERS],
+    /// Far distance of each cascade from the camera.
+    splits: [f32; ShadowMaps::CASCADES],
+}
+
+impl ShadowMaps {
+    pub const SIZE: i32 = 2048;
+    pub const CASCADES: usize = 4;
+    pub const MAX_SPOTS: usize = 4;
+    const LAYERS: usize = Self::CASCADES + Self::MAX_SPOTS;
+    /// How far behind a cascade casters are still rendered.
+    const CASTER_MARGIN: f32 = 200.;
+    const SPOT_NEAR: f32 = 0.1;
+
+    pub fn new() -> Self {
+        let mut texture = 0;
+        let mut framebuffer = 0;
+        unsafe {
+            gl::CreateTextures(gl::TEXTURE_2D_ARRAY, 1, &mut texture);
+            glcall!(gl::TextureStorage3D(
+                texture,
+                1,
+                gl::DEPTH_COMPONENT32F,
+                Self::SIZE,
+                Self::SIZE,
+                Self::LAYERS as i32
+            ));
+            // Linear filtering of a comparison sampler blends the 2x2 nearest
+            // comparisons, smoothing the PCF kernel further.
+            gl::TextureParameteri(texture, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TextureParameteri(texture, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TextureParameteri(texture, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+            gl::TextureParameteri(texture, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+            gl::TextureParameteri(
+                texture,
+                gl::TEXTURE_COMPARE_MODE,
+                gl::COMPARE_REF_TO_TEXTURE as i32,
+            );
+            gl::TextureParameteri(texture, gl::TEXTURE_COMPARE_FUNC, gl::LEQUAL as i32);
+
+            gl::CreateFramebuffers(1, &mut framebuffer);
+            gl::NamedFramebufferDrawBuffer(framebuffer, gl::NONE);
+            gl::NamedFramebufferReadBuffer(framebuffer, gl::NONE);
+        }
+        Self {
+            texture,
+            framebuffer,
+            shader: ShaderProgram::new(
+                include_str!("../../resources/v_shadow.glsl"),
+                include_str!("../../resources/f_shadow.glsl"),
+            ),
+            settings: ShadowSettings::default(),
+            layers: vec![],
+            matrices: [Mat4::IDENTITY; Self::LAYERS],
+            active: [false; Self::LAYERS],
+            splits: [0.; Self::CASCADES],
+        }
+    }
+
+    #[inline]
+    pub fn get_settings(&self) -> &ShadowSettings {
+        &self.settings
+    }
+
+    #[inline]
+    pub fn get_mut_settings(&mut self) -> &mut ShadowSettings {
+        &mut self.settings
+    }
+
+    /// Shadow map layer of every scene light as of the last update.
+    #[inline]
+    pub fn get_layers(&self) -> &[i32] {
+        &self.layers
+    }
+
+    /// View volumes of the layers rendered this frame. Only what intersects
+    /// one of them can cast a shadow.
+    pub fn get_frusta(&self) -> impl Iterator<Item = Frustum> + '_ {
+        self.active
+            .iter()
+            .zip(self.matrices.iter())
+            .filter(|(&active, _)| active)
+            .map(|(_, matrix)| Frustum::from_matrix(matrix))
+    }
+
+    /// Assigns layers to the shadow casting lights of `scene` and fits their
+    /// matrices to the camera, whose frustum is described by `fov` in degrees,
+    /// `aspect` and `near`.
+    pub fn update(&mut self, scene: &Scene, fov: f32, aspect: f32, near: f32) {
+        self.layers.clear();
+        self.active = [false; Self::LAYERS];
+        let mut directional = false;
+        let mut spots = 0;
+        for (e, light) in scene.get_lights() {
+            let layer = match light.kind {
+                _ if !self.settings.enabled || !light.cast_shadows => None,
+                LightKind::Directional if !directional => {
+                    directional = true;
+                    Some(0)
+                }
+                LightKind::Spot { .. } if spots < Self::MAX_SPOTS => {
+                    spots += 1;
+                    Some(Self::CASCADES + spots - 1)
+                }
+                _ => None,
+            };
+            let Some(layer) = layer else {
+                self.layers.push(-1);
+                continue;
+            };
+            self.layers.push(layer as i32);
+
+            let world = scene.get_world_matrix(e);
+            let position = world.w_axis.truncate();
+            let direction = world.transform_vector3(Vec3::NEG_Z).normalize_or_zero();
+            match light.kind {
+                LightKind::Directional => {
+                    let camera = scene.get_camera();
+                    let mut start = near;
+                    for cascade in 0..Self::CASCADES {
+                        let end = self.split(cascade, near);
+                        self.matrices[cascade] =
+                            cascade_matrix(camera, fov, aspect, start, end, direction);
+                        self.active[cascade] = true;
+                        self.splits[cascade] = end;
+                        start = end;
+                    }
+                }
+                LightKind::Spot {
+                    range,
+                    inner_angle,
+                    outer_angle,
+                } => {
+                    let angle = outer_angle.max(inner_angle).min(89.);
+                    let projection = Mat4::perspective_rh_gl(
+                        2. * angle.to_radians(),
+                        1.,
+                        Self::SPOT_NEAR,
+                        range.max(Self::SPOT_NEAR * 2.),
+                    );
+                    let view = Mat4::look_at_rh(position, position + direction, up(direction));
+                    self.matrices[layer] = projection * view;
+                    self.active[layer] = true;
+                }
+                LightKind::Point { .. } => unreachable!(),
+            }
+        }
+    }
+
+    /// Far end of `cascade`, blending uniform and logarithmic splits.
+    fn split(&self, cascade: usize, near: f32) -> f32 {
+        let far = self.settings.distance.max(near * 2.);
+        let p = (cascade + 1) as f32 / Self::CASCADES as f32;
+        let logarithmic = near * (far / near).powf(p);
+        let uniform = near + (far - near) * p;
+        let lambda = self.settings.split_lambda.clamp(0., 1.);
+        lambda * logarithmic + (1. - lambda) * uniform
+    }
+
+    /// Renders every active layer, calling `draw` to issue the draws of the
+    /// shadow casters with the depth shader bound.
+    pub fn render(&mut self, mut draw: impl FnMut()) {
+        if !self.active.contains(&true) {
+            return;
+        }
+        let mut viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.framebuffer);
+            gl::Viewport(0, 0, Self::SIZE, Self::SIZE);
+            gl::Enable(gl::POLYGON_OFFSET_FILL);
+            gl::PolygonOffset(self.settings.slope_bias, 1.);
+        }
+        self.shader.activate();
+        for layer in 0..Self::LAYERS {
+            if !self.active[layer] {
+                continue;
+            }
+            unsafe {
+                gl::NamedFramebufferTextureLayer(
+                    self.framebuffer,
+                    gl::DEPTH_ATTACHMENT,
+                    self.texture,
+                    0,
+                    layer as i32,
+                );
+                gl::Clear(gl::DEPTH_BUFFER_BIT);
+            }
+            self.shader.set_mat4("u_LightMatrix", &self.matrices[layer]);
+            draw();
+        }
+        unsafe {
+            gl::Disable(gl::POLYGON_OFFSET_FILL);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+        }
+    }
+
+    /// Binds the maps to texture unit `unit` and sets the uniforms `shader`
+    /// needs to look them up.
+    pub fn bind(&self, shader: &mut ShaderProgram, unit: u32) {
+        unsafe {
+            gl::BindTextureUnit(unit, self.texture);
+        }
+        // The lookups rely on the comparison state of the texture itself.
+        Sampler::unbind(unit);
+        shader.set_int("u_ShadowMaps", unit as i8);
+        shader.set_mat4_array("u_ShadowMatrices", &self.matrices);
+        shader.set_float_array("u_CascadeSplits", &self.splits);
+        shader.set_float("u_DepthBias", self.settings.depth_bias);
+        shader.set_float("u_NormalBias", self.settings.normal_bias);
+        shader.set_uint("u_PcfRadius", self.settings.pcf_radius);
+    }
+}
+
+impl Drop for ShadowMaps {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(1, &self.framebuffer);
+            gl::DeleteTextures(1, &self.texture);
+        }
+    }
+}
+
+/// Any up vector not parallel to `direction`.
+fn up(direction: Vec3) -> Vec3 {
+    if direction.y.abs() > 0.99 {
+        Vec3::X
+    } else {
+        Vec3::Y
+    }
+}
+
+/// Orthographic light matrix covering the part of the camera frustum between
+/// `near` and `far`.
+///
+/// The matrix is fit around the bounding sphere of that slice, so it does not
+/// change size as the camera turns, and moves in whole texels, so shadow
+/// edges do not shimmer as the camera moves.
+fn cascade_matrix(
+    camera: &Camera,
+    fov: f32,
+    aspect: f32,
+    near: f32,
+    far: f32,
+    direction: Vec3,
+) -> Mat4 {
+    let forward = camera.direction.normalize();
+    let right = forward.cross(camera.up).normalize();
+    let up_axis = right.cross(forward);
+    let tan = (fov.to_radians() / 2.).tan();
+    let mut corners = vec![];
+    for distance in [near, far] {
+        let center = camera.position + forward * distance;
+        let half_height = up_axis * distance * tan;
+        let half_width = right * distance * tan * aspect;
+        for (x, y) in [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)] {
+            corners.push(center + half_width * x + half_height * y);
+        }
+    }
+    let center = corners.iter().sum::<Vec3>() / corners.len() as f32;
+    let radius = corners
+        .iter()
+        .map(|c| c.distance(center))
+        .fold(0., f32::max);
+    let radius = (radius * 16.).ceil() / 16.;
+
+    let view = Mat4::look_at_rh(
+        center - direction * (radius + ShadowMaps::CASTER_MARGIN),
+        center,
+        up(direction),
+    );
+    let mut projection = Mat4::orthographic_rh_gl(
+        -radius,
+        radius,
+        -radius,
+        radius,
+        0.,
+        2. * radius + ShadowMaps::CASTER_MARGIN,
+    );
+    let half_size = ShadowMaps::SIZE as f32 / 2.;
+    let origin = (projection * view).project_point3(Vec3::ZERO) * half_size;
+    let offset = (origin.round() - origin) / half_size;
+    projection.w_axis.x += offset.x;
+    projection.w_axis.y += offset.y;
+    projection * view
+}
@@ -0,0 +1,76 @@
+use gl::types::GLuint;
+use glam::{Mat4, Vec4};
+
+use crate::glcall;
+
+use super::{shader::ShaderProgram, texture::Cubemap, vertex_array};
+
+/// Draws an environment cube map behind the scene.
+pub struct Skybox {
+    shader: ShaderProgram,
+    /// Full-screen triangles need no vertex data, but a bound VAO.
+    vao: GLuint,
+    enabled: bool,
+}
+
+impl Drop for Skybox {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+impl Skybox {
+    pub fn new() -> Self {
+        let mut vao = 0;
+        unsafe {
+            gl::CreateVertexArrays(1, &mut vao);
+        }
+        Self {
+            shader: ShaderProgram::new(
+                include_str!("../../resources/v_skybox.glsl"),
+                include_str!("../../resources/f_skybox.glsl"),
+            ),
+            vao,
+            enabled: true,
+        }
+    }
+
+    #[inline]
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+
+    #[inline]
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    /// Fills the pixels of the bound framebuffer nothing was drawn to with
+    /// `environment` times `intensity`. Drawn after the opaque geometry, so
+    /// covered pixels are rejected by the depth test before shading.
+    pub fn draw(&mut self, environment: &Cubemap, view: &Mat4, projection: &Mat4, intensity: f32) {
+        // Only the camera rotation matters for something infinitely far.
+        let mut rotation = *view;
+        rotation.w_axis = Vec4::W;
+        self.shader.activate();
+        self.shader.set_mat4(
+            "u_InverseViewProjection",
+            &(*projection * rotation).inverse(),
+        );
+        environment.bind(0);
+        self.shader.set_int("u_Sky", 0);
+        self.shader.set_float("u_Intensity", intensity);
+        vertex_array::bind_id(self.vao);
+        unsafe {
+            // The triangle lies on the far plane, where the depth buffer is
+            // cleared to, so it must pass on equal depths and not write.
+            gl::DepthFunc(gl::LEQUAL);
+            gl::DepthMask(gl::FALSE);
+            glcall!(gl::DrawArrays(gl::TRIANGLES, 0, 3));
+            gl::DepthMask(gl::TRUE);
+            gl::DepthFunc(gl::LESS);
+        }
+    }
+}
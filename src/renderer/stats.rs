@@ -0,0 +1,97 @@
+use gl::types::GLuint;
+
+/// Work done by the renderer in one frame.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameStats {
+    /// Draw calls of the scene geometry, shadow passes included.
+    pub draw_calls: u32,
+    /// Instances sent to the main pass.
+    pub instances: usize,
+    /// Static instances among them. Culling on the CPU draws every one of
+    /// them and only leaves dynamic instances out.
+    pub statics: usize,
+    /// Dynamic instances left out of the main pass, known when culling on
+    /// the CPU only.
+    pub culled: Option<usize>,
+    /// Triangles drawn by the main pass, known when culling on the CPU only.
+    pub triangles: Option<usize>,
+    /// Instance data written for the GPU.
+    pub upload_bytes: usize,
+    /// CPU time spent issuing the frame, in milliseconds.
+    pub cpu_ms: f32,
+    /// GPU time of a frame a few frames back, once its result arrived.
+    pub gpu_ms: Option<f32>,
+}
+
+/// Measures the GPU time of the commands issued between
+/// [`GpuTimer::begin`] and [`GpuTimer::end`], reading the result a few frames
+/// later so it never waits for the GPU.
+pub struct GpuTimer {
+    queries: [GLuint; GpuTimer::LATENCY],
+    /// Whether each query was issued and its result not read yet.
+    pending: [bool; GpuTimer::LATENCY],
+    current: usize,
+    last_ms: Option<f32>,
+}
+
+impl Drop for GpuTimer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteQueries(Self::LATENCY as i32, self.queries.as_ptr());
+        }
+    }
+}
+
+impl GpuTimer {
+    /// Frames in flight, each with its own query.
+    const LATENCY: usize = 4;
+
+    pub fn new() -> Self {
+        let mut queries = [0; Self::LATENCY];
+        unsafe {
+            gl::CreateQueries(gl::TIME_ELAPSED, Self::LATENCY as i32, queries.as_mut_ptr());
+        }
+        Self {
+            queries,
+            pending: [false; Self::LATENCY],
+            current: 0,
+            last_ms: None,
+        }
+    }
+
+    /// Starts timing, after collecting the oldest result if it is ready.
+    pub fn begin(&mut self) {
+        self.current = (self.current + 1) % Self::LATENCY;
+        let query = self.queries[self.current];
+        if self.pending[self.current] {
+            let mut available = 0;
+            unsafe {
+                gl::GetQueryObjectiv(query, gl::QUERY_RESULT_AVAILABLE, &mut available);
+            }
+            if available != 0 {
+                let mut nanoseconds = 0;
+                unsafe {
+                    gl::GetQueryObjectui64v(query, gl::QUERY_RESULT, &mut nanoseconds);
+                }
+                self.last_ms = Some(nanoseconds as f32 / 1e6);
+            }
+            // A result that is not ready yet is dropped with the query reuse.
+        }
+        unsafe {
+            gl::BeginQuery(gl::TIME_ELAPSED, query);
+        }
+        self.pending[self.current] = true;
+    }
+
+    pub fn end(&mut self) {
+        unsafe {
+            gl::EndQuery(gl::TIME_ELAPSED);
+        }
+    }
+
+    /// Latest measured time, in milliseconds.
+    #[inline]
+    pub fn get_last_ms(&self) -> Option<f32> {
+        self.last_ms
+    }
+}
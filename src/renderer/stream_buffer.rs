@@ -0,0 +1,151 @@
+use std::{marker::PhantomData, mem::size_of};
+
+use gl::types::{GLsync, GLuint};
+
+use crate::glcall;
+
+/// Persistently mapped buffer split into a ring of regions, one written by
+/// the CPU each frame while the GPU may still read the others.
+///
+/// A fence is placed after the draws reading a region, and the region is
+/// only written again once that fence has signaled, so writing never stalls
+/// on an implicit synchronization.
+pub struct StreamBuffer<T> {
+    id: GLuint,
+    ptr: *mut T,
+    /// Elements per region.
+    capacity: usize,
+    fences: Vec<GLsync>,
+    current: usize,
+    t_element: PhantomData<T>,
+}
+
+impl<T: Copy> StreamBuffer<T> {
+    const FLAGS: u32 = gl::MAP_WRITE_BIT | gl::MAP_PERSISTENT_BIT | gl::MAP_COHERENT_BIT;
+
+    pub fn new(capacity: usize, regions: usize) -> Self {
+        let (id, ptr) = Self::allocate(capacity, regions);
+        Self {
+            id,
+            ptr,
+            capacity,
+            fences: vec![std::ptr::null(); regions],
+            current: 0,
+            t_element: PhantomData,
+        }
+    }
+
+    fn allocate(capacity: usize, regions: usize) -> (GLuint, *mut T) {
+        let size = (capacity * regions * size_of::<T>()) as isize;
+        let mut id = 0;
+        unsafe {
+            gl::CreateBuffers(1, &mut id);
+            glcall!(gl::NamedBufferStorage(
+                id,
+                size,
+                std::ptr::null(),
+                Self::FLAGS
+            ));
+            let ptr = gl::MapNamedBufferRange(id, 0, size, Self::FLAGS).cast::<T>();
+            assert!(!ptr.is_null(), "Cannot map stream buffer");
+            (id, ptr)
+        }
+    }
+
+    #[inline]
+    pub fn get_id(&self) -> GLuint {
+        self.id
+    }
+
+    #[inline]
+    pub fn get_capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Byte offset of the region being written this frame.
+    #[inline]
+    pub fn get_offset(&self) -> isize {
+        (self.current * self.capacity * size_of::<T>()) as isize
+    }
+
+    /// Moves to the next region, waiting until the GPU is done with it, and
+    /// makes sure it holds at least `count` elements. Growing reallocates the
+    /// whole ring, after waiting for every region.
+    pub fn begin_frame(&mut self, count: usize) {
+        self.current = (self.current + 1) % self.fences.len();
+        if count > self.capacity {
+            for i in 0..self.fences.len() {
+                self.wait(i);
+            }
+            self.release();
+            let capacity = count.max(self.capacity * 2);
+            (self.id, self.ptr) = Self::allocate(capacity, self.fences.len());
+            self.capacity = capacity;
+        } else {
+            self.wait(self.current);
+        }
+    }
+
+    /// Writes `data` at `index` of the current region.
+    pub fn write(&mut self, index: usize, data: impl IntoIterator<Item = T>) -> usize {
+        let mut written = 0;
+        for (i, value) in data.into_iter().enumerate() {
+            assert!(index + i < self.capacity, "Stream buffer region overflow");
+            // The mapping is coherent, so plain writes are seen by the GPU.
+            unsafe {
+                self.ptr
+                    .add(self.current * self.capacity + index + i)
+                    .write_unaligned(value);
+            }
+            written += 1;
+        }
+        written
+    }
+
+    /// Marks the current region as in use by the commands issued so far.
+    pub fn end_frame(&mut self) {
+        unsafe {
+            let fence = &mut self.fences[self.current];
+            if !fence.is_null() {
+                gl::DeleteSync(*fence);
+            }
+            *fence = gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0);
+        }
+    }
+
+    fn wait(&mut self, region: usize) {
+        let fence = std::mem::replace(&mut self.fences[region], std::ptr::null());
+        if fence.is_null() {
+            return;
+        }
+        unsafe {
+            loop {
+                match gl::ClientWaitSync(fence, gl::SYNC_FLUSH_COMMANDS_BIT, 1_000_000_000) {
+                    gl::ALREADY_SIGNALED | gl::CONDITION_SATISFIED => break,
+                    gl::TIMEOUT_EXPIRED => continue,
+                    _ => panic!("Waiting on a stream buffer fence failed"),
+                }
+            }
+            gl::DeleteSync(fence);
+        }
+    }
+
+    fn release(&mut self) {
+        unsafe {
+            gl::UnmapNamedBuffer(self.id);
+            gl::DeleteBuffers(1, &self.id);
+        }
+    }
+}
+
+impl<T> Drop for StreamBuffer<T> {
+    fn drop(&mut self) {
+        unsafe {
+            for &fence in self.fences.iter().filter(|f| !f.is_null()) {
+                gl::DeleteSync(fence);
+            }
+            gl::UnmapNamedBuffer(self.id);
+            gl::DeleteBuffers(1, &self.id);
+        }
+    }
+}
@@ -0,0 +1,611 @@
+use std::{collections::HashMap, fmt, fs, mem::size_of, path::Path};
+
+use ab_glyph::{Font as _, FontVec, GlyphId, InvalidFont, PxScale, ScaleFont};
+use gl::types::GLuint;
+use glam::{Mat4, Vec2, Vec4};
+
+use crate::{entity::EntityId, glcall, scene::Scene};
+
+use super::{
+    shader::ShaderProgram,
+    texture::{Filter, Sampler, SamplerSettings, Texture2D, Wrap},
+    vertex_array,
+    vertex_buffer::{Buffer, Dynamic},
+};
+
+#[derive(Debug)]
+pub enum FontError {
+    Io(std::io::Error),
+    Invalid(InvalidFont),
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FontError::Io(e) => write!(f, "io error: {e}"),
+            FontError::Invalid(e) => write!(f, "cannot parse font: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for FontError {}
+
+impl From<std::io::Error> for FontError {
+    fn from(e: std::io::Error) -> Self {
+        FontError::Io(e)
+    }
+}
+
+impl From<InvalidFont> for FontError {
+    fn from(e: InvalidFont) -> Self {
+        FontError::Invalid(e)
+    }
+}
+
+/// What the atlas of a [`Font`] stores per glyph.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GlyphFormat {
+    /// Coverage, sharpest at the size the glyphs were rasterized at.
+    Coverage,
+    /// Signed distance to the outline, covering `spread` pixels on both
+    /// sides, which stays sharp when scaled up.
+    DistanceField { spread: u32 },
+}
+
+/// Placement of a glyph in the atlas.
+#[derive(Clone, Copy, Debug)]
+struct AtlasGlyph {
+    /// Top-left texel and size of the glyph image.
+    position: Vec2,
+    size: Vec2,
+    /// From the pen position on the baseline to the top-left of the image.
+    offset: Vec2,
+}
+
+/// Screen rectangle of a glyph of laid out text and its atlas coordinates.
+#[derive(Clone, Copy, Debug)]
+pub struct GlyphQuad {
+    /// Top-left and bottom-right corners in pixels, Y down.
+    pub min: Vec2,
+    pub max: Vec2,
+    pub uv_min: Vec2,
+    pub uv_max: Vec2,
+}
+
+/// Text laid out from the top-left of its first line.
+#[derive(Clone, Debug, Default)]
+pub struct TextLayout {
+    pub quads: Vec<GlyphQuad>,
+    /// Width of the widest line and height of all lines, in pixels.
+    pub size: Vec2,
+}
+
+/// TrueType font rasterized on demand into a single channel atlas texture.
+pub struct Font {
+    font: FontVec,
+    /// Pixel height the glyphs are rasterized at.
+    size: f32,
+    format: GlyphFormat,
+    texture: Texture2D,
+    /// `None` for characters without an outline, or that did not fit.
+    glyphs: HashMap<char, Option<AtlasGlyph>>,
+    /// Shelf packing: where the next glyph goes and the height of the
+    /// current row.
+    cursor: (i32, i32),
+    row_height: i32,
+}
+
+impl Font {
+    const ATLAS_SIZE: i32 = 1024;
+    /// Side of the opaque block in the corner of the atlas that solid
+    /// rectangles are drawn with.
+    const SOLID_SIZE: i32 = 4;
+    /// Characters rasterized up front.
+    const PRELOADED: std::ops::RangeInclusive<char> = ' '..='~';
+
+    /// Loads a TrueType or OpenType font from `path`.
+    pub fn load(path: impl AsRef<Path>, size: f32, format: GlyphFormat) -> Result<Self, FontError> {
+        Self::from_bytes(fs::read(path)?, size, format)
+    }
+
+    /// Creates a font from the content of a font file, rasterizing glyphs
+    /// `size` pixels high in `format`.
+    pub fn from_bytes(data: Vec<u8>, size: f32, format: GlyphFormat) -> Result<Self, FontError> {
+        let texture = Texture2D::with_format(Self::ATLAS_SIZE, Self::ATLAS_SIZE, 1, gl::R8);
+        // Uninitialized texels could show around the glyphs when filtering.
+        texture.clear();
+        let mut font = Self {
+            font: FontVec::try_from_vec(data)?,
+            size,
+            format,
+            texture,
+            glyphs: HashMap::new(),
+            cursor: (Self::SOLID_SIZE, 0),
+            row_height: Self::SOLID_SIZE,
+        };
+        let solid = vec![255; (Self::SOLID_SIZE * Self::SOLID_SIZE) as usize];
+        font.texture
+            .set_region(0, 0, Self::SOLID_SIZE, Self::SOLID_SIZE, gl::RED, &solid);
+        for c in Self::PRELOADED {
+            font.glyph(c);
+        }
+        Ok(font)
+    }
+
+    #[inline]
+    pub fn get_format(&self) -> GlyphFormat {
+        self.format
+    }
+
+    #[inline]
+    pub fn get_texture(&self) -> &Texture2D {
+        &self.texture
+    }
+
+    /// Atlas coordinates of a fully opaque texel.
+    #[inline]
+    pub fn get_solid_uv(&self) -> Vec2 {
+        Vec2::splat(Self::SOLID_SIZE as f32 / 2. / Self::ATLAS_SIZE as f32)
+    }
+
+    /// Atlas placement of `c`, rasterizing it on first use.
+    fn glyph(&mut self, c: char) -> Option<AtlasGlyph> {
+        if let Some(glyph) = self.glyphs.get(&c) {
+            return *glyph;
+        }
+        let glyph = self.rasterize(c);
+        self.glyphs.insert(c, glyph);
+        glyph
+    }
+
+    fn rasterize(&mut self, c: char) -> Option<AtlasGlyph> {
+        let scaled = self.font.as_scaled(PxScale::from(self.size));
+        let outlined = scaled.outline_glyph(scaled.scaled_glyph(c))?;
+        let bounds = outlined.px_bounds();
+        // A margin keeps filtering from reading the neighbouring glyphs.
+        let padding = match self.format {
+            GlyphFormat::Coverage => 1,
+            GlyphFormat::DistanceField { spread } => spread as i32 + 1,
+        };
+        let width = bounds.width() as i32 + 2 * padding;
+        let height = bounds.height() as i32 + 2 * padding;
+        let mut pixels = vec![0.; (width * height) as usize];
+        outlined.draw(|x, y, coverage| {
+            let index = (y as i32 + padding) * width + x as i32 + padding;
+            pixels[index as usize] = coverage;
+        });
+        let pixels: Vec<u8> = match self.format {
+            GlyphFormat::Coverage => pixels.iter().map(|&c| (c * 255.).round() as u8).collect(),
+            GlyphFormat::DistanceField { spread } => {
+                distance_field(&pixels, width as usize, height as usize, spread as f32)
+            }
+        };
+
+        if self.cursor.0 + width > Self::ATLAS_SIZE {
+            self.cursor = (0, self.cursor.1 + self.row_height);
+            self.row_height = 0;
+        }
+        if self.cursor.1 + height > Self::ATLAS_SIZE {
+            eprintln!("Font atlas full, cannot draw {c:?}");
+            return None;
+        }
+        let (x, y) = self.cursor;
+        self.texture
+            .set_region(x, y, width, height, gl::RED, &pixels);
+        self.cursor.0 += width;
+        self.row_height = self.row_height.max(height);
+
+        Some(AtlasGlyph {
+            position: Vec2::new(x as f32, y as f32),
+            size: Vec2::new(width as f32, height as f32),
+            offset: Vec2::new(bounds.min.x, bounds.min.y) - padding as f32,
+        })
+    }
+
+    /// Lays out `text` `size` pixels high, applying kerning and wrapping
+    /// lines at spaces before they get wider than `max_width`.
+    pub fn layout(&mut self, text: &str, size: f32, max_width: Option<f32>) -> TextLayout {
+        let scale = size / self.size;
+        let metrics = self.font.as_scaled(PxScale::from(size));
+        let line_height = metrics.height() + metrics.line_gap();
+        let ascent = metrics.ascent();
+        let atlas_size = Self::ATLAS_SIZE as f32;
+
+        let mut layout = TextLayout::default();
+        let mut baseline = ascent;
+        for line in text.lines() {
+            let mut x = 0.;
+            let mut previous: Option<GlyphId> = None;
+            for word in line.split_inclusive(' ') {
+                let width = self.measure(word.trim_end(), size);
+                if let Some(max_width) = max_width {
+                    if x > 0. && x + width > max_width {
+                        layout.size.x = layout.size.x.max(x);
+                        x = 0.;
+                        previous = None;
+                        baseline += line_height;
+                    }
+                }
+                for c in word.chars() {
+                    let scaled = self.font.as_scaled(PxScale::from(size));
+                    let id = scaled.glyph_id(c);
+                    if let Some(previous) = previous {
+                        x += scaled.kern(previous, id);
+                    }
+                    let advance = scaled.h_advance(id);
+                    previous = Some(id);
+                    if let Some(glyph) = self.glyph(c) {
+                        let min = Vec2::new(x, baseline) + glyph.offset * scale;
+                        layout.quads.push(GlyphQuad {
+                            min,
+                            max: min + glyph.size * scale,
+                            uv_min: glyph.position / atlas_size,
+                            uv_max: (glyph.position + glyph.size) / atlas_size,
+                        });
+                    }
+                    x += advance;
+                }
+            }
+            layout.size.x = layout.size.x.max(x);
+            baseline += line_height;
+        }
+        layout.size.y = baseline - ascent;
+        layout
+    }
+
+    /// Width of `text` on a single line, `size` pixels high.
+    pub fn measure(&self, text: &str, size: f32) -> f32 {
+        let scaled = self.font.as_scaled(PxScale::from(size));
+        let mut width = 0.;
+        let mut previous = None;
+        for c in text.chars() {
+            let id = scaled.glyph_id(c);
+            if let Some(previous) = previous {
+                width += scaled.kern(previous, id);
+            }
+            width += scaled.h_advance(id);
+            previous = Some(id);
+        }
+        width
+    }
+}
+
+/// Signed distances to the edge of the `coverage` image, mapped from
+/// `spread` pixels outside to `spread` pixels inside onto 0 to 255, so the
+/// edge is at 128.
+fn distance_field(coverage: &[f32], width: usize, height: usize, spread: f32) -> Vec<u8> {
+    let inside: Vec<bool> = coverage.iter().map(|&c| c >= 0.5).collect();
+    let to_inside = distances(&inside, width, height, true);
+    let to_outside = distances(&inside, width, height, false);
+    (0..coverage.len())
+        .map(|i| {
+            // Pixel centers lie half a pixel from the edge between them.
+            let signed = if inside[i] {
+                to_outside[i] - 0.5
+            } else {
+                0.5 - to_inside[i]
+            };
+            let value = 0.5 + signed / (2. * spread);
+            (value.clamp(0., 1.) * 255.).round() as u8
+        })
+        .collect()
+}
+
+/// Distance from every pixel to the nearest one where `inside` equals
+/// `target`, propagating the nearest pixel across the image in two passes.
+fn distances(inside: &[bool], width: usize, height: usize, target: bool) -> Vec<f32> {
+    const FAR: i32 = 1 << 14;
+    let mut nearest: Vec<(i32, i32)> = inside
+        .iter()
+        .map(|&i| if i == target { (0, 0) } else { (FAR, FAR) })
+        .collect();
+    let length = |(x, y): (i32, i32)| x * x + y * y;
+    let relax = |nearest: &mut Vec<(i32, i32)>, x: usize, y: usize, dx: i32, dy: i32| {
+        let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+        if nx < 0 || ny < 0 || nx >= width as i32 || ny >= height as i32 {
+            return;
+        }
+        let (ox, oy) = nearest[ny as usize * width + nx as usize];
+        let candidate = (ox + dx, oy + dy);
+        let current = &mut nearest[y * width + x];
+        if length(candidate) < length(*current) {
+            *current = candidate;
+        }
+    };
+    for y in 0..height {
+        for x in 0..width {
+            for (dx, dy) in [(-1, 0), (-1, -1), (0, -1), (1, -1)] {
+                relax(&mut nearest, x, y, dx, dy);
+            }
+        }
+        for x in (0..width).rev() {
+            relax(&mut nearest, x, y, 1, 0);
+        }
+    }
+    for y in (0..height).rev() {
+        for x in (0..width).rev() {
+            for (dx, dy) in [(1, 0), (1, 1), (0, 1), (-1, 1)] {
+                relax(&mut nearest, x, y, dx, dy);
+            }
+        }
+        for x in 0..width {
+            relax(&mut nearest, x, y, -1, 0);
+        }
+    }
+    nearest
+        .into_iter()
+        .map(|n| (length(n) as f32).sqrt())
+        .collect()
+}
+
+/// How a piece of text is drawn.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextStyle {
+    /// Height in pixels, or in world units for labels.
+    pub size: f32,
+    pub color: Vec4,
+    /// Lines are wrapped before getting wider, in the same units as `size`.
+    pub max_width: Option<f32>,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self {
+            size: 16.,
+            color: Vec4::ONE,
+            max_width: None,
+        }
+    }
+}
+
+/// Text shown above an entity.
+struct Label {
+    text: String,
+    style: TextStyle,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct TextVertex {
+    /// Position in pixels and atlas coordinates.
+    position_uv: Vec4,
+    color: Vec4,
+}
+
+/// Draws text over the screen: HUD text and rectangles queued during the
+/// frame, and labels attached to entities, which stay until removed or their
+/// entity is.
+pub struct TextRenderer {
+    font: Font,
+    shader: ShaderProgram,
+    sampler: Sampler,
+    vao: GLuint,
+    buffer: Buffer<Dynamic>,
+    /// Vertices the buffer has room for.
+    capacity: usize,
+    vertices: Vec<TextVertex>,
+    labels: HashMap<EntityId, Label>,
+}
+
+impl Drop for TextRenderer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.vao);
+        }
+    }
+}
+
+impl TextRenderer {
+    const INITIAL_CAPACITY: usize = 6 * 1024;
+    const FONT_UNIT: u32 = 0;
+    /// Height the built-in font is rasterized at, and its distance range.
+    const FONT_SIZE: f32 = 48.;
+    const FONT_SPREAD: u32 = 6;
+    /// Labels smaller than this many pixels are not drawn.
+    const MIN_LABEL_SIZE: f32 = 4.;
+
+    /// Creates a text renderer with the built-in DejaVu Sans font, as a
+    /// distance field so it scales to any size.
+    pub fn new() -> Self {
+        let font = Font::from_bytes(
+            include_bytes!("../../resources/DejaVuSans.ttf").to_vec(),
+            Self::FONT_SIZE,
+            GlyphFormat::DistanceField {
+                spread: Self::FONT_SPREAD,
+            },
+        )
+        .expect("Cannot load the built-in font");
+        let mut vao = 0;
+        unsafe {
+            gl::CreateVertexArrays(1, &mut vao);
+            for index in 0..2 {
+                gl::EnableVertexArrayAttrib(vao, index);
+                gl::VertexArrayAttribBinding(vao, index, 0);
+                gl::VertexArrayAttribFormat(vao, index, 4, gl::FLOAT, gl::FALSE, index * 16);
+            }
+        }
+        let renderer = Self {
+            font,
+            shader: ShaderProgram::new(
+                include_str!("../../resources/v_text.glsl"),
+                include_str!("../../resources/f_text.glsl"),
+            ),
+            sampler: Sampler::new(&SamplerSettings {
+                min_filter: Filter::Linear,
+                mag_filter: Filter::Linear,
+                mipmap_filter: None,
+                wrap_s: Wrap::ClampToEdge,
+                wrap_t: Wrap::ClampToEdge,
+                anisotropy: 1.,
+            }),
+            vao,
+            buffer: Buffer::<Dynamic>::instanced::<TextVertex>(Self::INITIAL_CAPACITY as isize),
+            capacity: Self::INITIAL_CAPACITY,
+            vertices: vec![],
+            labels: HashMap::new(),
+        };
+        renderer.attach_buffer();
+        renderer
+    }
+
+    fn attach_buffer(&self) {
+        unsafe {
+            gl::VertexArrayVertexBuffer(
+                self.vao,
+                0,
+                self.buffer.get_id(),
+                0,
+                size_of::<TextVertex>() as i32,
+            );
+        }
+    }
+
+    #[inline]
+    pub fn get_font(&self) -> &Font {
+        &self.font
+    }
+
+    /// Replaces the font every text is drawn with.
+    pub fn set_font(&mut self, font: Font) {
+        self.font = font;
+    }
+
+    /// Queues `text` for this frame, its first line starting at `position`
+    /// pixels from the top-left of the window. Returns its size.
+    pub fn draw_text(&mut self, text: &str, position: Vec2, style: &TextStyle) -> Vec2 {
+        let layout = self.font.layout(text, style.size, style.max_width);
+        self.push(&layout, position, style.color);
+        layout.size
+    }
+
+    /// Queues a solid rectangle for this frame, between the `min` and `max`
+    /// corners in pixels from the top-left of the window, for backgrounds
+    /// and graphs.
+    pub fn draw_rect(&mut self, min: Vec2, max: Vec2, color: Vec4) {
+        let uv = self.font.get_solid_uv();
+        let quad = GlyphQuad {
+            min: Vec2::ZERO,
+            max: max - min,
+            uv_min: uv,
+            uv_max: uv,
+        };
+        let layout = TextLayout {
+            quads: vec![quad],
+            size: max - min,
+        };
+        self.push(&layout, min, color);
+    }
+
+    /// Shows `text` above the entity `id` until removed or the entity is
+    /// despawned, `style` sizes being in world units.
+    pub fn set_label(&mut self, id: EntityId, text: impl Into<String>, style: TextStyle) {
+        let text = text.into();
+        self.labels.insert(id, Label { text, style });
+    }
+
+    /// Returns whether the entity `id` had a label.
+    pub fn remove_label(&mut self, id: EntityId) -> bool {
+        self.labels.remove(&id).is_some()
+    }
+
+    fn push(&mut self, layout: &TextLayout, position: Vec2, color: Vec4) {
+        for quad in layout.quads.iter() {
+            let (min, max) = (position + quad.min, position + quad.max);
+            let corners = [
+                (min, quad.uv_min),
+                (
+                    Vec2::new(max.x, min.y),
+                    Vec2::new(quad.uv_max.x, quad.uv_min.y),
+                ),
+                (max, quad.uv_max),
+                (
+                    Vec2::new(min.x, max.y),
+                    Vec2::new(quad.uv_min.x, quad.uv_max.y),
+                ),
+            ];
+            for i in [0, 1, 2, 0, 2, 3] {
+                let (position, uv) = corners[i];
+                self.vertices.push(TextVertex {
+                    position_uv: Vec4::new(position.x, position.y, uv.x, uv.y),
+                    color,
+                });
+            }
+        }
+    }
+
+    /// Queues the labels of the entities of `scene` in front of the camera,
+    /// centered above their bounds, and drops those of despawned entities.
+    fn queue_labels(&mut self, scene: &Scene, view_projection: &Mat4, size: (i32, i32)) {
+        let screen = Vec2::new(size.0 as f32, size.1 as f32);
+        self.labels.retain(|id, _| scene.get_entity(*id).is_some());
+        let labels: Vec<_> = self
+            .labels
+            .iter()
+            .filter_map(|(id, label)| {
+                let bounds = scene.get_world_bounds(scene.get_entity(*id)?);
+                let mut anchor = bounds.get_center();
+                anchor.y = bounds.max.y;
+                let clip = *view_projection * anchor.extend(1.);
+                if clip.w <= 0. {
+                    return None;
+                }
+                let ndc = clip.truncate() / clip.w;
+                let position = Vec2::new(ndc.x + 1., 1. - ndc.y) * 0.5 * screen;
+                // Pixels per world unit at the depth of the anchor.
+                let scale = view_projection.y_axis.y.abs().max(f32::EPSILON);
+                let pixels = 0.5 * screen.y * scale / clip.w;
+                Some((label.text.clone(), label.style, position, pixels))
+            })
+            .collect();
+        for (text, style, position, pixels) in labels {
+            let height = style.size * pixels;
+            if height < Self::MIN_LABEL_SIZE {
+                continue;
+            }
+            let max_width = style.max_width.map(|w| w * pixels);
+            let layout = self.font.layout(&text, height, max_width);
+            let origin = position - Vec2::new(layout.size.x / 2., layout.size.y);
+            self.push(&layout, origin, style.color);
+        }
+    }
+
+    /// Draws the labels and the text queued this frame over the bound
+    /// `size` pixels framebuffer, then forgets the queued text.
+    pub fn draw(&mut self, scene: &Scene, view_projection: &Mat4, size: (i32, i32)) {
+        self.queue_labels(scene, view_projection, size);
+        if self.vertices.is_empty() {
+            return;
+        }
+        if self.vertices.len() > self.capacity {
+            self.capacity = self.vertices.len().next_power_of_two();
+            self.buffer
+                .resize((self.capacity * size_of::<TextVertex>()) as isize, 0);
+            self.attach_buffer();
+        }
+        if let Err(e) = self.buffer.set_data(&self.vertices, 0) {
+            panic!("Cannot upload text: {e}");
+        }
+
+        let projection = Mat4::orthographic_rh_gl(0., size.0 as f32, size.1 as f32, 0., -1., 1.);
+        self.shader.activate();
+        self.shader.set_mat4("u_Projection", &projection);
+        self.shader.set_texture(
+            "u_Atlas",
+            Self::FONT_UNIT,
+            self.font.get_texture(),
+            &self.sampler,
+        );
+        let sdf = matches!(self.font.get_format(), GlyphFormat::DistanceField { .. });
+        self.shader.set_int("u_DistanceField", sdf as i8);
+        vertex_array::bind_id(self.vao);
+        unsafe {
+            gl::Disable(gl::DEPTH_TEST);
+            gl::Enable(gl::BLEND);
+            gl::BlendFunc(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA);
+            glcall!(gl::DrawArrays(gl::TRIANGLES, 0, self.vertices.len() as i32));
+            gl::Disable(gl::BLEND);
+            gl::Enable(gl::DEPTH_TEST);
+        }
+        self.vertices.clear();
+    }
+}
@@ -0,0 +1,500 @@
+use std::{fs::File, io::BufReader, path::Path};
+
+use gl::types::{GLenum, GLuint};
+use image::codecs::hdr::HdrDecoder;
+
+use crate::glcall;
+
+use super::shader::ShaderProgram;
+
+/// Core since OpenGL 4.6 but missing from the `gl` bindings.
+const TEXTURE_MAX_ANISOTROPY: GLenum = 0x84FE;
+
+/// Immutable 2D texture.
+pub struct Texture2D {
+    id: GLuint,
+    width: i32,
+    height: i32,
+    levels: i32,
+}
+
+impl Drop for Texture2D {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+        }
+    }
+}
+
+impl Texture2D {
+    /// Allocates an uninitialized texture with the sized internal `format`.
+    pub fn with_format(width: i32, height: i32, levels: i32, format: GLenum) -> Self {
+        let mut id = 0;
+        unsafe {
+            gl::CreateTextures(gl::TEXTURE_2D, 1, &mut id);
+            glcall!(gl::TextureStorage2D(id, levels, format, width, height));
+        }
+        Self {
+            id,
+            width,
+            height,
+            levels,
+        }
+    }
+
+    /// Creates a texture from tightly packed RGBA8 rows, bottom row first,
+    /// with a full mip chain if `mipmaps` is set.
+    pub fn from_rgba(width: i32, height: i32, pixels: &[u8], mipmaps: bool) -> Self {
+        Self::from_bytes(width, height, pixels, mipmaps, gl::RGBA8)
+    }
+
+    /// Like [`Texture2D::from_rgba`], for sRGB encoded colors, which are
+    /// decoded to linear ones when sampled.
+    pub fn from_srgba(width: i32, height: i32, pixels: &[u8], mipmaps: bool) -> Self {
+        Self::from_bytes(width, height, pixels, mipmaps, gl::SRGB8_ALPHA8)
+    }
+
+    fn from_bytes(width: i32, height: i32, pixels: &[u8], mipmaps: bool, format: GLenum) -> Self {
+        assert_eq!(
+            pixels.len(),
+            (width * height * 4) as usize,
+            "Texture data does not match its size"
+        );
+        let levels = if mipmaps {
+            mip_levels(width, height)
+        } else {
+            1
+        };
+        let texture = Self::with_format(width, height, levels, format);
+        unsafe {
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            glcall!(gl::TextureSubImage2D(
+                texture.id,
+                0,
+                0,
+                0,
+                width,
+                height,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_ptr().cast()
+            ));
+        }
+        if mipmaps {
+            texture.generate_mipmaps();
+        }
+        texture
+    }
+
+    /// Creates an RGBA16F texture from tightly packed RGBA rows, bottom row
+    /// first.
+    pub fn from_rgba_f32(width: i32, height: i32, pixels: &[f32]) -> Self {
+        assert_eq!(
+            pixels.len(),
+            (width * height * 4) as usize,
+            "Texture data does not match its size"
+        );
+        let texture = Self::with_format(width, height, 1, gl::RGBA16F);
+        unsafe {
+            glcall!(gl::TextureSubImage2D(
+                texture.id,
+                0,
+                0,
+                0,
+                width,
+                height,
+                gl::RGBA,
+                gl::FLOAT,
+                pixels.as_ptr().cast()
+            ));
+        }
+        texture
+    }
+
+    /// Loads a PNG, JPEG or TGA image holding sRGB colors, like base color
+    /// and emissive maps.
+    pub fn load_srgb(path: impl AsRef<Path>, mipmaps: bool) -> image::ImageResult<Self> {
+        // Images store the top row first, OpenGL expects the bottom one.
+        let image = image::open(path)?.flipv().into_rgba8();
+        let (width, height) = image.dimensions();
+        Ok(Self::from_srgba(
+            width as i32,
+            height as i32,
+            image.as_raw(),
+            mipmaps,
+        ))
+    }
+
+    /// Loads a Radiance HDR image.
+    pub fn load_hdr(path: impl AsRef<Path>) -> image::ImageResult<Self> {
+        // `image::open` would clamp the colors to 8 bits.
+        let file = BufReader::new(File::open(path)?);
+        let decoder = HdrDecoder::new(file)?;
+        let metadata = decoder.metadata();
+        let pixels = decoder.read_image_hdr()?;
+        let data: Vec<f32> = pixels
+            .chunks(metadata.width as usize)
+            .rev()
+            .flatten()
+            .flat_map(|p| [p[0], p[1], p[2], 1.])
+            .collect();
+        Ok(Self::from_rgba_f32(
+            metadata.width as i32,
+            metadata.height as i32,
+            &data,
+        ))
+    }
+
+    /// Fills every level below the base one from the base level.
+    pub fn generate_mipmaps(&self) {
+        if self.levels > 1 {
+            unsafe {
+                gl::GenerateTextureMipmap(self.id);
+            }
+        }
+    }
+
+    /// Sets every texel of the base level to zero.
+    pub fn clear(&self) {
+        unsafe {
+            glcall!(gl::ClearTexImage(
+                self.id,
+                0,
+                gl::RED,
+                gl::UNSIGNED_BYTE,
+                std::ptr::null()
+            ));
+        }
+    }
+
+    /// Writes tightly packed rows of `format` bytes, one per channel, to the
+    /// `width` by `height` texels of the base level starting at `x`, `y`.
+    pub fn set_region(
+        &self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        format: GLenum,
+        pixels: &[u8],
+    ) {
+        unsafe {
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            glcall!(gl::TextureSubImage2D(
+                self.id,
+                0,
+                x,
+                y,
+                width,
+                height,
+                format,
+                gl::UNSIGNED_BYTE,
+                pixels.as_ptr().cast()
+            ));
+        }
+    }
+
+    #[inline]
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::BindTextureUnit(unit, self.id);
+        }
+    }
+
+    #[inline]
+    pub fn get_id(&self) -> GLuint {
+        self.id
+    }
+
+    #[inline]
+    pub fn get_width(&self) -> i32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn get_height(&self) -> i32 {
+        self.height
+    }
+}
+
+/// Immutable cube map, filtered linearly across its mip levels and faces.
+pub struct Cubemap {
+    id: GLuint,
+    size: i32,
+    levels: i32,
+}
+
+impl Drop for Cubemap {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+        }
+    }
+}
+
+impl Cubemap {
+    /// Allocates an uninitialized cube map with faces `size` texels wide,
+    /// `levels` mip levels and the sized internal `format`.
+    pub fn new(size: i32, levels: i32, format: GLenum) -> Self {
+        let mut id = 0;
+        let min_filter = if levels > 1 {
+            gl::LINEAR_MIPMAP_LINEAR
+        } else {
+            gl::LINEAR
+        };
+        unsafe {
+            gl::CreateTextures(gl::TEXTURE_CUBE_MAP, 1, &mut id);
+            glcall!(gl::TextureStorage2D(id, levels, format, size, size));
+            gl::TextureParameteri(id, gl::TEXTURE_MIN_FILTER, min_filter as i32);
+            gl::TextureParameteri(id, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            for wrap in [gl::TEXTURE_WRAP_S, gl::TEXTURE_WRAP_T, gl::TEXTURE_WRAP_R] {
+                gl::TextureParameteri(id, wrap, gl::CLAMP_TO_EDGE as i32);
+            }
+        }
+        Self { id, size, levels }
+    }
+
+    /// Loads a cube map from six square sRGB images of the same size, in the
+    /// +X, -X, +Y, -Y, +Z, -Z order, with a full mip chain.
+    pub fn load<P: AsRef<Path>>(faces: [P; 6]) -> image::ImageResult<Self> {
+        let mut cubemap: Option<Self> = None;
+        for (face, path) in faces.iter().enumerate() {
+            // Unlike 2D textures, cube map faces are stored top row first.
+            let image = image::open(path)?.into_rgba8();
+            let (width, height) = image.dimensions();
+            let size = cubemap.as_ref().map_or(width as i32, |c| c.size);
+            if width != height || width as i32 != size {
+                return Err(image::ImageError::Parameter(
+                    image::error::ParameterError::from_kind(
+                        image::error::ParameterErrorKind::DimensionMismatch,
+                    ),
+                ));
+            }
+            let cubemap = cubemap
+                .get_or_insert_with(|| Self::new(size, Self::full_levels(size), gl::SRGB8_ALPHA8));
+            unsafe {
+                gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+                glcall!(gl::TextureSubImage3D(
+                    cubemap.id,
+                    0,
+                    0,
+                    0,
+                    face as i32,
+                    size,
+                    size,
+                    1,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    image.as_raw().as_ptr().cast()
+                ));
+            }
+        }
+        let cubemap = cubemap.expect("Six faces were loaded");
+        cubemap.generate_mipmaps();
+        Ok(cubemap)
+    }
+
+    /// Loads an equirectangular Radiance HDR image, see
+    /// [`Cubemap::from_equirect`].
+    pub fn load_equirect(path: impl AsRef<Path>, size: i32) -> image::ImageResult<Self> {
+        Ok(Self::from_equirect(&Texture2D::load_hdr(path)?, size))
+    }
+
+    /// Projects an equirectangular image onto the faces of a new RGBA16F cube
+    /// map `size` texels wide, with a full mip chain.
+    pub fn from_equirect(equirect: &Texture2D, size: i32) -> Self {
+        const GROUP_SIZE: u32 = 8;
+        let cubemap = Self::new(size, Self::full_levels(size), gl::RGBA16F);
+        // Environments are rarely loaded, the shader is not worth keeping.
+        let mut shader =
+            ShaderProgram::compute(include_str!("../../resources/equirect_to_cube.comp"));
+        let sampler = Sampler::new(&SamplerSettings {
+            min_filter: Filter::Linear,
+            mag_filter: Filter::Linear,
+            mipmap_filter: None,
+            wrap_s: Wrap::Repeat,
+            wrap_t: Wrap::ClampToEdge,
+            anisotropy: 1.,
+        });
+        shader.activate();
+        shader.set_texture("u_Equirect", 0, equirect, &sampler);
+        let groups = (size as u32).div_ceil(GROUP_SIZE);
+        unsafe {
+            glcall!(gl::BindImageTexture(
+                0,
+                cubemap.id,
+                0,
+                gl::TRUE,
+                0,
+                gl::WRITE_ONLY,
+                gl::RGBA16F
+            ));
+            glcall!(gl::DispatchCompute(groups, groups, 6));
+            gl::MemoryBarrier(gl::TEXTURE_FETCH_BARRIER_BIT | gl::SHADER_IMAGE_ACCESS_BARRIER_BIT);
+        }
+        Sampler::unbind(0);
+        cubemap.generate_mipmaps();
+        cubemap
+    }
+
+    /// Levels of a full mip chain for faces `size` texels wide.
+    #[inline]
+    pub fn full_levels(size: i32) -> i32 {
+        mip_levels(size, size)
+    }
+
+    /// Fills every level below the base one from the base level.
+    pub fn generate_mipmaps(&self) {
+        if self.levels > 1 {
+            unsafe {
+                gl::GenerateTextureMipmap(self.id);
+            }
+        }
+    }
+
+    /// Binds the cube map to texture unit `unit`, without any sampler object,
+    /// so its own filtering applies.
+    #[inline]
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::BindTextureUnit(unit, self.id);
+        }
+        Sampler::unbind(unit);
+    }
+
+    #[inline]
+    pub fn get_id(&self) -> GLuint {
+        self.id
+    }
+
+    #[inline]
+    pub fn get_size(&self) -> i32 {
+        self.size
+    }
+
+    #[inline]
+    pub fn get_levels(&self) -> i32 {
+        self.levels
+    }
+}
+
+/// Levels of a full mip chain down to 1x1.
+fn mip_levels(width: i32, height: i32) -> i32 {
+    32 - (width.max(height).max(1) as u32).leading_zeros() as i32
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Filter {
+    Nearest,
+    Linear,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Wrap {
+    Repeat,
+    ClampToEdge,
+}
+
+impl Wrap {
+    fn to_gl(self) -> GLenum {
+        match self {
+            Wrap::Repeat => gl::REPEAT,
+            Wrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+        }
+    }
+}
+
+/// How a [`Sampler`] reads a texture.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SamplerSettings {
+    pub min_filter: Filter,
+    pub mag_filter: Filter,
+    /// Filtering between mip levels, or `None` to only read the base level.
+    pub mipmap_filter: Option<Filter>,
+    pub wrap_s: Wrap,
+    pub wrap_t: Wrap,
+    /// Maximum anisotropy, 1 disables anisotropic filtering.
+    pub anisotropy: f32,
+}
+
+impl Default for SamplerSettings {
+    fn default() -> Self {
+        Self {
+            min_filter: Filter::Linear,
+            mag_filter: Filter::Linear,
+            mipmap_filter: Some(Filter::Linear),
+            wrap_s: Wrap::Repeat,
+            wrap_t: Wrap::Repeat,
+            anisotropy: 8.,
+        }
+    }
+}
+
+/// Sampler object, overriding the sampling state of whatever texture is bound
+/// to the same unit.
+pub struct Sampler {
+    id: GLuint,
+    settings: SamplerSettings,
+}
+
+impl Drop for Sampler {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteSamplers(1, &self.id);
+        }
+    }
+}
+
+impl Sampler {
+    pub fn new(settings: &SamplerSettings) -> Self {
+        let min_filter = match (settings.min_filter, settings.mipmap_filter) {
+            (Filter::Nearest, None) => gl::NEAREST,
+            (Filter::Linear, None) => gl::LINEAR,
+            (Filter::Nearest, Some(Filter::Nearest)) => gl::NEAREST_MIPMAP_NEAREST,
+            (Filter::Nearest, Some(Filter::Linear)) => gl::NEAREST_MIPMAP_LINEAR,
+            (Filter::Linear, Some(Filter::Nearest)) => gl::LINEAR_MIPMAP_NEAREST,
+            (Filter::Linear, Some(Filter::Linear)) => gl::LINEAR_MIPMAP_LINEAR,
+        };
+        let mag_filter = match settings.mag_filter {
+            Filter::Nearest => gl::NEAREST,
+            Filter::Linear => gl::LINEAR,
+        };
+        let mut id = 0;
+        unsafe {
+            gl::CreateSamplers(1, &mut id);
+            gl::SamplerParameteri(id, gl::TEXTURE_MIN_FILTER, min_filter as i32);
+            gl::SamplerParameteri(id, gl::TEXTURE_MAG_FILTER, mag_filter as i32);
+            gl::SamplerParameteri(id, gl::TEXTURE_WRAP_S, settings.wrap_s.to_gl() as i32);
+            gl::SamplerParameteri(id, gl::TEXTURE_WRAP_T, settings.wrap_t.to_gl() as i32);
+            glcall!(gl::SamplerParameterf(
+                id,
+                TEXTURE_MAX_ANISOTROPY,
+                settings.anisotropy.max(1.)
+            ));
+        }
+        Self {
+            id,
+            settings: *settings,
+        }
+    }
+
+    #[inline]
+    pub fn get_settings(&self) -> &SamplerSettings {
+        &self.settings
+    }
+
+    #[inline]
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::BindSampler(unit, self.id);
+        }
+    }
+
+    /// Lets the texture bound to `unit` use its own sampling state again.
+    #[inline]
+    pub fn unbind(unit: u32) {
+        unsafe {
+            gl::BindSampler(unit, 0);
+        }
+    }
+}
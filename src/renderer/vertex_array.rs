@@ -1,9 +1,11 @@
+use std::mem::size_of;
+
 use gl::types::GLuint;
-use glam::Mat4;
 
 use super::{
     mesh::Mesh,
     vertex_buffer::{Buffer, Dynamic},
+    Instance,
 };
 
 static mut BINDED_ID: GLuint = 0;
@@ -12,6 +14,7 @@ pub struct VertexArray {
     id: GLuint,
     index_size: i32,
     pub instanced_buffer: Buffer<Dynamic>,
+    instance_binding: GLuint,
 }
 
 impl VertexArray {
@@ -50,7 +53,7 @@ impl VertexArray {
                 binding_index += 1;
                 index += 1;
             }
-            let instanced_buffer = Buffer::<Dynamic>::instanced::<Mat4>(instance_count);
+            let instanced_buffer = Buffer::<Dynamic>::instanced::<Instance>(instance_count);
 
             gl::VertexArrayVertexBuffer(
                 id,
@@ -60,34 +63,92 @@ impl VertexArray {
                 instanced_buffer.get_stride(),
             );
 
-            for i in 0..4 {
+            // four columns of the model matrix followed by the color
+            for i in 0..5 {
                 gl::EnableVertexArrayAttrib(id, index);
                 gl::VertexArrayAttribBinding(id, index, binding_index);
                 gl::VertexArrayAttribFormat(id, index, 4, gl::FLOAT, gl::FALSE, i * 16);
                 index += 1;
             }
+            // and the material index, shadow flag and entity id
+            for i in 0..3 {
+                gl::EnableVertexArrayAttrib(id, index);
+                gl::VertexArrayAttribBinding(id, index, binding_index);
+                gl::VertexArrayAttribIFormat(id, index, 1, gl::UNSIGNED_INT, 5 * 16 + i * 4);
+                index += 1;
+            }
             gl::VertexArrayBindingDivisor(id, binding_index, 1);
 
             Self {
                 id,
                 index_size: mesh.index_buffer.count,
                 instanced_buffer,
+                instance_binding: binding_index,
             }
         }
     }
 
+    /// Number of instances that fit in the instance buffer.
+    #[inline]
+    pub fn get_instance_capacity(&self) -> usize {
+        self.instanced_buffer.get_capacity() as usize / size_of::<Instance>()
+    }
+
+    /// Grows the instance buffer, at least doubling it, so `count` instances
+    /// fit. The first `keep` instances are preserved.
+    pub fn reserve_instances(&mut self, count: usize, keep: usize) {
+        let capacity = self.get_instance_capacity();
+        if count <= capacity {
+            return;
+        }
+        let capacity = count.max(capacity * 2);
+        self.instanced_buffer.resize(
+            (capacity * size_of::<Instance>()) as isize,
+            (keep * size_of::<Instance>()) as isize,
+        );
+        unsafe {
+            gl::VertexArrayVertexBuffer(
+                self.id,
+                self.instance_binding,
+                self.instanced_buffer.get_id(),
+                0,
+                self.instanced_buffer.get_stride(),
+            );
+        }
+    }
+
+    /// Points the instance attributes at `buffer`, starting at `offset` bytes.
+    pub fn bind_instances(&self, buffer: GLuint, offset: isize) {
+        unsafe {
+            gl::VertexArrayVertexBuffer(
+                self.id,
+                self.instance_binding,
+                buffer,
+                offset,
+                size_of::<Instance>() as i32,
+            );
+        }
+    }
+
     #[inline]
     pub fn get_index_size(&self) -> i32 {
-        return self.index_size;
+        self.index_size
     }
 
     #[inline]
     pub fn bind(&self) {
-        unsafe {
-            if BINDED_ID != self.id {
-                gl::BindVertexArray(self.id);
-                BINDED_ID = self.id;
-            }
+        bind_id(self.id);
+    }
+}
+
+/// Binds the vertex array `id`, which may not belong to a [`VertexArray`],
+/// keeping track of the bound one.
+#[inline]
+pub fn bind_id(id: GLuint) {
+    unsafe {
+        if BINDED_ID != id {
+            gl::BindVertexArray(id);
+            BINDED_ID = id;
         }
     }
 }
@@ -1,7 +1,10 @@
-use std::marker::PhantomData;
+use std::{fmt, marker::PhantomData};
 
 use gl::types::GLuint;
 
+use crate::glcall;
+
+#[allow(dead_code)]
 pub trait Vertex {
     fn size(&self) -> u32;
 }
@@ -17,9 +20,31 @@ pub struct Buffer<T: Buf> {
     id: GLuint,
     element_count: i32,
     stride: i32,
+    /// Size in bytes.
+    capacity: isize,
     t_buf_type: PhantomData<T>,
 }
 
+/// A write that does not fit in the buffer, in bytes.
+#[derive(Debug)]
+pub struct BufferOverflow {
+    pub offset: isize,
+    pub size: isize,
+    pub capacity: isize,
+}
+
+impl fmt::Display for BufferOverflow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "writing {} bytes at offset {} overflows a buffer of {} bytes",
+            self.size, self.offset, self.capacity
+        )
+    }
+}
+
+impl std::error::Error for BufferOverflow {}
+
 impl<T: Buf> Drop for Buffer<T> {
     fn drop(&mut self) {
         unsafe {
@@ -44,6 +69,11 @@ impl<T: Buf> Buffer<T> {
     pub fn get_stride(&self) -> i32 {
         self.stride
     }
+
+    #[inline(always)]
+    pub fn get_capacity(&self) -> isize {
+        self.capacity
+    }
 }
 
 impl Buffer<Static> {
@@ -51,48 +81,69 @@ impl Buffer<Static> {
         let mut id = 0;
         let stride = std::mem::size_of::<T>() as i32 * N as i32;
         let element_count = N as i32;
+        let capacity = std::mem::size_of_val(data) as isize;
         unsafe {
             gl::CreateBuffers(1, &mut id);
-            gl::NamedBufferData(
-                id,
-                std::mem::size_of_val(data) as isize,
-                data.as_ptr().cast(),
-                gl::STATIC_DRAW,
-            );
+            gl::NamedBufferData(id, capacity, data.as_ptr().cast(), gl::STATIC_DRAW);
         }
         Self {
             id,
             element_count,
             stride,
+            capacity,
             t_buf_type: PhantomData,
         }
     }
 }
 
 impl Buffer<Dynamic> {
-    pub fn set_data<T>(&self, data: &[T], offset: isize) -> isize {
-        let byte_length = (std::mem::size_of::<T>() * data.len()) as isize;
+    /// Writes `data` at `offset` bytes, returning the number of bytes written.
+    pub fn set_data<T>(&self, data: &[T], offset: isize) -> Result<isize, BufferOverflow> {
+        let byte_length = std::mem::size_of_val(data) as isize;
+        if offset < 0 || offset + byte_length > self.capacity {
+            return Err(BufferOverflow {
+                offset,
+                size: byte_length,
+                capacity: self.capacity,
+            });
+        }
         unsafe { gl::NamedBufferSubData(self.get_id(), offset, byte_length, data.as_ptr().cast()) }
-        byte_length
+        Ok(byte_length)
     }
 
     pub fn instanced<T>(instance_count: isize) -> Self {
+        let stride = std::mem::size_of::<T>() as i32;
+        Self {
+            id: Self::allocate(instance_count * stride as isize),
+            element_count: stride / std::mem::size_of::<f32>() as i32,
+            stride,
+            capacity: instance_count * stride as isize,
+            t_buf_type: PhantomData,
+        }
+    }
+
+    /// Moves the content to a new buffer of `capacity` bytes, copying the
+    /// first `keep` bytes on the GPU. The buffer id changes, so vertex arrays
+    /// using it have to be pointed at the new one.
+    pub fn resize(&mut self, capacity: isize, keep: isize) {
+        let id = Self::allocate(capacity);
+        let keep = keep.clamp(0, self.capacity.min(capacity));
+        unsafe {
+            if keep > 0 {
+                glcall!(gl::CopyNamedBufferSubData(self.id, id, 0, 0, keep));
+            }
+            gl::DeleteBuffers(1, &self.id);
+        }
+        self.id = id;
+        self.capacity = capacity;
+    }
+
+    fn allocate(capacity: isize) -> GLuint {
         let mut id = 0;
         unsafe {
             gl::CreateBuffers(1, &mut id);
-            gl::NamedBufferData(
-                id,
-                instance_count * std::mem::size_of::<T>() as isize,
-                std::ptr::null(),
-                gl::DYNAMIC_DRAW,
-            );
-            let stride = std::mem::size_of::<T>() as i32;
-            Self {
-                id,
-                element_count: stride / std::mem::size_of::<f32>() as i32,
-                stride,
-                t_buf_type: PhantomData,
-            }
+            gl::NamedBufferData(id, capacity, std::ptr::null(), gl::DYNAMIC_DRAW);
         }
+        id
     }
 }
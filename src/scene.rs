@@ -1,51 +1,378 @@
-use glam::Vec3;
+use std::collections::HashMap;
 
-use crate::{camera::Camera, entity::Entity};
+use glam::{Mat4, Vec3};
+
+use crate::{
+    asset::AssetNames,
+    bounds::{Aabb, Frustum, Ray, Sphere},
+    camera::Camera,
+    clock::Clock,
+    debug::DebugDraw,
+    entity::{Entity, EntityId},
+    event::{Despawned, Events, Spawned},
+    light::Light,
+    physics::PhysicsWorld,
+    spatial::{Bvh, IndexKind, SpatialIndex},
+    transform::Transform,
+};
+
+pub use self::{
+    format::Component,
+    generator::Generator,
+    history::{Command, History},
+};
+
+mod format;
+mod generator;
+mod history;
+
+#[derive(Clone, Copy)]
+struct Location {
+    is_static: bool,
+    index: usize,
+}
 
 pub struct Scene {
     camera: Camera,
+    assets: AssetNames,
+    physics: PhysicsWorld,
     static_entity_list: Vec<Entity>,
     dynamic_entity_list: Vec<Entity>,
+    locations: HashMap<EntityId, Location>,
+    next_id: u32,
+    /// Bumped whenever static entities change in a way other than being appended,
+    /// so the renderer knows its uploaded copy is stale.
+    static_version: u64,
+    static_index: Bvh,
+    /// `static_version` and static entity count the static index was built for.
+    static_indexed: (u64, usize),
+    dynamic_index: Box<dyn SpatialIndex>,
+    dynamic_index_kind: IndexKind,
+    events: Events,
+    debug: DebugDraw,
 }
 
 impl Scene {
+    pub const DEFAULT_DYNAMIC_INDEX: IndexKind = IndexKind::Grid { cell_size: 16 };
+
     pub fn new() -> Self {
-        let camera = Camera::new();
+        Self::with_camera(Camera::new(), AssetNames::new())
+    }
+
+    fn with_camera(camera: Camera, assets: AssetNames) -> Self {
         Self {
             camera,
+            assets,
+            physics: PhysicsWorld::new(),
             static_entity_list: vec![],
             dynamic_entity_list: vec![],
+            locations: HashMap::new(),
+            next_id: 0,
+            static_version: 0,
+            static_index: Bvh::new(),
+            static_indexed: (0, 0),
+            dynamic_index: Self::DEFAULT_DYNAMIC_INDEX.create(),
+            dynamic_index_kind: Self::DEFAULT_DYNAMIC_INDEX,
+            events: Events::new(),
+            debug: DebugDraw::new(),
         }
     }
 
-    pub fn update(&mut self) {
+    /// Advances animations and physics by the frame time of `clock`.
+    ///
+    /// Also starts a new frame of events: everything sent before the previous
+    /// call is dropped, so each stage gets one frame to read what others sent.
+    /// Likewise debug shapes drawn last frame expire, unless given longer.
+    pub fn update(&mut self, clock: &Clock) {
+        self.events.update();
+        self.debug.update(clock.get_real_delta());
+
+        let dt = clock.get_delta();
         for e in self.dynamic_entity_list.iter_mut() {
-            e.get_mut_transform().update_pos(Vec3 {
-                x: 0.01,
-                y: 0.,
-                z: 0.005,
-            });
-        }
-    }
-
-    pub fn add_entities(&mut self, count: i32, is_static: bool) {
-        let padding = 5;
-        for i in -(count / 2)..(count / 2) {
-            for j in -(count / 2)..(count / 2) {
-                for k in -(count / 2)..(count / 2) {
-                    let pos = Vec3::new(
-                        (i * padding) as f32,
-                        (j * padding) as f32,
-                        (k * padding) as f32,
-                    );
-                    if is_static {
-                        self.static_entity_list.push(Entity::new(pos));
-                    } else {
-                        self.dynamic_entity_list.push(Entity::new(pos));
-                    }
+            e.update_animation(dt);
+        }
+
+        for e in self
+            .dynamic_entity_list
+            .iter_mut()
+            .filter(|e| e.get_rigid_body().is_none() && e.get_animation().is_none())
+        {
+            e.get_mut_transform().update_pos(
+                Vec3 {
+                    x: 0.6,
+                    y: 0.,
+                    z: 0.3,
+                } * dt,
+            );
+        }
+
+        self.physics.simulate(
+            clock.get_fixed_steps(),
+            &mut self.dynamic_entity_list,
+            &self.static_entity_list,
+        );
+        for contact in self.physics.get_contacts() {
+            self.events.send(*contact);
+        }
+
+        self.update_indices();
+    }
+
+    /// Refits the dynamic index to where entities moved and rebuilds the
+    /// static one if static entities were added or changed.
+    fn update_indices(&mut self) {
+        let moved: Vec<_> = self
+            .dynamic_entity_list
+            .iter()
+            .map(|e| (e.get_id(), self.get_world_bounds(e)))
+            .collect();
+        for (id, aabb) in moved {
+            self.dynamic_index.update(id, aabb);
+        }
+        self.dynamic_index.rebalance();
+
+        let indexed = (self.static_version, self.static_entity_list.len());
+        if self.static_indexed != indexed {
+            let items: Vec<_> = self
+                .static_entity_list
+                .iter()
+                .map(|e| (e.get_id(), self.get_world_bounds(e)))
+                .collect();
+            self.static_index.rebuild(&items);
+            self.static_indexed = indexed;
+        }
+    }
+
+    /// Switches the structure used for dynamic entities.
+    pub fn set_dynamic_index(&mut self, kind: IndexKind) {
+        let items: Vec<_> = self
+            .dynamic_entity_list
+            .iter()
+            .map(|e| (e.get_id(), self.get_world_bounds(e)))
+            .collect();
+        self.dynamic_index = kind.create();
+        self.dynamic_index.rebuild(&items);
+        self.dynamic_index_kind = kind;
+    }
+
+    /// Spawns one entity at every position produced by `generator` for `seed`.
+    pub fn add_entities(&mut self, generator: &Generator, seed: u64, is_static: bool) {
+        for pos in generator.generate(seed) {
+            self.spawn(Transform::with_pos(pos), is_static);
+        }
+    }
+
+    pub fn spawn(&mut self, transform: Transform, is_static: bool) -> EntityId {
+        let id = EntityId(self.next_id);
+        self.insert(Entity::with_transform(id, transform), is_static);
+        id
+    }
+
+    /// Inserts an entity that already carries its id, e.g. one read from a file.
+    fn insert(&mut self, entity: Entity, is_static: bool) {
+        let id = entity.get_id();
+        self.next_id = self.next_id.max(id.0 + 1);
+        self.place(entity, is_static);
+        self.events.send(Spawned(id));
+    }
+
+    /// Adds `entity` to the static or dynamic list and indexes it.
+    fn place(&mut self, entity: Entity, is_static: bool) {
+        let id = entity.get_id();
+        if !is_static {
+            let aabb = self.get_world_bounds(&entity);
+            self.dynamic_index.insert(id, aabb);
+        }
+        let list = if is_static {
+            &mut self.static_entity_list
+        } else {
+            &mut self.dynamic_entity_list
+        };
+        self.locations.insert(
+            id,
+            Location {
+                is_static,
+                index: list.len(),
+            },
+        );
+        list.push(entity);
+    }
+
+    /// Takes entity `id` out of its list and index, leaving its children
+    /// attached.
+    fn take(&mut self, id: EntityId) -> Option<Entity> {
+        let location = self.locations.remove(&id)?;
+        self.dynamic_index.remove(id);
+        let list = if location.is_static {
+            self.static_version += 1;
+            &mut self.static_entity_list
+        } else {
+            &mut self.dynamic_entity_list
+        };
+        let entity = list.swap_remove(location.index);
+        if let Some(moved) = list.get(location.index) {
+            self.locations.insert(moved.get_id(), location);
+        }
+        Some(entity)
+    }
+
+    /// Moves entity `id` to the static or dynamic entities, keeping its id and
+    /// children. Returns false if there is no such entity.
+    pub fn set_static(&mut self, id: EntityId, is_static: bool) -> bool {
+        match self.is_static(id) {
+            Some(current) if current == is_static => true,
+            Some(_) => {
+                let entity = self.take(id).expect("Entity has a location");
+                self.place(entity, is_static);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes an entity, detaching its children.
+    pub fn despawn(&mut self, id: EntityId) -> Option<Entity> {
+        let entity = self.take(id)?;
+
+        for child in self
+            .dynamic_entity_list
+            .iter_mut()
+            .filter(|e| e.get_parent() == Some(id))
+        {
+            child.set_parent(None);
+        }
+        for child in self
+            .static_entity_list
+            .iter_mut()
+            .filter(|e| e.get_parent() == Some(id))
+        {
+            child.set_parent(None);
+            self.static_version += 1;
+        }
+        self.events.send(Despawned(id));
+        Some(entity)
+    }
+
+    #[inline]
+    pub fn get_entity(&self, id: EntityId) -> Option<&Entity> {
+        let location = self.locations.get(&id)?;
+        if location.is_static {
+            self.static_entity_list.get(location.index)
+        } else {
+            self.dynamic_entity_list.get(location.index)
+        }
+    }
+
+    /// Mutable access to a single entity. Touching a static entity through
+    /// this forces the renderer to upload the static set again.
+    #[inline]
+    pub fn get_mut_entity(&mut self, id: EntityId) -> Option<&mut Entity> {
+        let location = *self.locations.get(&id)?;
+        if location.is_static {
+            self.static_version += 1;
+            self.static_entity_list.get_mut(location.index)
+        } else {
+            self.dynamic_entity_list.get_mut(location.index)
+        }
+    }
+
+    #[inline]
+    pub fn is_static(&self, id: EntityId) -> Option<bool> {
+        self.locations.get(&id).map(|l| l.is_static)
+    }
+
+    /// Attaches `child` to `parent`. Returns false if either entity is missing
+    /// or the new link would make `child` its own ancestor.
+    pub fn set_parent(&mut self, child: EntityId, parent: Option<EntityId>) -> bool {
+        if let Some(parent) = parent {
+            let mut ancestor = Some(parent);
+            while let Some(id) = ancestor {
+                if id == child {
+                    return false;
+                }
+                match self.get_entity(id) {
+                    Some(e) => ancestor = e.get_parent(),
+                    None => return false,
                 }
             }
         }
+        match self.get_mut_entity(child) {
+            Some(entity) => {
+                entity.set_parent(parent);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Model matrix of `entity` with all of its ancestors applied.
+    pub fn get_world_matrix(&self, entity: &Entity) -> Mat4 {
+        let mut matrix = entity.get_transform().get_matrix();
+        let mut parent = entity.get_parent();
+        while let Some(e) = parent.and_then(|id| self.get_entity(id)) {
+            matrix = e.get_transform().get_matrix() * matrix;
+            parent = e.get_parent();
+        }
+        matrix
+    }
+
+    /// World space bounds of the entity's mesh.
+    pub fn get_world_bounds(&self, entity: &Entity) -> Aabb {
+        self.assets
+            .get_mesh_bounds(entity.get_mesh())
+            .transformed(&self.get_world_matrix(entity))
+    }
+
+    /// Sets the local bounds of the mesh called `name`, as the renderer
+    /// loaded it. Static entities are indexed again on the next update.
+    pub fn set_mesh_bounds(&mut self, name: &str, bounds: Aabb) {
+        self.assets.set_mesh_bounds(name, bounds);
+        self.static_indexed = (u64::MAX, 0);
+    }
+
+    /// Queries see entity positions as of the last [`Scene::update`].
+    pub fn query_aabb(&self, aabb: &Aabb) -> Vec<EntityId> {
+        let mut out = vec![];
+        self.static_index.query_aabb(aabb, &mut out);
+        self.dynamic_index.query_aabb(aabb, &mut out);
+        out
+    }
+
+    pub fn query_sphere(&self, sphere: &Sphere) -> Vec<EntityId> {
+        let mut out = vec![];
+        self.static_index.query_sphere(sphere, &mut out);
+        self.dynamic_index.query_sphere(sphere, &mut out);
+        out
+    }
+
+    pub fn query_frustum(&self, frustum: &Frustum) -> Vec<EntityId> {
+        let mut out = vec![];
+        self.static_index.query_frustum(frustum, &mut out);
+        self.dynamic_index.query_frustum(frustum, &mut out);
+        out
+    }
+
+    /// Closest entity whose bounds are hit by `ray` within `max_distance`.
+    pub fn raycast(&self, ray: &Ray, max_distance: f32) -> Option<(EntityId, f32)> {
+        let mut hits = vec![];
+        self.static_index.query_ray(ray, max_distance, &mut hits);
+        self.dynamic_index.query_ray(ray, max_distance, &mut hits);
+        hits.into_iter().min_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    #[inline]
+    pub fn get_dynamic_index(&self) -> &dyn SpatialIndex {
+        self.dynamic_index.as_ref()
+    }
+
+    #[inline]
+    pub fn get_dynamic_index_kind(&self) -> IndexKind {
+        self.dynamic_index_kind
+    }
+
+    #[inline]
+    pub fn get_camera(&self) -> &Camera {
+        &self.camera
     }
 
     #[inline]
@@ -54,12 +381,91 @@ impl Scene {
     }
 
     #[inline]
-    pub fn get_mut_static_entities(&mut self) -> &mut Vec<Entity> {
-        &mut self.static_entity_list
+    pub fn get_physics(&self) -> &PhysicsWorld {
+        &self.physics
+    }
+
+    /// Event queues shared by the scene systems. Contacts from the physics
+    /// step are sent as [`Contact`](crate::physics::Contact) events.
+    #[inline]
+    pub fn get_events(&self) -> &Events {
+        &self.events
     }
 
     #[inline]
-    pub fn get_dynamic_entities(&mut self) -> &Vec<Entity> {
+    pub fn get_mut_events(&mut self) -> &mut Events {
+        &mut self.events
+    }
+
+    /// Debug shapes drawn over the scene this frame.
+    #[inline]
+    pub fn get_debug(&self) -> &DebugDraw {
+        &self.debug
+    }
+
+    #[inline]
+    pub fn get_mut_debug(&mut self) -> &mut DebugDraw {
+        &mut self.debug
+    }
+
+    #[inline]
+    pub fn get_assets(&self) -> &AssetNames {
+        &self.assets
+    }
+
+    #[inline]
+    pub fn get_mut_assets(&mut self) -> &mut AssetNames {
+        &mut self.assets
+    }
+
+    #[inline]
+    pub fn get_static_entities(&self) -> &[Entity] {
+        &self.static_entity_list
+    }
+
+    #[inline]
+    pub fn get_static_version(&self) -> u64 {
+        self.static_version
+    }
+
+    #[inline]
+    pub fn get_dynamic_entities(&self) -> &[Entity] {
         &self.dynamic_entity_list
     }
+
+    /// Entities emitting light, with their light.
+    pub fn get_lights(&self) -> impl Iterator<Item = (&Entity, &Light)> {
+        self.static_entity_list
+            .iter()
+            .chain(self.dynamic_entity_list.iter())
+            .filter_map(|e| Some((e, e.get_light()?)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn queries_use_mesh_bounds() {
+        let mut scene = Scene::new();
+        let mesh = scene.get_mut_assets().mesh("wide");
+        scene.set_mesh_bounds("wide", Aabb::new(Vec3::splat(-4.), Vec3::splat(4.)));
+        for (x, is_static) in [(10., true), (-10., false)] {
+            let id = scene.spawn(Transform::with_pos(Vec3::new(x, 0., 0.)), is_static);
+            scene.get_mut_entity(id).unwrap().set_mesh(mesh);
+        }
+        scene.update(&Clock::new());
+
+        let entity = &scene.get_static_entities()[0];
+        assert_eq!(
+            scene.get_world_bounds(entity),
+            Aabb::new(Vec3::new(6., -4., -4.), Vec3::new(14., 4., 4.))
+        );
+        let near = |x: f32| Aabb::from_center_half_extents(Vec3::new(x, 3., 0.), Vec3::ONE);
+        assert_eq!(scene.query_aabb(&near(7.)).len(), 1);
+        assert_eq!(scene.query_aabb(&near(-7.)).len(), 1);
+        let hit = scene.raycast(&Ray::new(Vec3::ZERO, Vec3::X), 100.);
+        assert_eq!(hit.map(|(_, t)| t), Some(6.));
+    }
 }
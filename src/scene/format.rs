@@ -0,0 +1,764 @@
+//! On-disk representation of a [`Scene`].
+//!
+//! A scene is stored either as RON text or as a compact binary blob. Both
+//! encode the same [`SceneFile`] structure; the text form looks like this:
+//!
+//! ```text
+//! (
+//!     version: 2,
+//!     camera: (position: (0.0, 0.0, 5.0), up: (0.0, 1.0, 0.0), pitch: 0.0, yaw: -90.0),
+//!     meshes: ["cube"],
+//!     materials: ["default"],
+//!     clips: [],
+//!     entities: [
+//!         (
+//!             id: 0,
+//!             parent: None,
+//!             is_static: false,
+//!             transform: (
+//!                 position: (0.0, 0.0, 0.0),
+//!                 rotation: (0.0, 0.0, 0.0, 1.0),
+//!                 scale: (1.0, 1.0, 1.0),
+//!             ),
+//!             mesh: 0,
+//!             material: 0,
+//!             components: [Name("root")],
+//!         ),
+//!     ],
+//! )
+//! ```
+//!
+//! `mesh` and `material` index into the `meshes` and `materials` name tables,
+//! `parent` refers to another entity's `id` and rotations are quaternions in
+//! `(x, y, z, w)` order. Animation clips are stored once in `clips` and
+//! animation components refer to them by index, so entities sharing a clip
+//! still share it after loading.
+//!
+//! The binary form is the magic bytes `RGLS`, the format version as a little
+//! endian `u32`, and then the same structure encoded with `bincode`. Files
+//! whose header and body disagree on the version are rejected.
+//!
+//! # Versioning
+//!
+//! [`FORMAT_VERSION`] is written into every file and files with any version up
+//! to it can be read. Optional per-entity data lives in the `components` list,
+//! so new component kinds are added as new [`Component`] variants at the end of
+//! the enum and leave older files valid in both encodings. Changing the layout
+//! of anything that already exists requires bumping the version and keeping
+//! the previous structure around to upgrade from.
+//!
+//! Version 1 stored a copy of its clip in every animation component.
+
+use std::{collections::HashSet, fmt, fs, path::Path, sync::Arc};
+
+use glam::{Quat, Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    animation::{AnimationClip, AnimationPlayer, LoopMode},
+    asset::{AssetNames, MaterialId, MeshId},
+    camera::Camera,
+    entity::{Entity, EntityId},
+    light::Light,
+    physics::{Collider, RigidBody},
+    transform::Transform,
+};
+
+use super::Scene;
+
+pub const FORMAT_VERSION: u32 = 2;
+const MAGIC: &[u8; 4] = b"RGLS";
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct SceneFile {
+    pub version: u32,
+    pub camera: CameraData,
+    pub meshes: Vec<String>,
+    pub materials: Vec<String>,
+    pub clips: Vec<AnimationClip>,
+    pub entities: Vec<EntityData>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraData {
+    pub position: Vec3,
+    pub up: Vec3,
+    pub pitch: f64,
+    pub yaw: f64,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct EntityData {
+    pub id: u32,
+    pub parent: Option<u32>,
+    pub is_static: bool,
+    pub transform: TransformData,
+    pub mesh: u32,
+    pub material: u32,
+    pub components: Vec<Component>,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct TransformData {
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+/// Optional data attached to an entity. Only append new variants.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Component {
+    Name(String),
+    RigidBody(RigidBody),
+    Collider(Collider),
+    Color(Vec4),
+    Animation(AnimationData),
+    Light(Light),
+}
+
+impl Component {
+    /// Attaches the component to `entity`, replacing any previous one.
+    /// Animations refer to a clip of `clips` and are skipped if it does not
+    /// exist.
+    pub fn apply(&self, entity: &mut Entity, clips: &[Arc<AnimationClip>]) {
+        match self {
+            Component::Name(name) => entity.set_name(Some(name.clone())),
+            Component::RigidBody(body) => entity.set_rigid_body(Some(*body)),
+            Component::Collider(collider) => entity.set_collider(Some(*collider)),
+            Component::Color(color) => entity.set_color(*color),
+            Component::Animation(animation) => {
+                if let Some(clip) = clips.get(animation.clip as usize) {
+                    entity.set_animation(Some(animation.to_player(clip.clone())));
+                }
+            }
+            Component::Light(light) => entity.set_light(Some(*light)),
+        }
+    }
+}
+
+/// State of an [`AnimationPlayer`], with its clip as an index into the
+/// file's `clips`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AnimationData {
+    pub clip: u32,
+    pub loop_mode: LoopMode,
+    pub speed: f32,
+    pub playing: bool,
+    pub time: f32,
+}
+
+impl AnimationData {
+    pub fn from_player(player: &AnimationPlayer, clip: u32) -> Self {
+        Self {
+            clip,
+            loop_mode: player.loop_mode,
+            speed: player.speed,
+            playing: player.playing,
+            time: player.get_time(),
+        }
+    }
+
+    pub fn to_player(&self, clip: Arc<AnimationClip>) -> AnimationPlayer {
+        let mut player = AnimationPlayer::new(clip, self.loop_mode).with_time(self.time);
+        player.speed = self.speed;
+        player.playing = self.playing;
+        player
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Encoding {
+    Text,
+    Binary,
+}
+
+impl Encoding {
+    /// `.ron` files are text, everything else is binary.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("ron") => Encoding::Text,
+            _ => Encoding::Binary,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SceneError {
+    Io(std::io::Error),
+    TextEncode(ron::Error),
+    TextDecode(ron::error::SpannedError),
+    Binary(bincode::Error),
+    UnsupportedVersion(u32),
+    Invalid(String),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(e) => write!(f, "io error: {e}"),
+            SceneError::TextEncode(e) => write!(f, "cannot write scene text: {e}"),
+            SceneError::TextDecode(e) => write!(f, "cannot parse scene text: {e}"),
+            SceneError::Binary(e) => write!(f, "cannot decode binary scene: {e}"),
+            SceneError::UnsupportedVersion(v) => write!(
+                f,
+                "scene format version {v} is newer than the supported version {FORMAT_VERSION}"
+            ),
+            SceneError::Invalid(reason) => write!(f, "invalid scene: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for SceneError {}
+
+impl From<std::io::Error> for SceneError {
+    fn from(e: std::io::Error) -> Self {
+        SceneError::Io(e)
+    }
+}
+
+impl From<ron::Error> for SceneError {
+    fn from(e: ron::Error) -> Self {
+        SceneError::TextEncode(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for SceneError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        SceneError::TextDecode(e)
+    }
+}
+
+impl From<bincode::Error> for SceneError {
+    fn from(e: bincode::Error) -> Self {
+        SceneError::Binary(e)
+    }
+}
+
+impl Scene {
+    /// Writes the scene to `path`, picking the encoding from the extension.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SceneError> {
+        let path = path.as_ref();
+        let bytes = match Encoding::from_path(path) {
+            Encoding::Text => self.to_text()?.into_bytes(),
+            Encoding::Binary => self.to_bytes()?,
+        };
+        fs::write(path, bytes)?;
+        Ok(())
+    }
+
+    /// Reads a scene written by [`Scene::save`]. Binary files are recognized by
+    /// their magic bytes, so the extension does not matter.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SceneError> {
+        let bytes = fs::read(path)?;
+        if bytes.starts_with(MAGIC) {
+            Self::from_bytes(&bytes)
+        } else {
+            let text = String::from_utf8(bytes)
+                .map_err(|_| SceneError::Invalid("text scene is not valid utf8".to_owned()))?;
+            Self::from_text(&text)
+        }
+    }
+
+    pub fn to_text(&self) -> Result<String, SceneError> {
+        let config = ron::ser::PrettyConfig::default().struct_names(false);
+        Ok(ron::ser::to_string_pretty(&self.to_file(), config)?)
+    }
+
+    pub fn from_text(text: &str) -> Result<Self, SceneError> {
+        let file = match ron::from_str::<VersionHeader>(text)?.version {
+            1 => ron::from_str::<SceneFileV1>(text)?.upgrade(),
+            v if v > FORMAT_VERSION => return Err(SceneError::UnsupportedVersion(v)),
+            _ => ron::from_str(text)?,
+        };
+        Self::from_file(file)
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, SceneError> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bincode::serialize_into(&mut bytes, &self.to_file())?;
+        Ok(bytes)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SceneError> {
+        let header = bytes
+            .get(..8)
+            .filter(|h| h.starts_with(MAGIC))
+            .ok_or_else(|| SceneError::Invalid("missing binary scene header".to_owned()))?;
+        let version = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        let (body_version, file) = match version {
+            1 => {
+                let file = bincode::deserialize::<SceneFileV1>(&bytes[8..])?;
+                (file.version, file.upgrade())
+            }
+            2 => {
+                let file: SceneFile = bincode::deserialize(&bytes[8..])?;
+                (file.version, file)
+            }
+            v => return Err(SceneError::UnsupportedVersion(v)),
+        };
+        if body_version != version {
+            return Err(SceneError::Invalid(format!(
+                "header version {version} does not match body version {body_version}"
+            )));
+        }
+        Self::from_file(file)
+    }
+
+    pub fn to_file(&self) -> SceneFile {
+        let camera = self.get_camera();
+        // Every shared clip is written once.
+        let mut clips: Vec<&Arc<AnimationClip>> = vec![];
+        let entities = self
+            .get_static_entities()
+            .iter()
+            .map(|e| (e, true))
+            .chain(self.get_dynamic_entities().iter().map(|e| (e, false)))
+            .map(|(e, is_static)| {
+                let transform = e.get_transform();
+                let mut components = vec![];
+                if let Some(name) = e.get_name() {
+                    components.push(Component::Name(name.to_owned()));
+                }
+                if let Some(body) = e.get_rigid_body() {
+                    components.push(Component::RigidBody(*body));
+                }
+                if let Some(collider) = e.get_collider() {
+                    components.push(Component::Collider(*collider));
+                }
+                if e.get_color() != Vec4::ONE {
+                    components.push(Component::Color(e.get_color()));
+                }
+                if let Some(animation) = e.get_animation() {
+                    let clip = animation.get_clip();
+                    let index = match clips.iter().position(|c| Arc::ptr_eq(c, clip)) {
+                        Some(index) => index,
+                        None => {
+                            clips.push(clip);
+                            clips.len() - 1
+                        }
+                    };
+                    components.push(Component::Animation(AnimationData::from_player(
+                        animation,
+                        index as u32,
+                    )));
+                }
+                if let Some(light) = e.get_light() {
+                    components.push(Component::Light(*light));
+                }
+                EntityData {
+                    id: e.get_id().0,
+                    parent: e.get_parent().map(|p| p.0),
+                    is_static,
+                    transform: TransformData {
+                        position: transform.get_position(),
+                        rotation: transform.get_rotation(),
+                        scale: transform.get_scale(),
+                    },
+                    mesh: e.get_mesh().0,
+                    material: e.get_material().0,
+                    components,
+                }
+            })
+            .collect();
+
+        SceneFile {
+            version: FORMAT_VERSION,
+            camera: CameraData {
+                position: camera.position,
+                up: camera.up,
+                pitch: camera.get_pitch(),
+                yaw: camera.get_yaw(),
+            },
+            meshes: self.get_assets().get_meshes().to_vec(),
+            materials: self.get_assets().get_materials().to_vec(),
+            clips: clips.into_iter().map(|c| (**c).clone()).collect(),
+            entities,
+        }
+    }
+
+    pub fn from_file(file: SceneFile) -> Result<Self, SceneError> {
+        if file.version > FORMAT_VERSION {
+            return Err(SceneError::UnsupportedVersion(file.version));
+        }
+
+        let camera = Camera::with_orientation(
+            file.camera.position,
+            file.camera.up,
+            file.camera.pitch,
+            file.camera.yaw,
+        );
+        let mesh_count = file.meshes.len().max(1) as u32;
+        let material_count = file.materials.len().max(1) as u32;
+        let assets = AssetNames::from_names(file.meshes, file.materials);
+        let mut scene = Scene::with_camera(camera, assets);
+        let clips: Vec<_> = file.clips.into_iter().map(Arc::new).collect();
+
+        let mut ids = HashSet::new();
+        for data in file.entities.iter() {
+            if !ids.insert(data.id) {
+                return Err(SceneError::Invalid(format!(
+                    "duplicate entity id {}",
+                    data.id
+                )));
+            }
+            if data.mesh >= mesh_count || data.material >= material_count {
+                return Err(SceneError::Invalid(format!(
+                    "entity {} references an unknown mesh or material",
+                    data.id
+                )));
+            }
+            let unknown_clip = data
+                .components
+                .iter()
+                .any(|c| matches!(c, Component::Animation(a) if a.clip as usize >= clips.len()));
+            if unknown_clip {
+                return Err(SceneError::Invalid(format!(
+                    "entity {} references an unknown animation clip",
+                    data.id
+                )));
+            }
+        }
+
+        for data in file.entities.iter() {
+            let t = &data.transform;
+            let mut entity = Entity::with_transform(
+                EntityId(data.id),
+                Transform::new(t.position, t.rotation.normalize(), t.scale),
+            );
+            entity.set_mesh(MeshId(data.mesh));
+            entity.set_material(MaterialId(data.material));
+            for component in data.components.iter() {
+                component.apply(&mut entity, &clips);
+            }
+            scene.insert(entity, data.is_static);
+        }
+
+        // Parents are linked last so they may appear after their children, and
+        // so `set_parent` can reject cycles.
+        for data in file.entities.iter() {
+            if let Some(parent) = data.parent {
+                if !scene.set_parent(EntityId(data.id), Some(EntityId(parent))) {
+                    return Err(SceneError::Invalid(format!(
+                        "entity {} has a missing or cyclic parent {parent}",
+                        data.id
+                    )));
+                }
+            }
+        }
+        Ok(scene)
+    }
+}
+
+/// Just the version of a text scene, to pick the structure to parse.
+#[derive(Deserialize)]
+struct VersionHeader {
+    version: u32,
+}
+
+/// Version 1 layout, where animation components held their own clip.
+#[derive(Deserialize)]
+struct SceneFileV1 {
+    version: u32,
+    camera: CameraData,
+    meshes: Vec<String>,
+    materials: Vec<String>,
+    entities: Vec<EntityDataV1>,
+}
+
+#[derive(Deserialize)]
+struct EntityDataV1 {
+    id: u32,
+    parent: Option<u32>,
+    is_static: bool,
+    transform: TransformData,
+    mesh: u32,
+    material: u32,
+    components: Vec<ComponentV1>,
+}
+
+#[derive(Deserialize)]
+enum ComponentV1 {
+    Name(String),
+    RigidBody(RigidBody),
+    Collider(Collider),
+    Color(Vec4),
+    Animation(AnimationPlayerV1),
+    Light(Light),
+}
+
+#[derive(Deserialize)]
+struct AnimationPlayerV1 {
+    clip: AnimationClip,
+    loop_mode: LoopMode,
+    speed: f32,
+    playing: bool,
+    time: f32,
+}
+
+impl SceneFileV1 {
+    /// Moves the clips into the clip table, equal clips becoming one.
+    fn upgrade(self) -> SceneFile {
+        let mut clips: Vec<AnimationClip> = vec![];
+        let entities = self
+            .entities
+            .into_iter()
+            .map(|data| {
+                let components = data
+                    .components
+                    .into_iter()
+                    .map(|component| match component {
+                        ComponentV1::Name(name) => Component::Name(name),
+                        ComponentV1::RigidBody(body) => Component::RigidBody(body),
+                        ComponentV1::Collider(collider) => Component::Collider(collider),
+                        ComponentV1::Color(color) => Component::Color(color),
+                        ComponentV1::Animation(player) => {
+                            let index = match clips.iter().position(|c| *c == player.clip) {
+                                Some(index) => index,
+                                None => {
+                                    clips.push(player.clip);
+                                    clips.len() - 1
+                                }
+                            };
+                            Component::Animation(AnimationData {
+                                clip: index as u32,
+                                loop_mode: player.loop_mode,
+                                speed: player.speed,
+                                playing: player.playing,
+                                time: player.time,
+                            })
+                        }
+                        ComponentV1::Light(light) => Component::Light(light),
+                    })
+                    .collect();
+                EntityData {
+                    id: data.id,
+                    parent: data.parent,
+                    is_static: data.is_static,
+                    transform: data.transform,
+                    mesh: data.mesh,
+                    material: data.material,
+                    components,
+                }
+            })
+            .collect();
+        SceneFile {
+            version: FORMAT_VERSION,
+            camera: self.camera,
+            meshes: self.meshes,
+            materials: self.materials,
+            clips,
+            entities,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Quat, Vec3, Vec4};
+
+    use super::*;
+    use crate::{
+        animation::{Interpolation, Keyframe, Track},
+        light::LightKind,
+    };
+
+    fn clip() -> Arc<AnimationClip> {
+        Arc::new(AnimationClip {
+            position: Some(Track::new(
+                Interpolation::Linear,
+                vec![
+                    Keyframe::new(0., Vec3::ZERO),
+                    Keyframe::new(2., Vec3::new(0., 3., 0.)),
+                ],
+            )),
+            ..Default::default()
+        })
+    }
+
+    /// A parent with a rigid body and collider, a lit child, and two
+    /// entities sharing one animation clip.
+    fn sample() -> Scene {
+        let mut scene = Scene::new();
+        *scene.get_mut_camera() =
+            Camera::with_orientation(Vec3::new(1., 2., 3.), Vec3::Y, 10., -45.);
+
+        let mesh = scene.get_mut_assets().mesh("sphere");
+        let material = scene.get_mut_assets().material("metal");
+        let parent = scene.spawn(
+            Transform::new(Vec3::new(0., 5., 0.), Quat::IDENTITY, Vec3::splat(2.)),
+            false,
+        );
+        let entity = scene.get_mut_entity(parent).unwrap();
+        entity.set_name(Some("parent".to_owned()));
+        entity.set_mesh(mesh);
+        entity.set_material(material);
+        entity.set_rigid_body(Some(RigidBody::new(3.)));
+        entity.set_collider(Some(Collider::Sphere { radius: 0.5 }));
+        entity.set_color(Vec4::new(1., 0., 0., 1.));
+
+        let child = scene.spawn(
+            Transform::new(Vec3::X, Quat::from_xyzw(0., 1., 0., 0.), Vec3::ONE),
+            false,
+        );
+        scene
+            .get_mut_entity(child)
+            .unwrap()
+            .set_light(Some(Light::new(
+                LightKind::Spot {
+                    range: 20.,
+                    inner_angle: 15.,
+                    outer_angle: 30.,
+                },
+                Vec3::new(1., 0.9, 0.8),
+                4.,
+            )));
+        assert!(scene.set_parent(child, Some(parent)));
+
+        let clip = clip();
+        for (x, time) in [(-4., 0.), (4., 1.)] {
+            let id = scene.spawn(Transform::with_pos(Vec3::new(x, 0., 0.)), true);
+            let player = AnimationPlayer::new(clip.clone(), LoopMode::Loop).with_time(time);
+            scene
+                .get_mut_entity(id)
+                .unwrap()
+                .set_animation(Some(player));
+        }
+        scene
+    }
+
+    /// Whether the animated entities of `scene` play the same clip.
+    fn shares_clip(scene: &Scene) -> bool {
+        let clips: Vec<_> = scene
+            .get_static_entities()
+            .iter()
+            .filter_map(|e| e.get_animation())
+            .map(|a| a.get_clip())
+            .collect();
+        clips.len() == 2 && Arc::ptr_eq(clips[0], clips[1])
+    }
+
+    #[test]
+    fn round_trips_as_text() {
+        let scene = sample();
+        let loaded = Scene::from_text(&scene.to_text().unwrap()).unwrap();
+        assert_eq!(loaded.to_file(), scene.to_file());
+        assert!(shares_clip(&loaded));
+    }
+
+    #[test]
+    fn round_trips_as_binary() {
+        let scene = sample();
+        let loaded = Scene::from_bytes(&scene.to_bytes().unwrap()).unwrap();
+        assert_eq!(loaded.to_file(), scene.to_file());
+        assert!(shares_clip(&loaded));
+    }
+
+    #[test]
+    fn writes_shared_clips_once() {
+        let file = sample().to_file();
+        assert_eq!(file.clips.len(), 1);
+    }
+
+    #[test]
+    fn upgrades_version_1_text() {
+        let clip = "(position: Some((interpolation: Linear, keyframes: [\
+                    (time: 0.0, value: (0.0, 0.0, 0.0)), (time: 1.0, value: (0.0, 1.0, 0.0))])), \
+                    rotation: None, scale: None, color: None)";
+        let entity = |id: u32| {
+            format!(
+                "(id: {id}, parent: None, is_static: false, \
+                 transform: (position: (0.0, 0.0, 0.0), rotation: (0.0, 0.0, 0.0, 1.0), \
+                 scale: (1.0, 1.0, 1.0)), mesh: 0, material: 0, components: [Animation((\
+                 clip: {clip}, loop_mode: Loop, speed: 1.0, playing: true, time: 0.5))])"
+            )
+        };
+        let text = format!(
+            "(version: 1, camera: (position: (0.0, 0.0, 5.0), up: (0.0, 1.0, 0.0), \
+             pitch: 0.0, yaw: -90.0), meshes: [\"cube\"], materials: [\"default\"], \
+             entities: [{}, {}])",
+            entity(0),
+            entity(1)
+        );
+        let scene = Scene::from_text(&text).unwrap();
+        let file = scene.to_file();
+        assert_eq!(file.version, FORMAT_VERSION);
+        assert_eq!(file.clips.len(), 1);
+        assert_eq!(scene.get_dynamic_entities().len(), 2);
+    }
+
+    #[test]
+    fn rejects_bad_magic() {
+        let mut bytes = sample().to_bytes().unwrap();
+        bytes[..4].copy_from_slice(b"RGLX");
+        assert!(matches!(
+            Scene::from_bytes(&bytes),
+            Err(SceneError::Invalid(_))
+        ));
+        assert!(Scene::from_bytes(b"RG").is_err());
+    }
+
+    #[test]
+    fn rejects_newer_binary_version() {
+        let mut bytes = sample().to_bytes().unwrap();
+        bytes[4..8].copy_from_slice(&(FORMAT_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            Scene::from_bytes(&bytes),
+            Err(SceneError::UnsupportedVersion(v)) if v == FORMAT_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn rejects_newer_text_version() {
+        let text = sample().to_text().unwrap().replacen(
+            &format!("version: {FORMAT_VERSION}"),
+            &format!("version: {}", FORMAT_VERSION + 1),
+            1,
+        );
+        assert!(matches!(
+            Scene::from_text(&text),
+            Err(SceneError::UnsupportedVersion(v)) if v == FORMAT_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn rejects_mismatched_binary_version() {
+        let mut bytes = sample().to_bytes().unwrap();
+        bytes[4..8].copy_from_slice(&1_u32.to_le_bytes());
+        assert!(Scene::from_bytes(&bytes).is_err());
+
+        let mut file = sample().to_file();
+        file.version = 1;
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+        bincode::serialize_into(&mut bytes, &file).unwrap();
+        assert!(matches!(
+            Scene::from_bytes(&bytes),
+            Err(SceneError::Invalid(_))
+        ));
+    }
+
+    #[test]
+    fn saves_and_loads_both_encodings() {
+        let scene = sample();
+        let dir = std::env::temp_dir();
+        for name in ["format_test.ron", "format_test.scene"] {
+            let path = dir.join(format!("{}-{name}", std::process::id()));
+            scene.save(&path).unwrap();
+            let bytes = fs::read(&path).unwrap();
+            assert_eq!(bytes.starts_with(MAGIC), name.ends_with(".scene"));
+            let loaded = Scene::load(&path).unwrap();
+            fs::remove_file(&path).unwrap();
+            assert_eq!(loaded.to_file(), scene.to_file());
+        }
+    }
+
+    #[test]
+    fn rejects_truncated_binary() {
+        let bytes = sample().to_bytes().unwrap();
+        assert!(matches!(
+            Scene::from_bytes(&bytes[..bytes.len() / 2]),
+            Err(SceneError::Binary(_))
+        ));
+    }
+}
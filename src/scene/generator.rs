@@ -0,0 +1,415 @@
+use std::f32::consts::PI;
+
+use glam::{IVec3, UVec3, Vec3};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+
+/// Describes how a batch of entity positions is laid out in the scene.
+///
+/// Every generator is driven by an explicit seed and uses `ChaCha8Rng`, whose
+/// output is specified independently of platform and pointer width, so the same
+/// seed produces the same scene on every machine.
+pub enum Generator {
+    /// Regular grid centered on `center` with `counts` cells per axis.
+    Grid {
+        counts: UVec3,
+        spacing: Vec3,
+        center: Vec3,
+        /// Random offset applied to each cell, as a fraction of `spacing`.
+        jitter: f32,
+    },
+    /// Uniformly distributed points inside an axis aligned box.
+    ScatterBox { count: usize, min: Vec3, max: Vec3 },
+    /// Uniformly distributed points inside a solid sphere.
+    ScatterSphere {
+        count: usize,
+        center: Vec3,
+        radius: f32,
+    },
+    /// Points inside a box that are never closer than `radius` to each other.
+    PoissonDisk {
+        min: Vec3,
+        max: Vec3,
+        radius: f32,
+        /// Candidates tried around each active point before it is retired.
+        attempts: u32,
+    },
+    /// Points along a spiral rising on the y axis.
+    Spiral {
+        count: usize,
+        center: Vec3,
+        turns: f32,
+        radius: f32,
+        height: f32,
+        jitter: f32,
+    },
+    /// Points on the surface of a sphere, optionally spread over a thickness.
+    SphereShell {
+        count: usize,
+        center: Vec3,
+        radius: f32,
+        thickness: f32,
+    },
+}
+
+impl Generator {
+    /// Cube shaped grid with `count` cells per axis, centered on the origin.
+    pub fn grid(count: u32, spacing: f32) -> Self {
+        Self::Grid {
+            counts: UVec3::splat(count),
+            spacing: Vec3::splat(spacing),
+            center: Vec3::ZERO,
+            jitter: 0.,
+        }
+    }
+
+    pub fn generate(&self, seed: u64) -> Vec<Vec3> {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        match *self {
+            Generator::Grid {
+                counts,
+                spacing,
+                center,
+                jitter,
+            } => grid(&mut rng, counts, spacing, center, jitter),
+            Generator::ScatterBox { count, min, max } => (0..count)
+                .map(|_| random_in_box(&mut rng, min, max))
+                .collect(),
+            Generator::ScatterSphere {
+                count,
+                center,
+                radius,
+            } => (0..count)
+                .map(|_| center + random_in_unit_sphere(&mut rng) * radius)
+                .collect(),
+            Generator::PoissonDisk {
+                min,
+                max,
+                radius,
+                attempts,
+            } => poisson_disk(&mut rng, min, max, radius, attempts),
+            Generator::Spiral {
+                count,
+                center,
+                turns,
+                radius,
+                height,
+                jitter,
+            } => spiral(&mut rng, count, center, turns, radius, height, jitter),
+            Generator::SphereShell {
+                count,
+                center,
+                radius,
+                thickness,
+            } => (0..count)
+                .map(|_| {
+                    let r = radius + rng.gen_range(-0.5..=0.5) * thickness;
+                    center + random_unit_vector(&mut rng) * r
+                })
+                .collect(),
+        }
+    }
+}
+
+fn grid(
+    rng: &mut ChaCha8Rng,
+    counts: UVec3,
+    spacing: Vec3,
+    center: Vec3,
+    jitter: f32,
+) -> Vec<Vec3> {
+    let half = counts.as_ivec3() / 2;
+    let mut positions = Vec::with_capacity(volume(counts));
+    for i in 0..counts.x as i32 {
+        for j in 0..counts.y as i32 {
+            for k in 0..counts.z as i32 {
+                let mut pos = center + (IVec3::new(i, j, k) - half).as_vec3() * spacing;
+                if jitter > 0. {
+                    pos += random_in_box(rng, -Vec3::ONE, Vec3::ONE) * spacing * jitter * 0.5;
+                }
+                positions.push(pos);
+            }
+        }
+    }
+    positions
+}
+
+fn spiral(
+    rng: &mut ChaCha8Rng,
+    count: usize,
+    center: Vec3,
+    turns: f32,
+    radius: f32,
+    height: f32,
+    jitter: f32,
+) -> Vec<Vec3> {
+    (0..count)
+        .map(|i| {
+            let t = if count > 1 {
+                i as f32 / (count - 1) as f32
+            } else {
+                0.
+            };
+            let angle = t * turns * 2. * PI;
+            let pos = Vec3::new(
+                angle.cos() * radius * t,
+                (t - 0.5) * height,
+                angle.sin() * radius * t,
+            );
+            let offset = if jitter > 0. {
+                random_in_unit_sphere(rng) * jitter
+            } else {
+                Vec3::ZERO
+            };
+            center + pos + offset
+        })
+        .collect()
+}
+
+/// Bridson's algorithm extended to three dimensions.
+fn poisson_disk(
+    rng: &mut ChaCha8Rng,
+    min: Vec3,
+    max: Vec3,
+    radius: f32,
+    attempts: u32,
+) -> Vec<Vec3> {
+    let size = max - min;
+    if radius <= 0. || size.min_element() <= 0. {
+        return vec![];
+    }
+
+    // A cell this size can hold at most one sample.
+    let cell = radius / 3_f32.sqrt();
+    let dims = (size / cell).ceil().as_uvec3().max(UVec3::ONE);
+    let mut cells: Vec<Option<usize>> = vec![None; volume(dims)];
+    let cell_of = |p: Vec3| ((p - min) / cell).as_uvec3().min(dims - UVec3::ONE);
+    let index_of =
+        |c: UVec3| c.x as usize + dims.x as usize * (c.y as usize + dims.y as usize * c.z as usize);
+
+    let mut samples = vec![random_in_box(rng, min, max)];
+    let mut active = vec![0];
+    cells[index_of(cell_of(samples[0]))] = Some(0);
+
+    while !active.is_empty() {
+        let slot = rng.gen_range(0..active.len());
+        let origin = samples[active[slot]];
+        let mut found = false;
+
+        for _ in 0..attempts {
+            let candidate = origin + random_unit_vector(rng) * rng.gen_range(radius..=2. * radius);
+            if candidate.cmplt(min).any() || candidate.cmpge(max).any() {
+                continue;
+            }
+
+            let c = cell_of(candidate).as_ivec3();
+            let lo = (c - IVec3::splat(2)).max(IVec3::ZERO);
+            let hi = (c + IVec3::splat(2)).min(dims.as_ivec3() - IVec3::ONE);
+            let mut too_close = false;
+            'search: for x in lo.x..=hi.x {
+                for y in lo.y..=hi.y {
+                    for z in lo.z..=hi.z {
+                        let neighbour = cells[index_of(IVec3::new(x, y, z).as_uvec3())];
+                        if let Some(n) = neighbour {
+                            if samples[n].distance_squared(candidate) < radius * radius {
+                                too_close = true;
+                                break 'search;
+                            }
+                        }
+                    }
+                }
+            }
+
+            if !too_close {
+                cells[index_of(c.as_uvec3())] = Some(samples.len());
+                active.push(samples.len());
+                samples.push(candidate);
+                found = true;
+                break;
+            }
+        }
+
+        if !found {
+            active.swap_remove(slot);
+        }
+    }
+    samples
+}
+
+/// Number of cells in a grid of `counts`, without overflowing `u32`.
+fn volume(counts: UVec3) -> usize {
+    counts.x as usize * counts.y as usize * counts.z as usize
+}
+
+fn random_in_box(rng: &mut ChaCha8Rng, min: Vec3, max: Vec3) -> Vec3 {
+    Vec3::new(rng.gen::<f32>(), rng.gen::<f32>(), rng.gen::<f32>()) * (max - min) + min
+}
+
+fn random_unit_vector(rng: &mut ChaCha8Rng) -> Vec3 {
+    let z: f32 = rng.gen_range(-1.0..=1.0);
+    let angle = rng.gen_range(0.0..2. * PI);
+    let r = (1. - z * z).sqrt();
+    Vec3::new(r * angle.cos(), r * angle.sin(), z)
+}
+
+fn random_in_unit_sphere(rng: &mut ChaCha8Rng) -> Vec3 {
+    random_unit_vector(rng) * rng.gen::<f32>().cbrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generators() -> Vec<Generator> {
+        vec![
+            Generator::Grid {
+                counts: UVec3::new(3, 4, 5),
+                spacing: Vec3::splat(2.),
+                center: Vec3::ONE,
+                jitter: 0.5,
+            },
+            Generator::ScatterBox {
+                count: 64,
+                min: -Vec3::ONE,
+                max: Vec3::ONE,
+            },
+            Generator::ScatterSphere {
+                count: 64,
+                center: Vec3::ZERO,
+                radius: 3.,
+            },
+            Generator::PoissonDisk {
+                min: Vec3::ZERO,
+                max: Vec3::splat(10.),
+                radius: 1.5,
+                attempts: 30,
+            },
+            Generator::Spiral {
+                count: 64,
+                center: Vec3::ZERO,
+                turns: 3.,
+                radius: 10.,
+                height: 5.,
+                jitter: 0.2,
+            },
+            Generator::SphereShell {
+                count: 64,
+                center: Vec3::ZERO,
+                radius: 5.,
+                thickness: 1.,
+            },
+        ]
+    }
+
+    #[test]
+    fn same_seed_gives_same_positions() {
+        for generator in generators() {
+            let positions = generator.generate(7);
+            assert!(!positions.is_empty());
+            assert_eq!(positions, generator.generate(7));
+            assert_ne!(positions, generator.generate(8));
+        }
+    }
+
+    #[test]
+    fn grid_has_a_position_per_cell() {
+        let grid = Generator::Grid {
+            counts: UVec3::new(3, 4, 5),
+            spacing: Vec3::ONE,
+            center: Vec3::ZERO,
+            jitter: 0.,
+        };
+        let positions = grid.generate(0);
+        assert_eq!(positions.len(), 3 * 4 * 5);
+        assert!(positions.contains(&Vec3::ZERO));
+        assert_eq!(Generator::grid(4, 1.).generate(0).len(), 64);
+    }
+
+    #[test]
+    fn scattered_points_stay_inside() {
+        let inside_box = Generator::ScatterBox {
+            count: 256,
+            min: Vec3::new(-1., 2., 3.),
+            max: Vec3::new(1., 4., 6.),
+        };
+        for p in inside_box.generate(1) {
+            assert!(p.cmpge(Vec3::new(-1., 2., 3.)).all() && p.cmple(Vec3::new(1., 4., 6.)).all());
+        }
+        let inside_sphere = Generator::ScatterSphere {
+            count: 256,
+            center: Vec3::X,
+            radius: 2.,
+        };
+        for p in inside_sphere.generate(1) {
+            assert!(p.distance(Vec3::X) <= 2. + 1e-4);
+        }
+    }
+
+    #[test]
+    fn poisson_points_keep_their_distance() {
+        let (min, max, radius) = (Vec3::ZERO, Vec3::new(20., 10., 15.), 2.);
+        let disk = Generator::PoissonDisk {
+            min,
+            max,
+            radius,
+            attempts: 30,
+        };
+        let points = disk.generate(3);
+        assert!(points.len() > 50);
+        for (i, a) in points.iter().enumerate() {
+            assert!(a.cmpge(min).all() && a.cmplt(max).all());
+            for b in &points[i + 1..] {
+                assert!(a.distance(*b) >= radius - 1e-4);
+            }
+        }
+    }
+
+    #[test]
+    fn poisson_disk_without_room_is_empty() {
+        let flat = Generator::PoissonDisk {
+            min: Vec3::ZERO,
+            max: Vec3::new(10., 0., 10.),
+            radius: 1.,
+            attempts: 30,
+        };
+        assert!(flat.generate(0).is_empty());
+    }
+
+    #[test]
+    fn shell_points_sit_at_the_radius() {
+        let shell = Generator::SphereShell {
+            count: 256,
+            center: Vec3::new(1., 2., 3.),
+            radius: 5.,
+            thickness: 0.,
+        };
+        for p in shell.generate(4) {
+            assert!((p.distance(Vec3::new(1., 2., 3.)) - 5.).abs() < 1e-4);
+        }
+        let thick = Generator::SphereShell {
+            count: 256,
+            center: Vec3::ZERO,
+            radius: 5.,
+            thickness: 2.,
+        };
+        for p in thick.generate(4) {
+            assert!((p.length() - 5.).abs() <= 1. + 1e-4);
+        }
+    }
+
+    #[test]
+    fn spiral_spans_its_height() {
+        let spiral = Generator::Spiral {
+            count: 11,
+            center: Vec3::ZERO,
+            turns: 2.,
+            radius: 4.,
+            height: 10.,
+            jitter: 0.,
+        };
+        let points = spiral.generate(0);
+        assert_eq!(points.len(), 11);
+        assert!(points[0].abs_diff_eq(Vec3::new(0., -5., 0.), 1e-4));
+        assert!(points[10].abs_diff_eq(Vec3::new(4., 5., 0.), 1e-3));
+    }
+}
@@ -0,0 +1,495 @@
+use std::{collections::VecDeque, mem::size_of};
+
+use crate::{
+    entity::{Entity, EntityId},
+    transform::Transform,
+};
+
+use super::Scene;
+
+/// A reversible scene mutation. Commands hold both the state before and
+/// after the change, so they can be applied and reverted any number of times.
+#[derive(Clone)]
+pub enum Command {
+    Spawn {
+        entity: Box<Entity>,
+        is_static: bool,
+    },
+    Despawn {
+        entity: Box<Entity>,
+        is_static: bool,
+        /// Children that were detached and get their parent back on undo.
+        children: Vec<EntityId>,
+    },
+    SetTransform {
+        id: EntityId,
+        before: Transform,
+        after: Transform,
+    },
+    /// Any change to the components of an entity, stored as snapshots.
+    Edit {
+        before: Box<Entity>,
+        after: Box<Entity>,
+    },
+    SetParent {
+        id: EntityId,
+        before: Option<EntityId>,
+        after: Option<EntityId>,
+    },
+}
+
+impl Command {
+    /// Records an entity that was already spawned by other means, e.g. from a
+    /// prefab, so that spawning it can be undone.
+    pub fn spawned(scene: &Scene, id: EntityId) -> Option<Command> {
+        Some(Command::Spawn {
+            entity: Box::new(scene.get_entity(id)?.clone()),
+            is_static: scene.is_static(id)?,
+        })
+    }
+
+    fn apply(&self, scene: &mut Scene) {
+        match self {
+            Command::Spawn { entity, is_static } => {
+                let parent = entity.get_parent();
+                let mut entity = entity.as_ref().clone();
+                entity.set_parent(None);
+                let id = entity.get_id();
+                scene.insert(entity, *is_static);
+                scene.set_parent(id, parent);
+            }
+            Command::Despawn { entity, .. } => {
+                scene.despawn(entity.get_id());
+            }
+            Command::SetTransform { id, after, .. } => set_transform(scene, *id, after),
+            Command::Edit { after, .. } => restore(scene, after),
+            Command::SetParent { id, after, .. } => {
+                scene.set_parent(*id, *after);
+            }
+        }
+    }
+
+    fn revert(&self, scene: &mut Scene) {
+        match self {
+            Command::Spawn { entity, .. } => {
+                scene.despawn(entity.get_id());
+            }
+            Command::Despawn {
+                entity,
+                is_static,
+                children,
+            } => {
+                Command::Spawn {
+                    entity: entity.clone(),
+                    is_static: *is_static,
+                }
+                .apply(scene);
+                for &child in children.iter() {
+                    scene.set_parent(child, Some(entity.get_id()));
+                }
+            }
+            Command::SetTransform { id, before, .. } => set_transform(scene, *id, before),
+            Command::Edit { before, .. } => restore(scene, before),
+            Command::SetParent { id, before, .. } => {
+                scene.set_parent(*id, *before);
+            }
+        }
+    }
+
+    /// Rough number of bytes kept alive by the command.
+    fn get_size(&self) -> usize {
+        let entity = |e: &Entity| size_of::<Entity>() + e.get_name().map_or(0, str::len);
+        size_of::<Command>()
+            + match self {
+                Command::Spawn { entity: e, .. } => entity(e),
+                Command::Despawn {
+                    entity: e,
+                    children,
+                    ..
+                } => entity(e) + children.len() * size_of::<EntityId>(),
+                Command::Edit { before, after } => entity(before) + entity(after),
+                Command::SetTransform { .. } | Command::SetParent { .. } => 0,
+            }
+    }
+}
+
+fn set_transform(scene: &mut Scene, id: EntityId, transform: &Transform) {
+    if let Some(entity) = scene.get_mut_entity(id) {
+        *entity.get_mut_transform() = *transform;
+    }
+}
+
+/// Overwrites an entity with a snapshot, keeping its current parent.
+fn restore(scene: &mut Scene, snapshot: &Entity) {
+    if let Some(entity) = scene.get_mut_entity(snapshot.get_id()) {
+        let parent = entity.get_parent();
+        *entity = snapshot.clone();
+        entity.set_parent(parent);
+    }
+}
+
+/// Undo and redo stacks of scene edits.
+///
+/// Edits made through the history are applied to the scene right away. Edits
+/// between [`History::begin_group`] and [`History::end_group`] are undone as
+/// one step. Once the recorded steps on both stacks need more than the
+/// memory limit, the oldest undo steps are forgotten first, then the redo
+/// steps furthest away.
+pub struct History {
+    undo: VecDeque<Vec<Command>>,
+    redo: Vec<Vec<Command>>,
+    group: Option<Vec<Command>>,
+    depth: u32,
+    /// Estimated bytes of the undo and redo steps.
+    memory: usize,
+    memory_limit: usize,
+}
+
+impl History {
+    pub fn new(memory_limit: usize) -> Self {
+        Self {
+            undo: VecDeque::new(),
+            redo: vec![],
+            group: None,
+            depth: 0,
+            memory: 0,
+            memory_limit,
+        }
+    }
+
+    pub fn spawn(&mut self, scene: &mut Scene, transform: Transform, is_static: bool) -> EntityId {
+        let id = scene.spawn(transform, is_static);
+        if let Some(command) = Command::spawned(scene, id) {
+            self.push(command);
+        }
+        id
+    }
+
+    /// Despawns `id`, returning false if it does not exist.
+    pub fn despawn(&mut self, scene: &mut Scene, id: EntityId) -> bool {
+        let Some(is_static) = scene.is_static(id) else {
+            return false;
+        };
+        let children = scene
+            .get_static_entities()
+            .iter()
+            .chain(scene.get_dynamic_entities())
+            .filter(|e| e.get_parent() == Some(id))
+            .map(Entity::get_id)
+            .collect();
+        let Some(entity) = scene.despawn(id) else {
+            return false;
+        };
+        self.push(Command::Despawn {
+            entity: Box::new(entity),
+            is_static,
+            children,
+        });
+        true
+    }
+
+    pub fn set_transform(&mut self, scene: &mut Scene, id: EntityId, transform: Transform) -> bool {
+        let Some(entity) = scene.get_mut_entity(id) else {
+            return false;
+        };
+        let before = *entity.get_transform();
+        *entity.get_mut_transform() = transform;
+        self.push(Command::SetTransform {
+            id,
+            before,
+            after: transform,
+        });
+        true
+    }
+
+    /// Changes the components of `id` with `edit`.
+    pub fn edit(
+        &mut self,
+        scene: &mut Scene,
+        id: EntityId,
+        edit: impl FnOnce(&mut Entity),
+    ) -> bool {
+        let Some(entity) = scene.get_mut_entity(id) else {
+            return false;
+        };
+        let before = Box::new(entity.clone());
+        edit(entity);
+        // The id and parent have their own commands.
+        entity.set_parent(before.get_parent());
+        let after = Box::new(entity.clone());
+        self.push(Command::Edit { before, after });
+        true
+    }
+
+    pub fn set_parent(
+        &mut self,
+        scene: &mut Scene,
+        id: EntityId,
+        parent: Option<EntityId>,
+    ) -> bool {
+        let Some(before) = scene.get_entity(id).map(Entity::get_parent) else {
+            return false;
+        };
+        if !scene.set_parent(id, parent) {
+            return false;
+        }
+        self.push(Command::SetParent {
+            id,
+            before,
+            after: parent,
+        });
+        true
+    }
+
+    /// Starts collecting edits into one undo step. Groups can be nested, the
+    /// step is recorded when the outermost group ends.
+    pub fn begin_group(&mut self) {
+        self.depth += 1;
+        self.group.get_or_insert_with(Vec::new);
+    }
+
+    pub fn end_group(&mut self) {
+        self.depth = self.depth.saturating_sub(1);
+        if self.depth == 0 {
+            if let Some(group) = self.group.take().filter(|g| !g.is_empty()) {
+                self.push_step(group);
+            }
+        }
+    }
+
+    /// Records a command that was already applied to the scene.
+    pub fn push(&mut self, command: Command) {
+        match self.group.as_mut() {
+            Some(group) => group.push(command),
+            None => self.push_step(vec![command]),
+        }
+    }
+
+    fn push_step(&mut self, step: Vec<Command>) {
+        self.memory -= self.redo.iter().map(|s| step_size(s)).sum::<usize>();
+        self.redo.clear();
+        self.memory += step_size(&step);
+        self.undo.push_back(step);
+        self.trim();
+    }
+
+    /// Forgets steps until the rest fit in the memory limit.
+    fn trim(&mut self) {
+        while self.memory > self.memory_limit {
+            let oldest = match self.undo.pop_front() {
+                Some(step) => step,
+                None if !self.redo.is_empty() => self.redo.remove(0),
+                None => break,
+            };
+            self.memory -= step_size(&oldest);
+        }
+    }
+
+    /// Reverts the last step. Returns false if there is nothing to undo.
+    pub fn undo(&mut self, scene: &mut Scene) -> bool {
+        let Some(step) = self.undo.pop_back() else {
+            return false;
+        };
+        for command in step.iter().rev() {
+            command.revert(scene);
+        }
+        self.redo.push(step);
+        self.trim();
+        true
+    }
+
+    /// Applies the last undone step again.
+    pub fn redo(&mut self, scene: &mut Scene) -> bool {
+        let Some(step) = self.redo.pop() else {
+            return false;
+        };
+        for command in step.iter() {
+            command.apply(scene);
+        }
+        self.undo.push_back(step);
+        self.trim();
+        true
+    }
+
+    #[inline]
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    #[inline]
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// Estimated bytes used by the undo and redo steps.
+    #[inline]
+    pub fn get_memory_usage(&self) -> usize {
+        self.memory
+    }
+}
+
+fn step_size(step: &[Command]) -> usize {
+    step.iter().map(Command::get_size).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Vec3, Vec4};
+
+    use super::*;
+
+    fn transform(x: f32) -> Transform {
+        Transform::with_pos(Vec3::new(x, 0., 0.))
+    }
+
+    fn position(scene: &Scene, id: EntityId) -> Vec3 {
+        scene.get_entity(id).unwrap().get_transform().get_position()
+    }
+
+    #[test]
+    fn undoes_and_redoes_spawn() {
+        let mut scene = Scene::new();
+        let mut history = History::new(usize::MAX);
+        let id = history.spawn(&mut scene, transform(1.), true);
+        assert!(history.undo(&mut scene));
+        assert!(scene.get_entity(id).is_none());
+        assert!(history.redo(&mut scene));
+        assert_eq!(scene.is_static(id), Some(true));
+        assert_eq!(position(&scene, id), Vec3::X);
+    }
+
+    #[test]
+    fn undoes_despawn_with_children() {
+        let mut scene = Scene::new();
+        let mut history = History::new(usize::MAX);
+        let parent = scene.spawn(transform(1.), false);
+        let child = scene.spawn(transform(2.), false);
+        scene.set_parent(child, Some(parent));
+        assert!(history.despawn(&mut scene, parent));
+        assert!(scene.get_entity(parent).is_none());
+        assert_eq!(scene.get_entity(child).unwrap().get_parent(), None);
+
+        assert!(history.undo(&mut scene));
+        assert_eq!(position(&scene, parent), Vec3::X);
+        assert_eq!(scene.get_entity(child).unwrap().get_parent(), Some(parent));
+        assert!(history.redo(&mut scene));
+        assert!(scene.get_entity(parent).is_none());
+        assert!(!history.despawn(&mut scene, parent));
+    }
+
+    #[test]
+    fn undoes_and_redoes_transform() {
+        let mut scene = Scene::new();
+        let mut history = History::new(usize::MAX);
+        let id = scene.spawn(transform(1.), false);
+        assert!(history.set_transform(&mut scene, id, transform(5.)));
+        assert_eq!(position(&scene, id), Vec3::new(5., 0., 0.));
+        history.undo(&mut scene);
+        assert_eq!(position(&scene, id), Vec3::X);
+        history.redo(&mut scene);
+        assert_eq!(position(&scene, id), Vec3::new(5., 0., 0.));
+    }
+
+    #[test]
+    fn undoes_and_redoes_edit() {
+        let mut scene = Scene::new();
+        let mut history = History::new(usize::MAX);
+        let parent = scene.spawn(transform(0.), false);
+        let id = scene.spawn(transform(1.), false);
+        let red = Vec4::new(1., 0., 0., 1.);
+        let before = scene.get_entity(id).unwrap().get_color();
+        assert!(history.edit(&mut scene, id, |e| {
+            e.set_color(red);
+            e.set_name(Some("red".to_owned()));
+        }));
+        // Parenting after the edit survives undoing and redoing it.
+        scene.set_parent(id, Some(parent));
+
+        history.undo(&mut scene);
+        let entity = scene.get_entity(id).unwrap();
+        assert_eq!(entity.get_color(), before);
+        assert_eq!(entity.get_name(), None);
+        assert_eq!(entity.get_parent(), Some(parent));
+        history.redo(&mut scene);
+        let entity = scene.get_entity(id).unwrap();
+        assert_eq!(entity.get_color(), red);
+        assert_eq!(entity.get_name(), Some("red"));
+        assert_eq!(entity.get_parent(), Some(parent));
+    }
+
+    #[test]
+    fn undoes_and_redoes_reparenting() {
+        let mut scene = Scene::new();
+        let mut history = History::new(usize::MAX);
+        let parent = scene.spawn(transform(0.), false);
+        let child = scene.spawn(transform(1.), false);
+        assert!(history.set_parent(&mut scene, child, Some(parent)));
+        // Cycles are refused and not recorded.
+        assert!(!history.set_parent(&mut scene, parent, Some(child)));
+        history.undo(&mut scene);
+        assert_eq!(scene.get_entity(child).unwrap().get_parent(), None);
+        assert!(!history.can_undo());
+        history.redo(&mut scene);
+        assert_eq!(scene.get_entity(child).unwrap().get_parent(), Some(parent));
+    }
+
+    #[test]
+    fn nested_groups_undo_as_one_step() {
+        let mut scene = Scene::new();
+        let mut history = History::new(usize::MAX);
+        let parent = scene.spawn(transform(0.), false);
+        history.begin_group();
+        let id = history.spawn(&mut scene, transform(1.), false);
+        history.begin_group();
+        history.set_transform(&mut scene, id, transform(2.));
+        history.set_parent(&mut scene, id, Some(parent));
+        history.end_group();
+        assert!(!history.can_undo());
+        history.edit(&mut scene, id, |e| e.set_color(Vec4::ZERO));
+        history.end_group();
+
+        assert!(history.undo(&mut scene));
+        assert!(scene.get_entity(id).is_none());
+        assert!(!history.can_undo());
+        assert!(history.redo(&mut scene));
+        let entity = scene.get_entity(id).unwrap();
+        assert_eq!(entity.get_transform().get_position(), Vec3::new(2., 0., 0.));
+        assert_eq!(entity.get_parent(), Some(parent));
+        assert_eq!(entity.get_color(), Vec4::ZERO);
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn new_edits_clear_redo() {
+        let mut scene = Scene::new();
+        let mut history = History::new(usize::MAX);
+        let id = scene.spawn(transform(0.), false);
+        history.set_transform(&mut scene, id, transform(1.));
+        history.undo(&mut scene);
+        assert!(history.can_redo());
+        let undone = history.get_memory_usage();
+        history.set_transform(&mut scene, id, transform(2.));
+        assert!(!history.can_redo());
+        assert_eq!(history.get_memory_usage(), undone);
+    }
+
+    #[test]
+    fn forgets_oldest_steps_over_the_memory_limit() {
+        let mut scene = Scene::new();
+        let step = size_of::<Command>();
+        let mut history = History::new(3 * step);
+        let id = scene.spawn(transform(0.), false);
+        for x in 1..=5 {
+            history.set_transform(&mut scene, id, transform(x as f32));
+            assert!(history.get_memory_usage() <= 3 * step);
+        }
+        assert_eq!(history.get_memory_usage(), 3 * step);
+        while history.undo(&mut scene) {}
+        assert_eq!(position(&scene, id), Vec3::new(2., 0., 0.));
+        // Undone steps still count until they are redone or replaced.
+        assert_eq!(history.get_memory_usage(), 3 * step);
+        while history.redo(&mut scene) {}
+        assert_eq!(position(&scene, id), Vec3::new(5., 0., 0.));
+        assert_eq!(history.get_memory_usage(), 3 * step);
+    }
+}
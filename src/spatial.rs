@@ -0,0 +1,200 @@
+use crate::{
+    bounds::{Aabb, Frustum, Ray, Sphere},
+    entity::EntityId,
+};
+
+pub use self::{bvh::Bvh, grid::UniformGrid};
+
+mod bvh;
+mod grid;
+
+/// Stores entity bounds and answers overlap queries against them.
+///
+/// Implementations only return entities whose stored bounds actually pass
+/// the test, not just candidates from the same cell or node.
+pub trait SpatialIndex {
+    /// Replaces the whole content of the index.
+    fn rebuild(&mut self, items: &[(EntityId, Aabb)]);
+    fn insert(&mut self, id: EntityId, aabb: Aabb);
+    /// Moves an entity that is already in the index.
+    fn update(&mut self, id: EntityId, aabb: Aabb);
+    fn remove(&mut self, id: EntityId);
+    fn len(&self) -> usize;
+
+    /// Gives the index a chance to restructure itself after many changes.
+    fn rebalance(&mut self) {}
+
+    fn query_aabb(&self, aabb: &Aabb, out: &mut Vec<EntityId>);
+    fn query_sphere(&self, sphere: &Sphere, out: &mut Vec<EntityId>);
+    fn query_frustum(&self, frustum: &Frustum, out: &mut Vec<EntityId>);
+    /// Every entity hit by `ray` within `max_distance`, with the entry distance.
+    fn query_ray(&self, ray: &Ray, max_distance: f32, out: &mut Vec<(EntityId, f32)>);
+}
+
+/// Which structure the scene uses for its dynamic entities.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum IndexKind {
+    /// Loose uniform grid, cheap to update when many entities move.
+    Grid { cell_size: u32 },
+    /// Bounding volume hierarchy refitted on updates, faster to query.
+    Bvh,
+}
+
+impl IndexKind {
+    pub fn create(self) -> Box<dyn SpatialIndex> {
+        match self {
+            IndexKind::Grid { cell_size } => Box::new(UniformGrid::new(cell_size as f32)),
+            IndexKind::Bvh => Box::new(Bvh::new()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{Mat4, Vec3};
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use super::*;
+
+    fn random_boxes(rng: &mut ChaCha8Rng, count: u32) -> Vec<(EntityId, Aabb)> {
+        (0..count)
+            .map(|i| {
+                let center = Vec3::new(
+                    rng.gen_range(-50.0..50.),
+                    rng.gen_range(-50.0..50.),
+                    rng.gen_range(-50.0..50.),
+                );
+                let half = Vec3::new(
+                    rng.gen_range(0.1..4.),
+                    rng.gen_range(0.1..4.),
+                    rng.gen_range(0.1..4.),
+                );
+                (EntityId(i), Aabb::from_center_half_extents(center, half))
+            })
+            .collect()
+    }
+
+    fn indices() -> Vec<Box<dyn SpatialIndex>> {
+        vec![
+            IndexKind::Grid { cell_size: 8 }.create(),
+            IndexKind::Bvh.create(),
+        ]
+    }
+
+    fn sorted(mut ids: Vec<EntityId>) -> Vec<EntityId> {
+        ids.sort_by_key(|id| id.0);
+        ids
+    }
+
+    /// Compares every query of `index` with testing all of `items`.
+    fn check(index: &dyn SpatialIndex, items: &[(EntityId, Aabb)], rng: &mut ChaCha8Rng) {
+        assert_eq!(index.len(), items.len());
+        let brute = |test: &dyn Fn(&Aabb) -> bool| {
+            sorted(
+                items
+                    .iter()
+                    .filter(|(_, b)| test(b))
+                    .map(|(id, _)| *id)
+                    .collect(),
+            )
+        };
+        for _ in 0..20 {
+            let center = Vec3::new(
+                rng.gen_range(-60.0..60.),
+                rng.gen_range(-60.0..60.),
+                rng.gen_range(-60.0..60.),
+            );
+
+            let area = Aabb::from_center_half_extents(center, Vec3::splat(rng.gen_range(1.0..20.)));
+            let mut out = vec![];
+            index.query_aabb(&area, &mut out);
+            assert_eq!(sorted(out), brute(&|b| b.intersects(&area)));
+
+            let sphere = Sphere {
+                center,
+                radius: rng.gen_range(1.0..20.),
+            };
+            let mut out = vec![];
+            index.query_sphere(&sphere, &mut out);
+            assert_eq!(sorted(out), brute(&|b| b.intersects_sphere(&sphere)));
+
+            let direction = Vec3::new(rng.gen(), rng.gen(), rng.gen()) - 0.5;
+            let ray = Ray::new(center, direction);
+            let mut hits = vec![];
+            index.query_ray(&ray, 40., &mut hits);
+            hits.sort_by_key(|(id, _)| id.0);
+            let expected: Vec<_> = items
+                .iter()
+                .filter_map(|(id, b)| Some((*id, b.intersects_ray(&ray).filter(|&t| t <= 40.)?)))
+                .collect();
+            assert_eq!(hits, expected);
+
+            let view = Mat4::look_at_rh(center, center + direction, Vec3::Y);
+            let projection = Mat4::perspective_rh(1., 1.5, 0.1, 50.);
+            let frustum = Frustum::from_matrix(&(projection * view));
+            let mut out = vec![];
+            index.query_frustum(&frustum, &mut out);
+            assert_eq!(sorted(out), brute(&|b| frustum.intersects_aabb(b)));
+        }
+    }
+
+    #[test]
+    fn queries_match_brute_force() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let items = random_boxes(&mut rng, 500);
+        for mut index in indices() {
+            index.rebuild(&items);
+            check(index.as_ref(), &items, &mut rng);
+        }
+    }
+
+    #[test]
+    fn queries_follow_updates() {
+        let mut rng = ChaCha8Rng::seed_from_u64(2);
+        for mut index in indices() {
+            let mut items = random_boxes(&mut rng, 300);
+            index.rebuild(&items[..200]);
+            // Inserted entities are found before and after rebalancing.
+            for (id, aabb) in items[200..].iter() {
+                index.insert(*id, *aabb);
+            }
+            check(index.as_ref(), &items, &mut rng);
+
+            let moved = random_boxes(&mut rng, 100);
+            for (item, (_, aabb)) in items.iter_mut().zip(moved) {
+                item.1 = aabb;
+                index.update(item.0, aabb);
+            }
+            for (id, _) in items.drain(250..) {
+                index.remove(id);
+            }
+            check(index.as_ref(), &items, &mut rng);
+            index.rebalance();
+            check(index.as_ref(), &items, &mut rng);
+
+            index.rebuild(&items[..10]);
+            check(index.as_ref(), &items[..10], &mut rng);
+        }
+    }
+
+    #[test]
+    fn huge_queries_do_not_overflow() {
+        let everything = Aabb::new(Vec3::splat(f32::MIN), Vec3::splat(f32::MAX));
+        let mut rng = ChaCha8Rng::seed_from_u64(3);
+        let items = random_boxes(&mut rng, 50);
+        for mut index in indices() {
+            index.rebuild(&items);
+            let mut out = vec![];
+            index.query_aabb(&everything, &mut out);
+            assert_eq!(out.len(), items.len());
+            let mut out = vec![];
+            let sphere = Sphere {
+                center: Vec3::ZERO,
+                radius: 1e30,
+            };
+            index.query_sphere(&sphere, &mut out);
+            assert_eq!(out.len(), items.len());
+        }
+    }
+}
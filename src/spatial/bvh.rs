@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+
+use crate::{
+    bounds::{Aabb, Frustum, Ray, Sphere},
+    entity::EntityId,
+};
+
+use super::SpatialIndex;
+
+const LEAF_SIZE: usize = 4;
+const NONE: u32 = u32::MAX;
+
+struct Node {
+    aabb: Aabb,
+    parent: u32,
+    /// First child for inner nodes, first entry of `order` for leaves.
+    start: u32,
+    /// Number of items for leaves, zero for inner nodes.
+    count: u32,
+}
+
+/// Bounding volume hierarchy built by median splits along the longest axis.
+///
+/// Moving an entity refits the boxes on the path to the root, which keeps
+/// queries correct but slowly degrades the tree. Inserted entities are kept
+/// in a side list until the next [`SpatialIndex::rebalance`], which rebuilds
+/// the tree once enough changes have piled up.
+pub struct Bvh {
+    nodes: Vec<Node>,
+    /// `None` marks an entity removed since the last build.
+    items: Vec<Option<(EntityId, Aabb)>>,
+    order: Vec<u32>,
+    leaf_of: Vec<u32>,
+    slots: HashMap<EntityId, usize>,
+    pending: Vec<usize>,
+    removed: usize,
+}
+
+impl Bvh {
+    pub fn new() -> Self {
+        Self {
+            nodes: vec![],
+            items: vec![],
+            order: vec![],
+            leaf_of: vec![],
+            slots: HashMap::new(),
+            pending: vec![],
+            removed: 0,
+        }
+    }
+
+    fn build(&mut self) {
+        let live: Vec<_> = self.items.iter().flatten().copied().collect();
+        self.items = live.into_iter().map(Some).collect();
+        self.slots = self
+            .items
+            .iter()
+            .enumerate()
+            .map(|(i, item)| (item.unwrap().0, i))
+            .collect();
+        self.order = (0..self.items.len() as u32).collect();
+        self.leaf_of = vec![NONE; self.items.len()];
+        self.nodes.clear();
+        self.pending.clear();
+        self.removed = 0;
+        if !self.items.is_empty() {
+            let count = self.items.len();
+            self.nodes.push(Node {
+                aabb: self.bounds_of(0, count),
+                parent: NONE,
+                start: 0,
+                count: count as u32,
+            });
+            self.split(0, 0, count);
+        }
+    }
+
+    /// Turns the leaf `index` into an inner node if it holds too many items.
+    /// Both children are allocated next to each other.
+    fn split(&mut self, index: u32, start: usize, end: usize) {
+        if end - start <= LEAF_SIZE {
+            for &item in self.order[start..end].iter() {
+                self.leaf_of[item as usize] = index;
+            }
+            return;
+        }
+
+        let centers = self.order[start..end]
+            .iter()
+            .map(|&i| self.get_aabb(i).get_center())
+            .fold(EMPTY, |b, c| Aabb::new(b.min.min(c), b.max.max(c)));
+        let size = centers.max - centers.min;
+        let axis = if size.x >= size.y && size.x >= size.z {
+            0
+        } else if size.y >= size.z {
+            1
+        } else {
+            2
+        };
+
+        let mid = start + (end - start) / 2;
+        let items = &self.items;
+        self.order[start..end].select_nth_unstable_by(mid - start, |&a, &b| {
+            let a = items[a as usize].map_or(0., |(_, aabb)| aabb.get_center()[axis]);
+            let b = items[b as usize].map_or(0., |(_, aabb)| aabb.get_center()[axis]);
+            a.total_cmp(&b)
+        });
+
+        let left = self.nodes.len() as u32;
+        for (s, e) in [(start, mid), (mid, end)] {
+            self.nodes.push(Node {
+                aabb: self.bounds_of(s, e),
+                parent: index,
+                start: s as u32,
+                count: (e - s) as u32,
+            });
+        }
+        let node = &mut self.nodes[index as usize];
+        node.start = left;
+        node.count = 0;
+
+        self.split(left, start, mid);
+        self.split(left + 1, mid, end);
+    }
+
+    #[inline]
+    fn get_aabb(&self, item: u32) -> Aabb {
+        self.items[item as usize].map_or(EMPTY, |(_, aabb)| aabb)
+    }
+
+    fn bounds_of(&self, start: usize, end: usize) -> Aabb {
+        self.order[start..end]
+            .iter()
+            .fold(EMPTY, |b, &i| b.union(&self.get_aabb(i)))
+    }
+
+    fn refit(&mut self, mut node: u32) {
+        while node != NONE {
+            let n = &self.nodes[node as usize];
+            let aabb = if n.count > 0 {
+                self.bounds_of(n.start as usize, (n.start + n.count) as usize)
+            } else {
+                let left = &self.nodes[n.start as usize];
+                let right = &self.nodes[n.start as usize + 1];
+                left.aabb.union(&right.aabb)
+            };
+            let n = &mut self.nodes[node as usize];
+            n.aabb = aabb;
+            node = n.parent;
+        }
+    }
+
+    fn visit(
+        &self,
+        mut node_test: impl FnMut(&Aabb) -> bool,
+        mut visit: impl FnMut(EntityId, &Aabb),
+    ) {
+        let mut stack = vec![];
+        if !self.nodes.is_empty() {
+            stack.push(0);
+        }
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index as usize];
+            if !node_test(&node.aabb) {
+                continue;
+            }
+            if node.count == 0 {
+                stack.push(node.start);
+                stack.push(node.start + 1);
+                continue;
+            }
+            for &item in self.order[node.start as usize..(node.start + node.count) as usize].iter()
+            {
+                if let Some((id, aabb)) = &self.items[item as usize] {
+                    visit(*id, aabb);
+                }
+            }
+        }
+        for &item in self.pending.iter() {
+            if let Some((id, aabb)) = &self.items[item] {
+                visit(*id, aabb);
+            }
+        }
+    }
+}
+
+const EMPTY: Aabb = Aabb {
+    min: glam::Vec3::splat(f32::MAX),
+    max: glam::Vec3::splat(f32::MIN),
+};
+
+impl SpatialIndex for Bvh {
+    fn rebuild(&mut self, items: &[(EntityId, Aabb)]) {
+        self.items = items.iter().copied().map(Some).collect();
+        self.build();
+    }
+
+    fn insert(&mut self, id: EntityId, aabb: Aabb) {
+        if self.slots.contains_key(&id) {
+            return self.update(id, aabb);
+        }
+        self.slots.insert(id, self.items.len());
+        self.pending.push(self.items.len());
+        self.items.push(Some((id, aabb)));
+        self.leaf_of.push(NONE);
+    }
+
+    fn update(&mut self, id: EntityId, aabb: Aabb) {
+        let Some(&slot) = self.slots.get(&id) else {
+            return self.insert(id, aabb);
+        };
+        self.items[slot] = Some((id, aabb));
+        let leaf = self.leaf_of[slot];
+        if leaf != NONE {
+            self.refit(leaf);
+        }
+    }
+
+    fn remove(&mut self, id: EntityId) {
+        if let Some(slot) = self.slots.remove(&id) {
+            self.items[slot] = None;
+            self.removed += 1;
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    /// Rebuilds the tree once inserts and removals since the last build make
+    /// up more than a quarter of its content.
+    fn rebalance(&mut self) {
+        if (self.pending.len() + self.removed) * 4 > self.items.len() {
+            self.build();
+        }
+    }
+
+    fn query_aabb(&self, aabb: &Aabb, out: &mut Vec<EntityId>) {
+        self.visit(
+            |node| node.intersects(aabb),
+            |id, bounds| {
+                if bounds.intersects(aabb) {
+                    out.push(id);
+                }
+            },
+        );
+    }
+
+    fn query_sphere(&self, sphere: &Sphere, out: &mut Vec<EntityId>) {
+        self.visit(
+            |node| node.intersects_sphere(sphere),
+            |id, bounds| {
+                if bounds.intersects_sphere(sphere) {
+                    out.push(id);
+                }
+            },
+        );
+    }
+
+    fn query_frustum(&self, frustum: &Frustum, out: &mut Vec<EntityId>) {
+        self.visit(
+            |node| frustum.intersects_aabb(node),
+            |id, bounds| {
+                if frustum.intersects_aabb(bounds) {
+                    out.push(id);
+                }
+            },
+        );
+    }
+
+    fn query_ray(&self, ray: &Ray, max_distance: f32, out: &mut Vec<(EntityId, f32)>) {
+        let within = |aabb: &Aabb| aabb.intersects_ray(ray).filter(|&t| t <= max_distance);
+        self.visit(
+            |node| within(node).is_some(),
+            |id, bounds| {
+                if let Some(t) = within(bounds) {
+                    out.push((id, t));
+                }
+            },
+        );
+    }
+}
@@ -0,0 +1,201 @@
+use std::collections::HashMap;
+
+use glam::{IVec3, Vec3};
+
+use crate::{
+    bounds::{Aabb, Frustum, Ray, Sphere},
+    entity::EntityId,
+};
+
+use super::SpatialIndex;
+
+/// Loose uniform grid: every entity lives in the single cell containing the
+/// center of its bounds, and queries are widened by the largest half extent
+/// stored. Moving an entity therefore touches at most two cells. The largest
+/// extent only grows on changes and shrinks again on
+/// [`SpatialIndex::rebalance`].
+pub struct UniformGrid {
+    cell_size: f32,
+    cells: HashMap<IVec3, Vec<EntityId>>,
+    entries: HashMap<EntityId, (IVec3, Aabb)>,
+    max_half_extent: Vec3,
+}
+
+impl UniformGrid {
+    pub fn new(cell_size: f32) -> Self {
+        Self {
+            cell_size,
+            cells: HashMap::new(),
+            entries: HashMap::new(),
+            max_half_extent: Vec3::ZERO,
+        }
+    }
+
+    #[inline]
+    fn cell_of(&self, point: Vec3) -> IVec3 {
+        (point / self.cell_size).floor().as_ivec3()
+    }
+
+    /// Bounds of everything that may be stored in `cell`.
+    fn loose_bounds(&self, cell: IVec3) -> Aabb {
+        let min = cell.as_vec3() * self.cell_size;
+        Aabb::new(
+            min - self.max_half_extent,
+            min + Vec3::splat(self.cell_size) + self.max_half_extent,
+        )
+    }
+
+    fn remove_from_cell(&mut self, cell: IVec3, id: EntityId) {
+        if let Some(list) = self.cells.get_mut(&cell) {
+            if let Some(i) = list.iter().position(|&e| e == id) {
+                list.swap_remove(i);
+            }
+            if list.is_empty() {
+                self.cells.remove(&cell);
+            }
+        }
+    }
+
+    fn visit_range(&self, area: &Aabb, mut visit: impl FnMut(EntityId, &Aabb)) {
+        let min = self.cell_of(area.min - self.max_half_extent);
+        let max = self.cell_of(area.max + self.max_half_extent);
+        // Cells are counted in i64, huge areas would overflow i32.
+        let cells = (0..3)
+            .map(|axis| (max[axis] as i64 - min[axis] as i64 + 1).max(0))
+            .fold(1_i64, i64::saturating_mul);
+
+        // Huge areas are cheaper to answer by walking the occupied cells.
+        if cells > self.cells.len() as i64 {
+            for (cell, list) in self.cells.iter() {
+                if cell.cmpge(min).all() && cell.cmple(max).all() {
+                    for id in list {
+                        visit(*id, &self.entries[id].1);
+                    }
+                }
+            }
+            return;
+        }
+
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                for z in min.z..=max.z {
+                    if let Some(list) = self.cells.get(&IVec3::new(x, y, z)) {
+                        for id in list {
+                            visit(*id, &self.entries[id].1);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl SpatialIndex for UniformGrid {
+    fn rebuild(&mut self, items: &[(EntityId, Aabb)]) {
+        self.cells.clear();
+        self.entries.clear();
+        self.max_half_extent = Vec3::ZERO;
+        for (id, aabb) in items {
+            self.insert(*id, *aabb);
+        }
+    }
+
+    fn insert(&mut self, id: EntityId, aabb: Aabb) {
+        let cell = self.cell_of(aabb.get_center());
+        self.max_half_extent = self.max_half_extent.max(aabb.get_half_extents());
+        if let Some((old, _)) = self.entries.insert(id, (cell, aabb)) {
+            self.remove_from_cell(old, id);
+        }
+        self.cells.entry(cell).or_default().push(id);
+    }
+
+    fn update(&mut self, id: EntityId, aabb: Aabb) {
+        let cell = self.cell_of(aabb.get_center());
+        match self.entries.get_mut(&id) {
+            Some(entry) if entry.0 == cell => {
+                entry.1 = aabb;
+                self.max_half_extent = self.max_half_extent.max(aabb.get_half_extents());
+            }
+            _ => self.insert(id, aabb),
+        }
+    }
+
+    fn remove(&mut self, id: EntityId) {
+        if let Some((cell, _)) = self.entries.remove(&id) {
+            self.remove_from_cell(cell, id);
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Shrinks the query margin to the largest entity still stored.
+    fn rebalance(&mut self) {
+        self.max_half_extent = self.entries.values().fold(Vec3::ZERO, |max, (_, aabb)| {
+            max.max(aabb.get_half_extents())
+        });
+    }
+
+    fn query_aabb(&self, aabb: &Aabb, out: &mut Vec<EntityId>) {
+        self.visit_range(aabb, |id, bounds| {
+            if bounds.intersects(aabb) {
+                out.push(id);
+            }
+        });
+    }
+
+    fn query_sphere(&self, sphere: &Sphere, out: &mut Vec<EntityId>) {
+        let area = Aabb::from_center_half_extents(sphere.center, Vec3::splat(sphere.radius));
+        self.visit_range(&area, |id, bounds| {
+            if bounds.intersects_sphere(sphere) {
+                out.push(id);
+            }
+        });
+    }
+
+    fn query_frustum(&self, frustum: &Frustum, out: &mut Vec<EntityId>) {
+        for (cell, list) in self.cells.iter() {
+            if !frustum.intersects_aabb(&self.loose_bounds(*cell)) {
+                continue;
+            }
+            out.extend(
+                list.iter()
+                    .filter(|id| frustum.intersects_aabb(&self.entries[id].1)),
+            );
+        }
+    }
+
+    fn query_ray(&self, ray: &Ray, max_distance: f32, out: &mut Vec<(EntityId, f32)>) {
+        for (cell, list) in self.cells.iter() {
+            match self.loose_bounds(*cell).intersects_ray(ray) {
+                Some(t) if t <= max_distance => {}
+                _ => continue,
+            }
+            for id in list {
+                if let Some(t) = self.entries[id].1.intersects_ray(ray) {
+                    if t <= max_distance {
+                        out.push((*id, t));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rebalance_forgets_removed_extents() {
+        let mut grid = UniformGrid::new(1.);
+        let huge = Aabb::from_center_half_extents(Vec3::ZERO, Vec3::splat(1000.));
+        grid.insert(EntityId(0), huge);
+        grid.insert(EntityId(1), Aabb::UNIT);
+        grid.remove(EntityId(0));
+        assert_eq!(grid.max_half_extent, Vec3::splat(1000.));
+        grid.rebalance();
+        assert_eq!(grid.max_half_extent, Vec3::splat(0.5));
+    }
+}
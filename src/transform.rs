@@ -1,34 +1,10 @@
 use glam::{Mat4, Quat, Vec3};
 
+#[derive(Clone, Copy, PartialEq)]
 pub struct Transform {
     position: Vec3,
     scale: Vec3,
-    rotation: Rotation,
-}
-
-struct Rotation {
-    direction: Vec3,
-    angle: f32,
-}
-
-impl Default for Rotation {
-    fn default() -> Self {
-        Self {
-            direction: Vec3 {
-                x: 0.,
-                y: 0.,
-                z: -1.,
-            },
-            angle: 0.,
-        }
-    }
-}
-
-impl Rotation {
-    #[inline]
-    pub fn get_rotation(&self) -> Quat {
-        Quat::from_axis_angle(self.direction, self.angle)
-    }
+    rotation: Quat,
 }
 
 impl Default for Transform {
@@ -36,12 +12,20 @@ impl Default for Transform {
         Self {
             position: Vec3::ZERO,
             scale: Vec3::ONE,
-            rotation: Rotation::default(),
+            rotation: Quat::IDENTITY,
         }
     }
 }
 
 impl Transform {
+    pub fn new(position: Vec3, rotation: Quat, scale: Vec3) -> Self {
+        Self {
+            position,
+            scale,
+            rotation,
+        }
+    }
+
     pub fn update_pos(&mut self, dif: Vec3) {
         self.position += dif;
     }
@@ -49,17 +33,42 @@ impl Transform {
     pub fn with_pos(position: Vec3) -> Self {
         Self {
             position,
-            scale: Vec3::ONE,
-            rotation: Rotation::default(),
+            ..Default::default()
         }
     }
 
+    #[inline]
+    pub fn get_position(&self) -> Vec3 {
+        self.position
+    }
+
+    #[inline]
+    pub fn set_position(&mut self, position: Vec3) {
+        self.position = position;
+    }
+
+    #[inline]
+    pub fn set_rotation(&mut self, rotation: Quat) {
+        self.rotation = rotation;
+    }
+
+    #[inline]
+    pub fn get_rotation(&self) -> Quat {
+        self.rotation
+    }
+
+    #[inline]
+    pub fn get_scale(&self) -> Vec3 {
+        self.scale
+    }
+
+    #[inline]
+    pub fn set_scale(&mut self, scale: Vec3) {
+        self.scale = scale;
+    }
+
     #[inline]
     pub fn get_matrix(&self) -> Mat4 {
-        Mat4::from_scale_rotation_translation(
-            self.scale,
-            self.rotation.get_rotation(),
-            self.position,
-        )
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.position)
     }
 }